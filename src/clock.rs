@@ -0,0 +1,112 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::TaskError;
+
+/// Source of the current time, so time-dependent behaviour can be tested with a fixed instant
+pub trait Clock: fmt::Debug + Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The real system clock, in UTC
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that always reports the same instant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+/// How `created_at`/`updated_at` stamps are written
+///
+/// Every variant is RFC 3339 in UTC, so stamps stay readable by older builds; they differ
+/// only in the precision of the fractional seconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampFormat {
+    /// `2024-01-15T09:00:00Z`
+    #[default]
+    Rfc3339,
+    /// `2024-01-15T09:00:00.123Z`
+    Rfc3339Millis,
+    /// `2024-01-15T09:00:00.123456Z`
+    Rfc3339Micros,
+}
+
+impl TimestampFormat {
+    /// Render an instant in this format
+    pub fn format(&self, instant: DateTime<Utc>) -> String {
+        let precision = match self {
+            TimestampFormat::Rfc3339 => SecondsFormat::Secs,
+            TimestampFormat::Rfc3339Millis => SecondsFormat::Millis,
+            TimestampFormat::Rfc3339Micros => SecondsFormat::Micros,
+        };
+        instant.to_rfc3339_opts(precision, true)
+    }
+}
+
+impl FromStr for TimestampFormat {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rfc3339" => Ok(TimestampFormat::Rfc3339),
+            "rfc3339_millis" => Ok(TimestampFormat::Rfc3339Millis),
+            "rfc3339_micros" => Ok(TimestampFormat::Rfc3339Micros),
+            other => Err(TaskError::Validation(format!(
+                "Invalid timestamp format: {} (expected rfc3339, rfc3339_millis or rfc3339_micros)",
+                other
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_timestamp_formats() {
+        let instant = Utc.with_ymd_and_hms(2024, 1, 15, 9, 0, 0).unwrap()
+            + chrono::Duration::microseconds(123_456);
+
+        assert_eq!(
+            TimestampFormat::Rfc3339.format(instant),
+            "2024-01-15T09:00:00Z"
+        );
+        assert_eq!(
+            TimestampFormat::Rfc3339Millis.format(instant),
+            "2024-01-15T09:00:00.123Z"
+        );
+        assert_eq!(
+            "rfc3339_micros"
+                .parse::<TimestampFormat>()
+                .unwrap()
+                .format(instant),
+            "2024-01-15T09:00:00.123456Z"
+        );
+        assert!("iso8601".parse::<TimestampFormat>().is_err());
+    }
+
+    #[test]
+    fn test_fixed_clock() {
+        let instant = Utc.with_ymd_and_hms(2024, 1, 15, 9, 0, 0).unwrap();
+        let clock = FixedClock(instant);
+        assert_eq!(clock.now(), instant);
+        assert_eq!(clock.now(), clock.now());
+    }
+}
@@ -1,20 +1,189 @@
-use std::path::PathBuf;
-use anyhow::Result;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::level_filters::LevelFilter;
+use tracing::warn;
+
+use crate::clock::TimestampFormat;
+use crate::error::{ConfigError, TaskError};
+use crate::logging::LogFormat;
+use crate::models::{IdStrategy, Priority, TaskLimits, TaskStatus};
+use crate::retry::RetryPolicy;
 
 /// Application configuration
 #[derive(Debug, Clone)]
 pub struct AppConfig {
     /// Path to the tasks JSON file
     pub tasks_file_path: PathBuf,
+    /// Where archive_completed moves finished tasks; `None` means next to the task file
+    /// (see [`AppConfig::archive_path`])
+    pub archive_file_path: Option<PathBuf>,
+    /// JSON Lines file that gets one event per task created, changed or deleted;
+    /// `None` disables the event log
+    pub event_log_path: Option<PathBuf>,
+    /// URL that gets a POST for every task created, changed or deleted; only used when built
+    /// with the `webhooks` feature
+    pub webhook_url: Option<String>,
+    /// Fail loading when the task file contains malformed entries or has an unsupported
+    /// schema version instead of only logging warnings
+    pub strict_validation: bool,
+    /// Reject unknown filter keys (e.g. a misspelled `statuss`) instead of ignoring them
+    pub strict_filters: bool,
+    /// Refuse status changes out of a finished state other than reopening as pending,
+    /// unless the caller passes `force`
+    pub strict_transitions: bool,
+    /// Number of rotating backups of the task file to keep on save (0 disables backups)
+    pub backup_count: usize,
+    /// Write the task file as indented JSON; `false` writes compact JSON to save disk space
+    pub pretty_storage: bool,
+    /// How reads and writes of the task and archive files are retried after I/O errors
+    pub io_retry: RetryPolicy,
+    /// Maximum title length, description length and tag count accepted for a task
+    pub limits: TaskLimits,
+    /// Longest list_tasks response in characters; tasks past it are dropped with a notice
+    /// (0 disables the limit)
+    pub max_response_chars: usize,
+    /// Hide and refuse every tool that changes tasks
+    pub read_only: bool,
+    /// Names of the only tools offered to clients; `None` offers every tool
+    pub enabled_tools: Option<Vec<String>>,
+    /// Precision of the RFC 3339 `created_at`/`updated_at` stamps written to the task file
+    pub timestamp_format: TimestampFormat,
+    /// Weight of each priority in workload and next-task scoring
+    pub priority_weights: HashMap<Priority, u32>,
+    /// Whether logs are written as text lines or JSON objects
+    pub log_format: LogFormat,
+    /// Most verbose level that is logged
+    pub log_level: LevelFilter,
+    /// Priority given to created tasks that don't specify one
+    pub default_priority: Priority,
+    /// Status given to created tasks that don't specify one
+    pub default_status: TaskStatus,
+    /// How created tasks get their ID
+    pub id_strategy: IdStrategy,
 }
 
 impl AppConfig {
     /// Load configuration from .env file and environment variables
-    pub fn load() -> Result<Self> {
+    /// If `TASKS_CONFIG` points to a TOML file, that file is used instead of plain environment variables
+    pub fn load() -> Result<Self, ConfigError> {
         // Load .env file if it exists (ignores errors if file doesn't exist)
         dotenv::dotenv().ok();
-        
-        Ok(Self::from_env())
+
+        match std::env::var("TASKS_CONFIG") {
+            Ok(path) => Self::from_file(Path::new(&path)),
+            Err(_) => Ok(Self::from_env()),
+        }
+    }
+
+    /// Load configuration from a TOML file
+    /// Keys missing from the file fall back to the values from environment variables
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let file_config: FileConfig =
+            toml::from_str(&content).map_err(|source| ConfigError::Parse {
+                path: path.to_path_buf(),
+                source,
+            })?;
+
+        let mut config = Self::from_env();
+        if let Some(tasks_file_path) = file_config.tasks_file_path {
+            config.tasks_file_path = tasks_file_path;
+        }
+        if let Some(archive_file_path) = file_config.archive_file_path {
+            config.archive_file_path = Some(archive_file_path);
+        }
+        if let Some(event_log_path) = file_config.event_log_path {
+            config.event_log_path = Some(event_log_path);
+        }
+        if let Some(webhook_url) = file_config.webhook_url {
+            config.webhook_url = Some(webhook_url);
+        }
+        if let Some(strict_validation) = file_config.strict_validation {
+            config.strict_validation = strict_validation;
+        }
+        if let Some(strict_filters) = file_config.strict_filters {
+            config.strict_filters = strict_filters;
+        }
+        if let Some(strict_transitions) = file_config.strict_transitions {
+            config.strict_transitions = strict_transitions;
+        }
+        if let Some(backup_count) = file_config.backup_count {
+            config.backup_count = backup_count;
+        }
+        if let Some(pretty_storage) = file_config.pretty_storage {
+            config.pretty_storage = pretty_storage;
+        }
+        if let Some(io_retry_attempts) = file_config.io_retry_attempts {
+            config.io_retry = RetryPolicy::new(io_retry_attempts, config.io_retry.base_delay);
+        }
+        if let Some(io_retry_delay_ms) = file_config.io_retry_delay_ms {
+            config.io_retry.base_delay = Duration::from_millis(io_retry_delay_ms);
+        }
+        if let Some(max_title_length) = file_config.max_title_length {
+            config.limits.max_title_length = max_title_length;
+        }
+        if let Some(max_description_length) = file_config.max_description_length {
+            config.limits.max_description_length = max_description_length;
+        }
+        if let Some(max_tags) = file_config.max_tags {
+            config.limits.max_tags = max_tags;
+        }
+        if let Some(max_response_chars) = file_config.max_response_chars {
+            config.max_response_chars = max_response_chars;
+        }
+        if let Some(read_only) = file_config.read_only {
+            config.read_only = read_only;
+        }
+        if let Some(enabled_tools) = file_config.enabled_tools {
+            config.enabled_tools = Some(enabled_tools);
+        }
+        if let Some(timestamp_format) = file_config.timestamp_format {
+            config.timestamp_format = timestamp_format;
+        }
+        if let Some(log_format) = file_config.log_format {
+            config.log_format = log_format;
+        }
+        if let Some(log_level) = file_config
+            .log_level
+            .and_then(|value| parse_log_level(&value, &path.display().to_string()))
+        {
+            config.log_level = log_level;
+        }
+        if let Some(default_priority) = file_config.default_priority {
+            config.default_priority = parse_task_default(
+                &default_priority,
+                &path.display().to_string(),
+                Priority::Medium,
+            );
+        }
+        if let Some(default_status) = file_config.default_status {
+            config.default_status = parse_task_default(
+                &default_status,
+                &path.display().to_string(),
+                TaskStatus::Pending,
+            );
+        }
+        if let Some(id_strategy) = file_config.id_strategy {
+            config.id_strategy = id_strategy;
+        }
+        if let Some(priority_weights) = file_config.priority_weights {
+            let entries = priority_weights.into_iter().map(|(name, value)| {
+                let value = match value {
+                    toml::Value::String(value) => value,
+                    other => other.to_string(),
+                };
+                (name, value)
+            });
+            config.priority_weights = parse_priority_weights(entries, &path.display().to_string());
+        }
+        Ok(config)
     }
 
     /// Create configuration from environment variables
@@ -22,18 +191,252 @@ impl AppConfig {
         let tasks_file_path = std::env::var("TASKS_FILE")
             .unwrap_or_else(|_| "./data/tasks.json".to_string())
             .into();
+        let defaults = TaskLimits::default();
 
-        Self { tasks_file_path }
+        Self {
+            tasks_file_path,
+            archive_file_path: std::env::var("TASKS_ARCHIVE_FILE").ok().map(PathBuf::from),
+            event_log_path: std::env::var("TASKS_EVENT_LOG").ok().map(PathBuf::from),
+            webhook_url: std::env::var("TASKS_WEBHOOK_URL")
+                .ok()
+                .filter(|url| !url.trim().is_empty()),
+            strict_validation: env_flag("TASKS_STRICT"),
+            strict_filters: env_flag("TASKS_STRICT_FILTERS"),
+            strict_transitions: env_flag("TASKS_STRICT_TRANSITIONS"),
+            backup_count: env_count("TASKS_BACKUPS", 0),
+            pretty_storage: !env_flag_disabled("TASKS_PRETTY_STORAGE"),
+            io_retry: RetryPolicy::new(
+                env_count("TASKS_IO_RETRIES", 1) as u32,
+                Duration::from_millis(env_count("TASKS_IO_RETRY_DELAY_MS", 100) as u64),
+            ),
+            limits: TaskLimits {
+                max_title_length: env_count("TASKS_MAX_TITLE_LENGTH", defaults.max_title_length),
+                max_description_length: env_count(
+                    "TASKS_MAX_DESCRIPTION_LENGTH",
+                    defaults.max_description_length,
+                ),
+                max_tags: env_count("TASKS_MAX_TAGS", defaults.max_tags),
+            },
+            max_response_chars: env_count("TASKS_MAX_RESPONSE_CHARS", 0),
+            read_only: env_flag("TASKS_READONLY"),
+            enabled_tools: std::env::var("TASKS_ENABLED_TOOLS")
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+                .map(|value| {
+                    value
+                        .split(',')
+                        .map(|name| name.trim().to_string())
+                        .filter(|name| !name.is_empty())
+                        .collect()
+                }),
+            timestamp_format: std::env::var("TASKS_TIMESTAMP_FORMAT")
+                .ok()
+                .and_then(|value| value.trim().parse().ok())
+                .unwrap_or_default(),
+            priority_weights: match std::env::var("TASKS_PRIORITY_WEIGHTS") {
+                Ok(value) => parse_priority_weights(
+                    value
+                        .split(',')
+                        .filter(|entry| !entry.trim().is_empty())
+                        .map(|entry| {
+                            let (name, weight) = entry.split_once('=').unwrap_or((entry, ""));
+                            (name.to_string(), weight.to_string())
+                        }),
+                    "TASKS_PRIORITY_WEIGHTS",
+                ),
+                Err(_) => default_priority_weights(),
+            },
+            log_format: std::env::var("TASKS_LOG_FORMAT")
+                .ok()
+                .and_then(|value| value.trim().parse().ok())
+                .unwrap_or_default(),
+            log_level: std::env::var("TASKS_LOG_LEVEL")
+                .ok()
+                .and_then(|value| parse_log_level(&value, "TASKS_LOG_LEVEL"))
+                .unwrap_or(LevelFilter::INFO),
+            default_priority: match std::env::var("TASKS_DEFAULT_PRIORITY") {
+                Ok(value) => parse_task_default(&value, "TASKS_DEFAULT_PRIORITY", Priority::Medium),
+                Err(_) => Priority::Medium,
+            },
+            default_status: match std::env::var("TASKS_DEFAULT_STATUS") {
+                Ok(value) => {
+                    parse_task_default(&value, "TASKS_DEFAULT_STATUS", TaskStatus::Pending)
+                }
+                Err(_) => TaskStatus::Pending,
+            },
+            id_strategy: std::env::var("TASKS_ID_STRATEGY")
+                .ok()
+                .and_then(|value| value.trim().parse().ok())
+                .unwrap_or_default(),
+        }
     }
 
     /// Create configuration with custom file path
     pub fn with_file_path<P: Into<PathBuf>>(path: P) -> Self {
         Self {
             tasks_file_path: path.into(),
+            archive_file_path: None,
+            event_log_path: None,
+            webhook_url: None,
+            strict_validation: false,
+            strict_filters: false,
+            strict_transitions: false,
+            backup_count: 0,
+            pretty_storage: true,
+            io_retry: RetryPolicy::none(),
+            limits: TaskLimits::default(),
+            max_response_chars: 0,
+            read_only: false,
+            enabled_tools: None,
+            timestamp_format: TimestampFormat::default(),
+            priority_weights: default_priority_weights(),
+            log_format: LogFormat::default(),
+            log_level: LevelFilter::INFO,
+            default_priority: Priority::Medium,
+            default_status: TaskStatus::Pending,
+            id_strategy: IdStrategy::default(),
+        }
+    }
+
+    /// Path of the archive file: the configured one, or `<stem>.archive.json` beside the task file
+    pub fn archive_path(&self) -> PathBuf {
+        self.archive_file_path.clone().unwrap_or_else(|| {
+            let stem = self
+                .tasks_file_path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "tasks".to_string());
+            self.tasks_file_path
+                .with_file_name(format!("{}.archive.json", stem))
+        })
+    }
+}
+
+/// Settings accepted in a TOML config file; every key is optional
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    tasks_file_path: Option<PathBuf>,
+    archive_file_path: Option<PathBuf>,
+    event_log_path: Option<PathBuf>,
+    webhook_url: Option<String>,
+    strict_validation: Option<bool>,
+    strict_filters: Option<bool>,
+    strict_transitions: Option<bool>,
+    backup_count: Option<usize>,
+    pretty_storage: Option<bool>,
+    io_retry_attempts: Option<u32>,
+    io_retry_delay_ms: Option<u64>,
+    max_title_length: Option<usize>,
+    max_description_length: Option<usize>,
+    max_tags: Option<usize>,
+    max_response_chars: Option<usize>,
+    read_only: Option<bool>,
+    enabled_tools: Option<Vec<String>>,
+    timestamp_format: Option<TimestampFormat>,
+    log_format: Option<LogFormat>,
+    log_level: Option<String>,
+    default_priority: Option<String>,
+    default_status: Option<String>,
+    id_strategy: Option<IdStrategy>,
+    priority_weights: Option<HashMap<String, toml::Value>>,
+}
+
+/// Default priority weights: low 1, medium 2, high 4, critical 8
+fn default_priority_weights() -> HashMap<Priority, u32> {
+    Priority::ALL
+        .iter()
+        .map(|priority| (priority.clone(), priority.weight()))
+        .collect()
+}
+
+/// Override the default weights with `(priority, weight)` entries from `source`
+/// Unknown priorities and weights that aren't positive integers are skipped with a warning,
+/// leaving that priority at its default
+fn parse_priority_weights(
+    entries: impl IntoIterator<Item = (String, String)>,
+    source: &str,
+) -> HashMap<Priority, u32> {
+    let mut weights = default_priority_weights();
+    for (name, value) in entries {
+        let priority = match name.trim().parse::<Priority>() {
+            Ok(priority) => priority,
+            Err(e) => {
+                warn!("Ignoring priority weight in {}: {}", source, e);
+                continue;
+            }
+        };
+        match value.trim().parse::<u32>() {
+            Ok(weight) if weight > 0 => {
+                weights.insert(priority, weight);
+            }
+            _ => warn!(
+                "Ignoring priority weight {} = {:?} in {}: expected a positive integer; using the default {}",
+                name.trim(),
+                value.trim(),
+                source,
+                priority.weight()
+            ),
+        }
+    }
+    weights
+}
+
+/// Parse a log level name such as `debug`, warning and returning `None` if it isn't one
+fn parse_log_level(value: &str, source: &str) -> Option<LevelFilter> {
+    match value.trim().parse() {
+        Ok(level) => Some(level),
+        Err(_) => {
+            warn!(
+                "Ignoring log level {:?} in {}: expected off, error, warn, info, debug or trace",
+                value.trim(),
+                source
+            );
+            None
         }
     }
 }
 
+/// Parse a default priority or status for new tasks, warning and returning `fallback`
+/// if the value isn't a valid one
+fn parse_task_default<T>(value: &str, source: &str, fallback: T) -> T
+where
+    T: FromStr<Err = TaskError> + Debug,
+{
+    match value.parse() {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            warn!(
+                "Ignoring default in {}: {}; using {:?}",
+                source, e, fallback
+            );
+            fallback
+        }
+    }
+}
+
+/// Read a boolean flag from the environment; `1`, `true` and `yes` (any case) enable it
+fn env_flag(name: &str) -> bool {
+    std::env::var(name)
+        .map(|value| matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
+/// Read an on-by-default flag from the environment; `0`, `false` and `no` (any case) disable it
+fn env_flag_disabled(name: &str) -> bool {
+    std::env::var(name)
+        .map(|value| matches!(value.trim().to_lowercase().as_str(), "0" | "false" | "no"))
+        .unwrap_or(false)
+}
+
+/// Read a non-negative count from the environment, falling back to `default` when unset or invalid
+fn env_count(name: &str, default: usize) -> usize {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(default)
+}
+
 impl Default for AppConfig {
     fn default() -> Self {
         Self::from_env()
@@ -44,6 +447,7 @@ impl Default for AppConfig {
 mod tests {
     use super::*;
     use std::env;
+    use tempfile::TempDir;
 
     #[test]
     fn test_load_config() {
@@ -90,4 +494,189 @@ mod tests {
         let config = AppConfig::with_file_path(custom_path);
         assert_eq!(config.tasks_file_path.to_string_lossy(), custom_path);
     }
+
+    #[test]
+    fn test_archive_path() {
+        let mut config = AppConfig::with_file_path("/srv/tasks/team.json");
+        assert_eq!(
+            config.archive_path(),
+            PathBuf::from("/srv/tasks/team.archive.json")
+        );
+
+        config.archive_file_path = Some(PathBuf::from("/srv/archive/old.json"));
+        assert_eq!(
+            config.archive_path(),
+            PathBuf::from("/srv/archive/old.json")
+        );
+    }
+
+    #[test]
+    fn test_strict_validation_flag() {
+        unsafe {
+            env::set_var("TASKS_STRICT", "true");
+        }
+        assert!(AppConfig::from_env().strict_validation);
+
+        unsafe {
+            env::set_var("TASKS_STRICT", "0");
+        }
+        assert!(!AppConfig::from_env().strict_validation);
+
+        unsafe {
+            env::remove_var("TASKS_STRICT");
+        }
+        assert!(!AppConfig::from_env().strict_validation);
+    }
+
+    #[test]
+    fn test_backup_count() {
+        unsafe {
+            env::set_var("TASKS_BACKUPS", "5");
+        }
+        assert_eq!(AppConfig::from_env().backup_count, 5);
+
+        unsafe {
+            env::set_var("TASKS_BACKUPS", "many");
+        }
+        assert_eq!(AppConfig::from_env().backup_count, 0);
+
+        unsafe {
+            env::remove_var("TASKS_BACKUPS");
+        }
+        assert_eq!(AppConfig::from_env().backup_count, 0);
+    }
+
+    #[test]
+    fn test_limits_from_file() {
+        let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join("config.toml");
+        std::fs::write(
+            &config_path,
+            "max_title_length = 80\nmax_tags = 5\ntimestamp_format = \"rfc3339_millis\"\nid_strategy = \"slug\"\nlog_format = \"json\"\nlog_level = \"debug\"\n",
+        )
+        .unwrap();
+
+        let config = AppConfig::from_file(&config_path).expect("Failed to load config file");
+        assert_eq!(config.limits.max_title_length, 80);
+        assert_eq!(config.limits.max_tags, 5);
+        assert_eq!(
+            config.limits.max_description_length,
+            TaskLimits::default().max_description_length
+        );
+        assert_eq!(config.timestamp_format, TimestampFormat::Rfc3339Millis);
+        assert_eq!(config.id_strategy, IdStrategy::Slug);
+        assert_eq!(config.log_format, LogFormat::Json);
+        assert_eq!(config.log_level, LevelFilter::DEBUG);
+    }
+
+    #[test]
+    fn test_priority_weights_fall_back_to_defaults() {
+        let weights = parse_priority_weights(
+            [
+                ("high".to_string(), "10".to_string()),
+                ("low".to_string(), "0".to_string()),
+                ("medium".to_string(), "lots".to_string()),
+                ("urgent".to_string(), "3".to_string()),
+            ],
+            "test",
+        );
+        assert_eq!(weights[&Priority::High], 10);
+        assert_eq!(weights[&Priority::Low], 1);
+        assert_eq!(weights[&Priority::Medium], 2);
+        assert_eq!(weights[&Priority::Critical], 8);
+        assert_eq!(weights.len(), 4);
+    }
+
+    #[test]
+    fn test_priority_weights_from_file() {
+        let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join("config.toml");
+        std::fs::write(
+            &config_path,
+            "[priority_weights]\nlow = 3\ncritical = \"high\"\n",
+        )
+        .unwrap();
+
+        let config = AppConfig::from_file(&config_path).expect("Failed to load config file");
+        assert_eq!(config.priority_weights[&Priority::Low], 3);
+        assert_eq!(config.priority_weights[&Priority::Critical], 8);
+    }
+
+    #[test]
+    fn test_task_defaults_from_file() {
+        let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join("config.toml");
+        std::fs::write(
+            &config_path,
+            "default_priority = \"High\"\ndefault_status = \"someday\"\n",
+        )
+        .unwrap();
+
+        let config = AppConfig::from_file(&config_path).expect("Failed to load config file");
+        assert_eq!(config.default_priority, Priority::High);
+        assert_eq!(config.default_status, TaskStatus::Pending);
+    }
+
+    #[test]
+    fn test_from_file() {
+        let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join("config.toml");
+        std::fs::write(
+            &config_path,
+            "tasks_file_path = \"/srv/tasks/team.json\"\nstrict_validation = true\nstrict_filters = true\npretty_storage = false\nmax_response_chars = 50000\nevent_log_path = \"/srv/tasks/events.jsonl\"\nwebhook_url = \"http://localhost:9000/hooks\"\nio_retry_attempts = 4\nio_retry_delay_ms = 250\nread_only = true\nenabled_tools = [\"list_tasks\", \"get_task\"]\n",
+        )
+        .unwrap();
+
+        let config = AppConfig::from_file(&config_path).expect("Failed to load config file");
+        assert_eq!(
+            config.tasks_file_path.to_string_lossy(),
+            "/srv/tasks/team.json"
+        );
+        assert!(config.strict_validation);
+        assert!(config.strict_filters);
+        assert!(!config.pretty_storage);
+        assert_eq!(config.max_response_chars, 50000);
+        assert!(config.read_only);
+        assert_eq!(
+            config.enabled_tools,
+            Some(vec!["list_tasks".to_string(), "get_task".to_string()])
+        );
+        assert_eq!(
+            config.event_log_path.as_deref(),
+            Some(Path::new("/srv/tasks/events.jsonl"))
+        );
+        assert_eq!(
+            config.io_retry,
+            RetryPolicy::new(4, Duration::from_millis(250))
+        );
+        assert_eq!(
+            config.webhook_url.as_deref(),
+            Some("http://localhost:9000/hooks")
+        );
+    }
+
+    #[test]
+    fn test_from_file_falls_back_to_env() {
+        let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join("config.toml");
+        std::fs::write(&config_path, "").unwrap();
+
+        let config = AppConfig::from_file(&config_path).expect("Failed to load config file");
+        assert!(
+            config
+                .tasks_file_path
+                .to_string_lossy()
+                .contains("tasks.json")
+        );
+    }
+
+    #[test]
+    fn test_from_file_rejects_unknown_keys() {
+        let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join("config.toml");
+        std::fs::write(&config_path, "task_file = \"typo.json\"\n").unwrap();
+
+        assert!(AppConfig::from_file(&config_path).is_err());
+        assert!(AppConfig::from_file(&dir.path().join("missing.toml")).is_err());
+    }
 }
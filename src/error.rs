@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Errors raised while reading or writing the task file
+#[derive(Debug, Error)]
+pub enum StorageError {
+    /// The task file disappeared while it was being accessed
+    #[error("Task file not found: {}", path.display())]
+    NotFound { path: PathBuf },
+
+    /// The task file exists but does not contain a valid task collection
+    #[error(
+        "Task file {} is not valid: {message} at line {line}, column {column} (byte offset {offset})",
+        path.display()
+    )]
+    InvalidJson {
+        path: PathBuf,
+        message: String,
+        line: usize,
+        column: usize,
+        offset: usize,
+    },
+
+    /// The task file was written by a newer build, or its version can't be read
+    #[error(
+        "Task file {} has unsupported schema version {version} (this build supports up to {})",
+        path.display(),
+        crate::models::SCHEMA_VERSION
+    )]
+    UnsupportedVersion { path: PathBuf, version: String },
+
+    /// Any other filesystem failure
+    #[error("I/O error on task file {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// The in-memory collection could not be serialized
+    #[error("Failed to serialize tasks: {0}")]
+    Serialize(#[source] serde_json::Error),
+
+    /// The storage backend has no file to perform the operation on
+    #[error("{operation} is only supported for file storage")]
+    Unsupported { operation: &'static str },
+}
+
+impl StorageError {
+    /// Classify an I/O error on the given path
+    pub(crate) fn io(path: PathBuf, source: std::io::Error) -> Self {
+        if source.kind() == std::io::ErrorKind::NotFound {
+            StorageError::NotFound { path }
+        } else {
+            StorageError::Io { path, source }
+        }
+    }
+
+    /// Describe a JSON parse failure, including the byte offset of the error within `content`
+    pub(crate) fn invalid_json(path: PathBuf, content: &str, source: serde_json::Error) -> Self {
+        let line = source.line();
+        let column = source.column();
+        let offset = content
+            .split_inclusive('\n')
+            .take(line.saturating_sub(1))
+            .map(str::len)
+            .sum::<usize>()
+            + column.saturating_sub(1);
+
+        // serde_json appends " at line X column Y" to its message; keep only the description
+        let message = source.to_string();
+        let message = message
+            .split(" at line ")
+            .next()
+            .unwrap_or(&message)
+            .to_string();
+
+        StorageError::InvalidJson {
+            path,
+            message,
+            line,
+            column,
+            offset: offset.min(content.len()),
+        }
+    }
+}
+
+/// Errors returned by the task service layer
+#[derive(Debug, Error)]
+pub enum TaskError {
+    /// No task with the given ID exists
+    #[error("Task not found: {0}")]
+    NotFound(String),
+
+    /// Caller input or stored task data failed validation
+    #[error("{0}")]
+    Validation(String),
+
+    /// The task file could not be read or written
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+
+    /// A value could not be converted to or from JSON
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    /// Tasks could not be written out as CSV
+    #[error("CSV export error: {0}")]
+    Csv(#[from] csv::Error),
+
+    /// A streamed export could not be written to its destination
+    #[error("Failed to write export: {0}")]
+    Write(#[from] std::io::Error),
+}
+
+/// Errors raised while loading the application configuration
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    /// The config file could not be read
+    #[error("Failed to read config file {}: {source}", path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// The config file is not valid TOML or contains unknown keys
+    #[error("Invalid config file {}: {source}", path.display())]
+    Parse {
+        path: PathBuf,
+        #[source]
+        source: toml::de::Error,
+    },
+}
@@ -0,0 +1,227 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::fs::{self, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+use crate::error::StorageError;
+use crate::models::{Task, TaskCollection};
+
+/// Kind of change recorded by a [`TaskEvent`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventOperation {
+    Create,
+    Update,
+    Delete,
+    /// An edit that changed the task's status, possibly along with other fields
+    StatusChange,
+}
+
+/// Old and new value of one field in a [`TaskEvent`]; `null` on the side where the task
+/// doesn't exist or the field is unset
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldDiff {
+    pub old: serde_json::Value,
+    pub new: serde_json::Value,
+}
+
+/// One line of the event log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskEvent {
+    pub operation: EventOperation,
+    pub task_id: String,
+    /// When the change was saved
+    pub at: String,
+    /// Changed fields by name; creates and deletes list every field of the task
+    pub diff: BTreeMap<String, FieldDiff>,
+}
+
+impl TaskEvent {
+    /// Events turning `before` into `after`: one per created, changed or deleted task,
+    /// in the order of `after` with deletions last
+    ///
+    /// A task is matched by ID. Changes are compared as in [`Task::changed_fields`], so a
+    /// task whose only difference is its `updated_at` or `history` produces no event.
+    pub fn between(before: &TaskCollection, after: &TaskCollection, at: &str) -> Vec<TaskEvent> {
+        let old_tasks: HashMap<&str, &Task> =
+            before.tasks.iter().map(|t| (t.id.as_str(), t)).collect();
+        let new_tasks: HashMap<&str, &Task> =
+            after.tasks.iter().map(|t| (t.id.as_str(), t)).collect();
+
+        let mut events = Vec::new();
+        for task in &after.tasks {
+            let event = match old_tasks.get(task.id.as_str()) {
+                None => TaskEvent::new(EventOperation::Create, task, at, whole_task(task, false)),
+                Some(old) => {
+                    let diff: BTreeMap<String, FieldDiff> = old
+                        .changed_fields(task)
+                        .into_iter()
+                        .map(|(field, old, new)| (field, FieldDiff { old, new }))
+                        .collect();
+                    if diff.is_empty() {
+                        continue;
+                    }
+                    let operation = if old.status != task.status {
+                        EventOperation::StatusChange
+                    } else {
+                        EventOperation::Update
+                    };
+                    TaskEvent::new(operation, task, at, diff)
+                }
+            };
+            events.push(event);
+        }
+        for task in &before.tasks {
+            if !new_tasks.contains_key(task.id.as_str()) {
+                events.push(TaskEvent::new(
+                    EventOperation::Delete,
+                    task,
+                    at,
+                    whole_task(task, true),
+                ));
+            }
+        }
+        events
+    }
+
+    fn new(
+        operation: EventOperation,
+        task: &Task,
+        at: &str,
+        diff: BTreeMap<String, FieldDiff>,
+    ) -> Self {
+        Self {
+            operation,
+            task_id: task.id.clone(),
+            at: at.to_string(),
+            diff,
+        }
+    }
+}
+
+/// Every stored field of `task` except `history`, as the old side of the diff when
+/// `deleted` and as the new side otherwise
+fn whole_task(task: &Task, deleted: bool) -> BTreeMap<String, FieldDiff> {
+    let fields = match serde_json::to_value(task) {
+        Ok(serde_json::Value::Object(map)) => map,
+        _ => serde_json::Map::new(),
+    };
+    fields
+        .into_iter()
+        .filter(|(field, _)| field != "history")
+        .map(|(field, value)| {
+            let diff = if deleted {
+                FieldDiff {
+                    old: value,
+                    new: serde_json::Value::Null,
+                }
+            } else {
+                FieldDiff {
+                    old: serde_json::Value::Null,
+                    new: value,
+                }
+            };
+            (field, diff)
+        })
+        .collect()
+}
+
+/// Append-only JSON Lines file receiving one [`TaskEvent`] per task change
+#[derive(Debug, Clone)]
+pub struct EventLog {
+    path: PathBuf,
+    /// Keeps the lines of concurrent appends from interleaving
+    lock: Arc<Mutex<()>>,
+}
+
+impl EventLog {
+    /// Create an event log writing to `path`; the file is created on the first append
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Path of the log file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append `events` as one JSON object per line
+    pub async fn append(&self, events: &[TaskEvent]) -> Result<(), StorageError> {
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        let mut lines = String::new();
+        for event in events {
+            lines.push_str(&serde_json::to_string(event).map_err(StorageError::Serialize)?);
+            lines.push('\n');
+        }
+
+        let _guard = self.lock.lock().await;
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|e| StorageError::io(parent.to_path_buf(), e))?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(|e| StorageError::io(self.path.clone(), e))?;
+        file.write_all(lines.as_bytes())
+            .await
+            .map_err(|e| StorageError::io(self.path.clone(), e))?;
+        file.flush()
+            .await
+            .map_err(|e| StorageError::io(self.path.clone(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Priority, TaskStatus};
+
+    fn collection(tasks: Vec<Task>) -> TaskCollection {
+        let mut collection = TaskCollection::new();
+        collection.tasks = tasks;
+        collection
+    }
+
+    #[test]
+    fn test_between_tells_status_changes_from_updates() {
+        let first = Task::new_pending("First", "", Priority::Low);
+        let second = Task::new_pending("Second", "", Priority::Low);
+        let before = collection(vec![first.clone(), second.clone()]);
+
+        let mut started = first.clone();
+        started.status = TaskStatus::InProgress;
+        let mut raised = second.clone();
+        raised.priority = Priority::High;
+        raised.updated_at = "2024-03-01T12:00:00Z".to_string();
+        let after = collection(vec![started, raised]);
+
+        let events = TaskEvent::between(&before, &after, "2024-03-01T12:00:00Z");
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].operation, EventOperation::StatusChange);
+        assert_eq!(events[0].task_id, first.id);
+        assert_eq!(
+            events[0].diff["status"],
+            FieldDiff {
+                old: "pending".into(),
+                new: "in_progress".into()
+            }
+        );
+        assert_eq!(events[1].operation, EventOperation::Update);
+        assert_eq!(events[1].diff.keys().collect::<Vec<_>>(), vec!["priority"]);
+
+        assert!(TaskEvent::between(&before, &before, "2024-03-01T12:00:00Z").is_empty());
+    }
+}
@@ -30,15 +30,38 @@
 //! }
 //! ```
 
+pub mod clock;
 pub mod config;
+pub mod error;
+pub mod event_log;
+pub mod logging;
 pub mod mcp_handler;
 pub mod models;
+pub mod retry;
 pub mod storage;
 pub mod task_service;
+#[cfg(feature = "webhooks")]
+pub mod webhook;
 
 // Re-export commonly used types
+pub use clock::{Clock, FixedClock, SystemClock, TimestampFormat};
 pub use config::AppConfig;
-pub use mcp_handler::TaskMcpHandler;
-pub use models::{Priority, Task, TaskCollection, TaskStatus};
+pub use error::{ConfigError, StorageError, TaskError};
+pub use event_log::{EventLog, EventOperation, FieldDiff, TaskEvent};
+pub use logging::{LogFormat, init_tracing};
+pub use mcp_handler::{MUTATING_TOOLS, TaskMcpHandler, build_tool_definitions};
+pub use models::{
+    IdStrategy, Priority, Recurrence, SCHEMA_VERSION, Task, TaskChange, TaskCollection,
+    TaskComment, TaskLimits, TaskStatus,
+};
+pub use retry::RetryPolicy;
+#[cfg(any(test, feature = "testing"))]
+pub use storage::MemoryStorage;
 pub use storage::TaskStorage;
-pub use task_service::{TaskService, TaskStatistics};
+pub use task_service::{
+    AssigneeWorkload, DailyDigest, FacetCount, Facets, GroupBy, HealthReport, ImportMode,
+    ImportSummary, MatchMode, NewTask, SearchHit, SortBy, TaskFilter, TaskService, TaskStatistics,
+    TaskUpdate,
+};
+#[cfg(feature = "webhooks")]
+pub use webhook::Webhook;
@@ -0,0 +1,106 @@
+use serde::Deserialize;
+use std::str::FromStr;
+use tracing::Subscriber;
+use tracing::subscriber::SetGlobalDefaultError;
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::config::AppConfig;
+use crate::error::TaskError;
+
+/// How log lines are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable text lines
+    #[default]
+    Pretty,
+    /// One JSON object per line, for structured log pipelines
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            other => Err(TaskError::Validation(format!(
+                "Invalid log format: {} (expected pretty or json)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Build the subscriber described by the config's log format and level, writing to `writer`
+/// Nothing is installed, so callers decide whether it becomes the global or a scoped default
+pub fn subscriber<W>(config: &AppConfig, writer: W) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let builder = tracing_subscriber::fmt()
+        .with_writer(writer)
+        .with_max_level(config.log_level);
+    match config.log_format {
+        LogFormat::Pretty => Box::new(builder.finish()),
+        LogFormat::Json => Box::new(builder.json().finish()),
+    }
+}
+
+/// Install the configured subscriber as the global default, logging to stderr
+/// (stdout carries the MCP JSON-RPC stream)
+pub fn init_tracing(config: &AppConfig) -> Result<(), SetGlobalDefaultError> {
+    tracing::subscriber::set_global_default(subscriber(config, std::io::stderr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use tracing::level_filters::LevelFilter;
+
+    /// In-memory log destination shared between the subscriber and the test
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_subscriber_writes_json_lines() {
+        let mut config = AppConfig::with_file_path("tasks.json");
+        config.log_format = LogFormat::Json;
+        config.log_level = LevelFilter::INFO;
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+
+        tracing::subscriber::with_default(subscriber(&config, move || writer.clone()), || {
+            tracing::debug!("below the configured level");
+            tracing::info!(tool = "list_tasks", "Tool call finished");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 1, "unexpected log output: {}", output);
+        let line: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["fields"]["message"], "Tool call finished");
+        assert_eq!(line["fields"]["tool"], "list_tasks");
+    }
+
+    #[test]
+    fn test_parse_log_format() {
+        assert_eq!("pretty".parse::<LogFormat>().unwrap(), LogFormat::Pretty);
+        assert_eq!("json".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert!("xml".parse::<LogFormat>().is_err());
+    }
+}
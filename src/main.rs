@@ -1,24 +1,66 @@
 use anyhow::Result;
-use mcp_todo_task::{AppConfig, TaskMcpHandler, TaskService, TaskStorage};
+use mcp_todo_task::{AppConfig, EventLog, TaskMcpHandler, TaskService, TaskStorage, init_tracing};
 use rmcp::service::ServiceExt;
 use tracing::info;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize tracing to stderr (stdout is used for JSON-RPC)
-    tracing_subscriber::fmt()
+    // Load configuration from .env file and environment variables, with warnings about
+    // bad settings going to a default stderr logger until the configured one is installed
+    let fallback = tracing_subscriber::fmt()
         .with_writer(std::io::stderr)
-        .init();
+        .finish();
+    let config = tracing::subscriber::with_default(fallback, AppConfig::load)?;
 
-    info!("Starting Task Manager MCP Server");
+    // Initialize tracing to stderr (stdout is used for JSON-RPC)
+    init_tracing(&config)?;
 
-    // Load configuration from .env file and environment variables
-    let config = AppConfig::load()?;
+    info!("Starting Task Manager MCP Server");
 
     // Set up the service layers
-    let storage = TaskStorage::new(config.tasks_file_path);
-    let task_service = TaskService::new(storage);
-    let handler = TaskMcpHandler::new(task_service);
+    let archive = TaskStorage::new(config.archive_path())
+        .with_strict_versions(config.strict_validation)
+        .with_pretty(config.pretty_storage)
+        .with_retry(config.io_retry);
+    let storage = TaskStorage::new(config.tasks_file_path)
+        .with_strict_versions(config.strict_validation)
+        .with_backups(config.backup_count)
+        .with_pretty(config.pretty_storage)
+        .with_retry(config.io_retry);
+    let mut task_service = TaskService::new(storage)
+        .with_archive(archive)
+        .with_strict_validation(config.strict_validation)
+        .with_strict_filters(config.strict_filters)
+        .with_strict_transitions(config.strict_transitions)
+        .with_limits(config.limits)
+        .with_priority_weights(config.priority_weights)
+        .with_timestamp_format(config.timestamp_format)
+        .with_task_defaults(config.default_priority, config.default_status)
+        .with_id_strategy(config.id_strategy);
+    if let Some(event_log_path) = config.event_log_path {
+        task_service = task_service.with_event_log(EventLog::new(event_log_path));
+    }
+    if let Some(webhook_url) = config.webhook_url {
+        #[cfg(feature = "webhooks")]
+        {
+            task_service = task_service.with_webhook(mcp_todo_task::Webhook::new(webhook_url));
+        }
+        #[cfg(not(feature = "webhooks"))]
+        tracing::warn!(
+            "Ignoring webhook URL {}: this build lacks the webhooks feature",
+            webhook_url
+        );
+    }
+    if config.read_only {
+        info!("Read-only mode: tools that change tasks are disabled");
+    }
+    let mut handler = TaskMcpHandler::new(task_service)
+        .with_max_response_chars(config.max_response_chars)
+        .with_read_only(config.read_only);
+    if let Some(enabled_tools) = config.enabled_tools {
+        info!("Enabled tools: {}", enabled_tools.join(", "));
+        handler = handler.with_enabled_tools(enabled_tools);
+    }
 
     // Set up transport - using stdio for MCP communication
     let transport = (tokio::io::stdin(), tokio::io::stdout());
@@ -1,28 +1,157 @@
-use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use rmcp::{
     ErrorData as McpError, ServerHandler,
     model::{
-        CallToolRequestMethod, CallToolRequestParam, CallToolResult, Content, Implementation,
-        InitializeRequestParam, InitializeResult, ListToolsResult, PaginatedRequestParam,
-        ProtocolVersion, ServerCapabilities, ServerInfo, Tool,
+        AnnotateAble, CallToolRequestMethod, CallToolRequestParam, CallToolResult, Content,
+        GetPromptRequestParam, GetPromptResult, Implementation, InitializeRequestParam,
+        InitializeResult, ListPromptsResult, ListResourcesResult, ListToolsResult,
+        PaginatedRequestParam, Prompt, PromptMessage, PromptMessageRole, ProtocolVersion,
+        RawResource, ReadResourceRequestParam, ReadResourceResult, Resource, ResourceContents,
+        ServerCapabilities, ServerInfo, Tool,
     },
     service::{RequestContext, RoleServer},
 };
-use std::{collections::HashMap, sync::Arc};
-use tracing::info;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{Instrument, field, info, info_span, warn};
 
-use crate::task_service::TaskService;
+use crate::error::{StorageError, TaskError};
+use crate::models::{Task, TaskComment, TaskStatus, parse_timestamp};
+use crate::task_service::{
+    GroupBy, ImportMode, NewTask, SortBy, TaskFilter, TaskPage, TaskService, TaskUpdate,
+};
+
+/// Window used by due_soon when the caller doesn't pass `days`
+const DEFAULT_DUE_SOON_DAYS: u32 = 7;
+
+/// Resource URI for the whole task collection
+const TASKS_RESOURCE_URI: &str = "tasks://all";
+
+/// Prefix of the per-task resource URIs (`task://<id>`)
+const TASK_RESOURCE_PREFIX: &str = "task://";
+
+/// How far back the daily_standup prompt looks for completed tasks
+const STANDUP_LOOKBACK_HOURS: i64 = 24;
 
 /// MCP server handler that manages tasks
 #[derive(Debug, Clone)]
 pub struct TaskMcpHandler {
     task_service: TaskService,
+    /// Longest list_tasks response in characters before tasks are dropped (0 means no limit)
+    max_response_chars: usize,
+    /// Hide and refuse the tools in [`MUTATING_TOOLS`]
+    read_only: bool,
+    /// Names of the only tools offered, or `None` to offer all of them
+    enabled_tools: Option<Vec<String>>,
 }
 
+/// Tools that change the task or archive file; hidden and refused in read-only mode
+pub const MUTATING_TOOLS: &[&str] = &[
+    "create_task",
+    "update_task",
+    "apply_patch",
+    "add_comment",
+    "add_tag",
+    "remove_tag",
+    "rename_tag",
+    "bulk_tag",
+    "delete_task",
+    "complete_task",
+    "start_task",
+    "cancel_task",
+    "reopen_task",
+    "reassign_task",
+    "unassign_task",
+    "set_due_date",
+    "reorder_task",
+    "import_csv",
+    "archive_completed",
+    "restore_task",
+    "deduplicate",
+    "compact_storage",
+    "undo",
+];
+
 impl TaskMcpHandler {
     /// Create a new MCP handler with the given task service
     pub fn new(task_service: TaskService) -> Self {
-        Self { task_service }
+        Self {
+            task_service,
+            max_response_chars: 0,
+            read_only: false,
+            enabled_tools: None,
+        }
+    }
+
+    /// Only offer tools that read tasks; the ones in [`MUTATING_TOOLS`] are left out of
+    /// the tool list and calling them fails
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Offer only the named tools; the others are left out of the tool list and calling
+    /// them fails. Names that aren't tools of this server are ignored with a warning
+    pub fn with_enabled_tools(mut self, enabled_tools: Vec<String>) -> Self {
+        let known = build_tool_definitions();
+        for name in &enabled_tools {
+            if !known.iter().any(|tool| tool.name == name.as_str()) {
+                warn!("Ignoring unknown tool {:?} in the enabled tools", name);
+            }
+        }
+        self.enabled_tools = Some(enabled_tools);
+        self
+    }
+
+    /// Whether the tool is enabled and, in read-only mode, doesn't change tasks
+    fn offers_tool(&self, name: &str) -> bool {
+        let enabled = self
+            .enabled_tools
+            .as_ref()
+            .is_none_or(|tools| tools.iter().any(|tool| tool == name));
+        enabled && !(self.read_only && MUTATING_TOOLS.contains(&name))
+    }
+
+    /// Tools offered by this handler, per [`Self::offers_tool`]
+    fn tool_definitions(&self) -> Vec<Tool> {
+        build_tool_definitions()
+            .into_iter()
+            .filter(|tool| self.offers_tool(&tool.name))
+            .collect()
+    }
+
+    /// Cap list_tasks responses at `max_response_chars` characters; 0 disables the cap
+    pub fn with_max_response_chars(mut self, max_response_chars: usize) -> Self {
+        self.max_response_chars = max_response_chars;
+        self
+    }
+
+    /// Render a response from the first `n` of `len` tasks, keeping as many as fit within
+    /// `max_response_chars`; when even none fit, the response without tasks is returned
+    fn fit_response(
+        &self,
+        len: usize,
+        render: impl Fn(usize) -> Result<String, McpError>,
+    ) -> Result<String, McpError> {
+        let fits = |text: &str| {
+            self.max_response_chars == 0 || text.chars().count() <= self.max_response_chars
+        };
+        let full = render(len)?;
+        if fits(&full) {
+            return Ok(full);
+        }
+
+        // Rendering `high` tasks never fits; look for the most that do
+        let (mut low, mut high) = (0, len);
+        while low + 1 < high {
+            let mid = low + (high - low) / 2;
+            if fits(&render(mid)?) {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        render(low)
     }
 
     /// Handle the list_tasks tool call
@@ -30,28 +159,138 @@ impl TaskMcpHandler {
         &self,
         arguments: Option<serde_json::Map<String, serde_json::Value>>,
     ) -> Result<CallToolResult, McpError> {
-        let task_collection =
-            self.task_service.load_tasks().await.map_err(|e| {
-                McpError::internal_error(format!("Failed to load tasks: {}", e), None)
-            })?;
+        let task_collection = self
+            .task_service
+            .snapshot()
+            .await
+            .map_err(|e| service_error("Failed to load tasks", e))?;
 
-        let filters: HashMap<String, String> = arguments
-            .unwrap_or_default()
-            .iter()
-            .filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string())))
-            .collect();
+        let arguments = arguments.unwrap_or_default();
+
+        let format = format_argument(&arguments)?;
 
-        let filtered_tasks = self
+        let filter = self
             .task_service
-            .filter_tasks(&task_collection.tasks, &filters);
+            .filter_from_arguments(&arguments, &["limit", "offset", "format", "sort_by"])
+            .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
 
-        let response = serde_json::json!({
-            "count": filtered_tasks.len(),
-            "tasks": filtered_tasks,
-            "filters_applied": filters
-        });
+        let offset = optional_integer(&arguments, "offset")?.unwrap_or(0);
+        if offset < 0 {
+            return Err(McpError::invalid_params(
+                "offset must not be negative",
+                None,
+            ));
+        }
 
-        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+        let limit = optional_integer(&arguments, "limit")?;
+        if limit.is_some_and(|limit| limit <= 0) {
+            return Err(McpError::invalid_params(
+                "limit must be a positive integer",
+                None,
+            ));
+        }
+
+        let sort_by = arguments
+            .get("sort_by")
+            .and_then(|v| v.as_str())
+            .map(str::parse::<SortBy>)
+            .transpose()
+            .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+
+        let mut filtered_tasks = self
+            .task_service
+            .filter_tasks(&task_collection.tasks, &filter);
+        if let Some(sort_by) = sort_by {
+            self.task_service.sort_tasks(&mut filtered_tasks, sort_by);
+        }
+
+        let page = self.task_service.paginate(
+            filtered_tasks,
+            offset as usize,
+            limit.map(|limit| limit as usize),
+        );
+
+        let render = |kept: usize| -> Result<String, McpError> {
+            let omitted = page.tasks.len() - kept;
+            let shown = TaskPage {
+                tasks: page.tasks[..kept].to_vec(),
+                ..page.clone()
+            };
+
+            if format == ListFormat::Markdown {
+                let mut text = format!(
+                    "{}\n\n{}",
+                    shown.summary(),
+                    self.task_service.format_task_list(&shown.tasks)
+                );
+                if omitted > 0 {
+                    text.push_str(&format!("\n\n{}", truncation_notice(omitted)));
+                }
+                return Ok(text);
+            }
+
+            let mut response = serde_json::json!({
+                "summary": shown.summary(),
+                "count": shown.tasks.len(),
+                "total": shown.total,
+                "offset": shown.offset,
+                "tasks": shown.tasks,
+                "filters_applied": filter
+            });
+            if omitted > 0 {
+                response["omitted"] = omitted.into();
+                response["notice"] = truncation_notice(omitted).into();
+            }
+
+            serde_json::to_string_pretty(&response).map_err(|e| {
+                McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+            })
+        };
+        let response_text = self.fit_response(page.tasks.len(), render)?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the count_tasks tool call
+    async fn handle_count_tasks(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let filter = self
+            .task_service
+            .filter_from_arguments(&arguments, &[])
+            .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+
+        let count = self
+            .task_service
+            .count(&filter)
+            .await
+            .map_err(|e| service_error("Failed to load tasks", e))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            count.to_string(),
+        )]))
+    }
+
+    /// Handle the group_tasks tool call
+    async fn handle_group_tasks(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let group_by = arguments
+            .get("group_by")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: group_by", None))?
+            .parse::<GroupBy>()
+            .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+
+        let groups = self
+            .task_service
+            .group_by(group_by)
+            .await
+            .map_err(|e| service_error("Failed to load tasks", e))?;
+
+        let response_text = serde_json::to_string_pretty(&groups).map_err(|e| {
             McpError::internal_error(format!("Failed to serialize response: {}", e), None)
         })?;
 
@@ -72,174 +311,4049 @@ impl TaskMcpHandler {
             .task_service
             .find_task_by_id(task_id)
             .await
-            .map_err(|e| McpError::internal_error(format!("Failed to load tasks: {}", e), None))?
-            .ok_or_else(|| {
-                McpError::invalid_params(format!("Task not found: {}", task_id), None)
-            })?;
+            .map_err(|e| service_error("Failed to load tasks", e))?;
+
+        let compact = optional_bool(&arguments, "compact")?;
 
         let response = serde_json::json!({
             "task": task
         });
 
+        let response_text = if compact {
+            serde_json::to_string(&response)
+        } else {
+            serde_json::to_string_pretty(&response)
+        }
+        .map_err(|e| McpError::internal_error(format!("Failed to serialize task: {}", e), None))?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the get_tasks_batch tool call
+    async fn handle_get_tasks_batch(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let task_ids = required_string_array(&arguments, "ids")?;
+
+        let (tasks, missing_ids) = self
+            .task_service
+            .find_tasks_by_ids(&task_ids)
+            .await
+            .map_err(|e| service_error("Failed to load tasks", e))?;
+
+        let response = serde_json::json!({
+            "count": tasks.len(),
+            "tasks": tasks,
+            "missing_ids": missing_ids
+        });
+
+        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the subtasks tool call
+    async fn handle_subtasks(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let task_id = arguments
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: id", None))?;
+
+        let children = self
+            .task_service
+            .children_of(task_id)
+            .await
+            .map_err(|e| service_error("Failed to load tasks", e))?;
+
+        let response = serde_json::json!({
+            "parent_id": task_id,
+            "count": children.len(),
+            "tasks": children
+        });
+
         let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the create_task tool call
+    async fn handle_create_task(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let new_task = NewTask::from_arguments(&arguments)
+            .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+
+        let task = self
+            .task_service
+            .create_task(&new_task)
+            .await
+            .map_err(|e| service_error("Failed to create task", e))?;
+
+        let response_text = serde_json::to_string_pretty(&task).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the update_task tool call
+    async fn handle_update_task(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let task_id = arguments
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: id", None))?;
+
+        let update = TaskUpdate::from_arguments(&arguments)
+            .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+        let dry_run = optional_bool(&arguments, "dry_run")?;
+
+        if dry_run {
+            let before = self
+                .task_service
+                .find_task_by_id(task_id)
+                .await
+                .map_err(|e| service_error("Failed to load tasks", e))?;
+            let after = self
+                .task_service
+                .update_task(task_id, &update, true)
+                .await
+                .map_err(|e| service_error("Failed to update task", e))?;
+
+            let response = serde_json::json!({
+                "dry_run": true,
+                "id": task_id,
+                "changes": task_changes(&before, &after)
+            });
+
+            let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+                McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+            })?;
+
+            return Ok(CallToolResult::success(vec![Content::text(response_text)]));
+        }
+
+        let task = self
+            .task_service
+            .update_task(task_id, &update, false)
+            .await
+            .map_err(|e| service_error("Failed to update task", e))?;
+
+        let response_text = serde_json::to_string_pretty(&task).map_err(|e| {
             McpError::internal_error(format!("Failed to serialize task: {}", e), None)
         })?;
 
         Ok(CallToolResult::success(vec![Content::text(response_text)]))
     }
 
-    /// Handle the task_stats tool call
-    async fn handle_task_stats(&self) -> Result<CallToolResult, McpError> {
-        let stats = self.task_service.get_task_statistics().await.map_err(|e| {
-            McpError::internal_error(format!("Failed to get task statistics: {}", e), None)
+    /// Handle the apply_patch tool call
+    async fn handle_apply_patch(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let task_id = arguments
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: id", None))?;
+        let patch = arguments
+            .get("patch")
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: patch", None))?;
+        let patch: json_patch::Patch = serde_json::from_value(patch.clone()).map_err(|e| {
+            McpError::invalid_params(format!("patch must be a JSON Patch array: {}", e), None)
+        })?;
+        let force = optional_bool(&arguments, "force")?;
+
+        let task = self
+            .task_service
+            .apply_patch(task_id, &patch, force)
+            .await
+            .map_err(|e| service_error("Failed to patch task", e))?;
+
+        let response_text = serde_json::to_string_pretty(&task).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize task: {}", e), None)
         })?;
 
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the add_comment tool call
+    async fn handle_add_comment(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let task_id = arguments
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: id", None))?;
+        let body = arguments
+            .get("body")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: body", None))?;
+        let author = arguments
+            .get("author")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+
+        let comment = TaskComment {
+            author,
+            body: body.to_string(),
+            created_at: self.task_service.timestamp(),
+        };
+        let task = self
+            .task_service
+            .add_comment(task_id, comment)
+            .await
+            .map_err(|e| service_error("Failed to add comment", e))?;
+
         let response = serde_json::json!({
-            "total_tasks": stats.total_tasks,
-            "status_counts": stats.status_counts,
-            "priority_counts": stats.priority_counts
+            "id": task_id,
+            "comment_count": task.comments.len(),
+            "comment": task.comments.last()
         });
 
         let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
-            McpError::internal_error(format!("Failed to serialize statistics: {}", e), None)
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
         })?;
 
         Ok(CallToolResult::success(vec![Content::text(response_text)]))
     }
-}
 
-impl ServerHandler for TaskMcpHandler {
-    fn get_info(&self) -> ServerInfo {
-        InitializeResult {
-            protocol_version: ProtocolVersion::LATEST,
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
-            server_info: Implementation::from_build_env(),
-            instructions: None,
-        }
+    /// Handle the add_tag tool call
+    async fn handle_add_tag(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let (task_id, tag) = tag_arguments(&arguments)?;
+
+        let tags = self
+            .task_service
+            .add_tag(task_id, tag)
+            .await
+            .map_err(|e| service_error("Failed to update task", e))?;
+
+        tags_response(task_id, tags)
     }
 
-    async fn initialize(
+    /// Handle the remove_tag tool call
+    async fn handle_remove_tag(
         &self,
-        _request: InitializeRequestParam,
-        _context: RequestContext<RoleServer>,
-    ) -> Result<InitializeResult, McpError> {
-        info!("Task Manager MCP server initialized");
-        Ok(InitializeResult {
-            protocol_version: ProtocolVersion::LATEST,
-            capabilities: self.get_info().capabilities,
-            server_info: Implementation::from_build_env(),
-            instructions: None,
-        })
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let (task_id, tag) = tag_arguments(&arguments)?;
+
+        let tags = self
+            .task_service
+            .remove_tag(task_id, tag)
+            .await
+            .map_err(|e| service_error("Failed to update task", e))?;
+
+        tags_response(task_id, tags)
     }
 
-    async fn list_tools(
+    /// Handle the rename_tag tool call
+    async fn handle_rename_tag(
         &self,
-        _request: Option<PaginatedRequestParam>,
-        _context: RequestContext<RoleServer>,
-    ) -> Result<ListToolsResult, McpError> {
-        let tools = vec![
-            Tool {
-                name: "list_tasks".into(),
-                description: Some(
-                    "List all tasks, optionally filtered by status, priority, assignee, or tag"
-                        .into(),
-                ),
-                input_schema: Arc::new({
-                    let schema = serde_json::json!({
-                        "type": "object",
-                        "properties": {
-                            "status": {
-                                "type": "string",
-                                "enum": ["pending", "in_progress", "completed", "cancelled"],
-                                "description": "Filter tasks by status"
-                            },
-                            "priority": {
-                                "type": "string",
-                                "enum": ["low", "medium", "high", "critical"],
-                                "description": "Filter tasks by priority"
-                            },
-                            "assignee": {
-                                "type": "string",
-                                "description": "Filter tasks by assignee"
-                            },
-                            "tag": {
-                                "type": "string",
-                                "description": "Filter tasks by tag"
-                            }
-                        },
-                        "additionalProperties": false
-                    });
-                    match schema {
-                        serde_json::Value::Object(map) => map,
-                        _ => panic!("Schema must be an object"),
-                    }
-                }),
-                output_schema: None,
-                annotations: None,
-            },
-            Tool {
-                name: "get_task".into(),
-                description: Some("Get detailed information about a specific task by ID".into()),
-                input_schema: Arc::new({
-                    let schema = serde_json::json!({
-                        "type": "object",
-                        "properties": {
-                            "id": {
-                                "type": "string",
-                                "description": "The task ID"
-                            }
-                        },
-                        "required": ["id"],
-                        "additionalProperties": false
-                    });
-                    match schema {
-                        serde_json::Value::Object(map) => map,
-                        _ => panic!("Schema must be an object"),
-                    }
-                }),
-                output_schema: None,
-                annotations: None,
-            },
-            Tool {
-                name: "task_stats".into(),
-                description: Some(
-                    "Get statistics about tasks (counts by status, priority, etc.)".into(),
-                ),
-                input_schema: Arc::new({
-                    let schema = serde_json::json!({
-                        "type": "object",
-                        "properties": {},
-                        "additionalProperties": false
-                    });
-                    match schema {
-                        serde_json::Value::Object(map) => map,
-                        _ => panic!("Schema must be an object"),
-                    }
-                }),
-                output_schema: None,
-                annotations: None,
-            },
-        ];
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let from = arguments
+            .get("from")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: from", None))?;
+        let to = arguments
+            .get("to")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: to", None))?;
 
-        Ok(ListToolsResult {
-            tools,
-            next_cursor: None,
-        })
+        let tasks_updated = self
+            .task_service
+            .rename_tag(from, to)
+            .await
+            .map_err(|e| service_error("Failed to rename tag", e))?;
+
+        let response = serde_json::json!({
+            "from": from.trim(),
+            "to": to.trim(),
+            "tasks_updated": tasks_updated
+        });
+
+        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
     }
 
-    async fn call_tool(
+    /// Handle the bulk_tag tool call
+    async fn handle_bulk_tag(
         &self,
-        request: CallToolRequestParam,
-        _context: RequestContext<RoleServer>,
+        arguments: serde_json::Map<String, serde_json::Value>,
     ) -> Result<CallToolResult, McpError> {
-        match request.name.as_ref() {
-            "list_tasks" => self.handle_list_tasks(request.arguments).await,
-            "get_task" => {
-                let arguments = request.arguments.unwrap_or_default();
-                self.handle_get_task(arguments).await
+        let filter = self
+            .task_service
+            .filter_from_arguments(&arguments, &["add_tags", "remove_tags"])
+            .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+        let add_tags = optional_string_array(&arguments, "add_tags")?;
+        let remove_tags = optional_string_array(&arguments, "remove_tags")?;
+
+        let tasks_updated = self
+            .task_service
+            .bulk_tag(&filter, &add_tags, &remove_tags)
+            .await
+            .map_err(|e| service_error("Failed to update tags", e))?;
+
+        let response = serde_json::json!({
+            "filter": filter,
+            "tasks_updated": tasks_updated
+        });
+
+        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the delete_task tool call
+    async fn handle_delete_task(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let task_id = arguments
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: id", None))?;
+
+        let confirmed = optional_bool(&arguments, "confirm")?;
+
+        if optional_bool(&arguments, "dry_run")? {
+            let task = self
+                .task_service
+                .find_task_by_id(task_id)
+                .await
+                .map_err(|e| service_error("Failed to load tasks", e))?;
+
+            let response = serde_json::json!({
+                "dry_run": true,
+                "would_delete": task
+            });
+
+            let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+                McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+            })?;
+
+            return Ok(CallToolResult::success(vec![Content::text(response_text)]));
+        }
+
+        if !confirmed {
+            return Ok(CallToolResult::success(vec![Content::text(format!(
+                "Deleting task {} removes it from the task file; undo can restore it only until the next change or a server restart. Re-invoke delete_task with \"confirm\": true to proceed.",
+                task_id
+            ))]));
+        }
+
+        let deleted = self
+            .task_service
+            .delete_task(task_id, false)
+            .await
+            .map_err(|e| service_error("Failed to delete task", e))?;
+
+        if !deleted {
+            return Err(McpError::invalid_params(
+                format!("Task not found: {}", task_id),
+                None,
+            ));
+        }
+
+        let response = serde_json::json!({
+            "deleted": task_id
+        });
+
+        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the complete_task tool call
+    async fn handle_complete_task(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let task_id = arguments
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: id", None))?;
+        let force = optional_bool(&arguments, "force")?;
+
+        let (previous_status, next_task) = self
+            .task_service
+            .complete_and_reschedule(task_id, force)
+            .await
+            .map_err(|e| service_error("Failed to update task", e))?;
+
+        let mut response = serde_json::json!({
+            "id": task_id,
+            "previous_status": previous_status,
+            "status": TaskStatus::Completed
+        });
+        if let Some(next_task) = next_task {
+            response["next_task"] = serde_json::to_value(next_task).map_err(|e| {
+                McpError::internal_error(format!("Failed to serialize task: {}", e), None)
+            })?;
+        }
+
+        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the start_task tool call
+    async fn handle_start_task(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        self.set_status(&arguments, TaskStatus::InProgress).await
+    }
+
+    /// Handle the cancel_task tool call
+    async fn handle_cancel_task(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        self.set_status(&arguments, TaskStatus::Cancelled).await
+    }
+
+    /// Handle the reopen_task tool call
+    async fn handle_reopen_task(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        self.set_status(&arguments, TaskStatus::Pending).await
+    }
+
+    /// Shared body of start_task, cancel_task and reopen_task
+    async fn set_status(
+        &self,
+        arguments: &serde_json::Map<String, serde_json::Value>,
+        status: TaskStatus,
+    ) -> Result<CallToolResult, McpError> {
+        let task_id = arguments
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: id", None))?;
+        let force = optional_bool(arguments, "force")?;
+
+        let previous_status = self
+            .task_service
+            .set_status(task_id, status.clone(), force)
+            .await
+            .map_err(|e| service_error("Failed to update task", e))?;
+
+        let response = serde_json::json!({
+            "id": task_id,
+            "previous_status": previous_status,
+            "status": status
+        });
+
+        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the reassign_task tool call
+    async fn handle_reassign_task(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let assignee = arguments
+            .get("assignee")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| {
+                McpError::invalid_params("Missing required parameter: assignee", None)
+            })?;
+
+        self.set_assignee(arguments.get("id"), Some(assignee.to_string()))
+            .await
+    }
+
+    /// Handle the unassign_task tool call
+    async fn handle_unassign_task(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        self.set_assignee(arguments.get("id"), None).await
+    }
+
+    /// Shared body of reassign_task and unassign_task
+    async fn set_assignee(
+        &self,
+        task_id: Option<&serde_json::Value>,
+        assignee: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        let task_id = task_id
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: id", None))?;
+
+        let previous_assignee = self
+            .task_service
+            .set_assignee(task_id, assignee)
+            .await
+            .map_err(|e| service_error("Failed to update task", e))?;
+        let task = self
+            .task_service
+            .find_task_by_id(task_id)
+            .await
+            .map_err(|e| service_error("Failed to load tasks", e))?;
+
+        let response = serde_json::json!({
+            "id": task_id,
+            "previous_assignee": previous_assignee,
+            "assignee": task.assignee
+        });
+
+        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the set_due_date tool call
+    async fn handle_set_due_date(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let task_id = arguments
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: id", None))?;
+        // An explicit null clears the due date, like an empty string
+        let due = match arguments.get("due") {
+            Some(serde_json::Value::Null) => "",
+            due => due
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| McpError::invalid_params("Missing required parameter: due", None))?,
+        };
+
+        let task = self
+            .task_service
+            .set_due_date(task_id, due, self.task_service.now())
+            .await
+            .map_err(|e| service_error("Failed to update task", e))?;
+
+        let response = serde_json::json!({
+            "id": task_id,
+            "due_date": task.due_date
+        });
+
+        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the reorder_task tool call
+    async fn handle_reorder_task(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let task_id = arguments
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: id", None))?;
+        let order = optional_integer(&arguments, "order")?
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: order", None))?;
+
+        let task = self
+            .task_service
+            .reorder_task(task_id, order)
+            .await
+            .map_err(|e| service_error("Failed to update task", e))?;
+
+        let response = serde_json::json!({
+            "id": task_id,
+            "order": task.order
+        });
+
+        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the overdue_tasks tool call
+    async fn handle_overdue_tasks(&self) -> Result<CallToolResult, McpError> {
+        let overdue = self
+            .task_service
+            .overdue_tasks(self.task_service.now())
+            .await
+            .map_err(|e| service_error("Failed to load tasks", e))?;
+
+        let response = serde_json::json!({
+            "count": overdue.len(),
+            "tasks": overdue
+        });
+
+        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the due_soon tool call
+    async fn handle_due_soon(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let days = match optional_integer(&arguments, "days")? {
+            None => DEFAULT_DUE_SOON_DAYS,
+            Some(days) => u32::try_from(days).map_err(|_| {
+                McpError::invalid_params("days must be a non-negative integer", None)
+            })?,
+        };
+
+        let due = self
+            .task_service
+            .due_within(self.task_service.now(), days)
+            .await
+            .map_err(|e| service_error("Failed to load tasks", e))?;
+
+        let response = serde_json::json!({
+            "days": days,
+            "count": due.len(),
+            "tasks": due
+        });
+
+        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the stale_tasks tool call
+    async fn handle_stale_tasks(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let days = optional_integer(&arguments, "days")?
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: days", None))?;
+        let days = u32::try_from(days)
+            .map_err(|_| McpError::invalid_params("days must be a non-negative integer", None))?;
+
+        let stale = self
+            .task_service
+            .stale(self.task_service.now(), days)
+            .await
+            .map_err(|e| service_error("Failed to load tasks", e))?;
+
+        let response = serde_json::json!({
+            "days": days,
+            "count": stale.len(),
+            "tasks": stale
+        });
+
+        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the tasks_due_on tool call
+    async fn handle_tasks_due_on(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let date = arguments
+            .get("date")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: date", None))?;
+        let day = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").map_err(|_| {
+            McpError::invalid_params(
+                format!("Invalid date: {} (expected YYYY-MM-DD)", date),
+                None,
+            )
+        })?;
+
+        let due = self
+            .task_service
+            .due_on(day)
+            .await
+            .map_err(|e| service_error("Failed to load tasks", e))?;
+
+        let response = serde_json::json!({
+            "date": day.to_string(),
+            "count": due.len(),
+            "tasks": due
+        });
+
+        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the export_csv tool call
+    async fn handle_export_csv(&self) -> Result<CallToolResult, McpError> {
+        let csv = self
+            .task_service
+            .export_csv()
+            .await
+            .map_err(|e| service_error("Failed to export tasks", e))?;
+
+        Ok(CallToolResult::success(vec![Content::text(csv)]))
+    }
+
+    /// Handle the import_csv tool call
+    async fn handle_import_csv(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let data = arguments
+            .get("csv")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: csv", None))?;
+        let mode = arguments
+            .get("mode")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: mode", None))?
+            .parse::<ImportMode>()
+            .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+
+        let summary = self
+            .task_service
+            .import_csv(data, mode)
+            .await
+            .map_err(|e| service_error("Failed to import tasks", e))?;
+
+        let response_text = serde_json::to_string_pretty(&summary).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the export_ical tool call
+    async fn handle_export_ical(&self) -> Result<CallToolResult, McpError> {
+        let ical = self
+            .task_service
+            .export_ical()
+            .await
+            .map_err(|e| service_error("Failed to export tasks", e))?;
+
+        Ok(CallToolResult::success(vec![Content::text(ical)]))
+    }
+
+    /// Handle the export_markdown tool call
+    async fn handle_export_markdown(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let group_by = match arguments.get("group_by") {
+            None | Some(serde_json::Value::Null) => None,
+            Some(serde_json::Value::String(group_by)) => Some(group_by.as_str()),
+            Some(_) => {
+                return Err(McpError::invalid_params("group_by must be a string", None));
+            }
+        };
+
+        let markdown = self
+            .task_service
+            .export_markdown(group_by)
+            .await
+            .map_err(|e| service_error("Failed to export tasks", e))?;
+
+        Ok(CallToolResult::success(vec![Content::text(markdown)]))
+    }
+
+    /// Handle the next_task tool call
+    async fn handle_next_task(&self) -> Result<CallToolResult, McpError> {
+        let next = self
+            .task_service
+            .next_task()
+            .await
+            .map_err(|e| service_error("Failed to load tasks", e))?;
+
+        let Some(task) = next else {
+            return Ok(CallToolResult::success(vec![Content::text(
+                "No actionable tasks: every task is completed, cancelled or blocked by a dependency.",
+            )]));
+        };
+
+        let response = serde_json::json!({
+            "task": task
+        });
+
+        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize task: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the blocked_tasks tool call
+    async fn handle_blocked_tasks(&self) -> Result<CallToolResult, McpError> {
+        let blocked = self
+            .task_service
+            .blocked_tasks()
+            .await
+            .map_err(|e| service_error("Failed to load tasks", e))?;
+
+        let response = serde_json::json!({
+            "count": blocked.len(),
+            "tasks": blocked
+        });
+
+        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the task_stats tool call
+    async fn handle_task_stats(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let format = format_argument(&arguments)?;
+        let stats = self
+            .task_service
+            .get_task_statistics()
+            .await
+            .map_err(|e| service_error("Failed to get task statistics", e))?;
+
+        if format == ListFormat::Markdown {
+            return Ok(CallToolResult::success(vec![Content::text(
+                stats.format_stats(),
+            )]));
+        }
+
+        let response_text = serde_json::to_string_pretty(&stats).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize statistics: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the deduplicate tool call
+    async fn handle_deduplicate(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let fix = optional_bool(&arguments, "fix")?;
+
+        let response = if fix {
+            let renamed = self
+                .task_service
+                .deduplicate_ids()
+                .await
+                .map_err(|e| service_error("Failed to deduplicate task IDs", e))?;
+            let renamed: Vec<serde_json::Value> = renamed
+                .into_iter()
+                .map(|(from, to)| serde_json::json!({ "from": from, "to": to }))
+                .collect();
+            serde_json::json!({
+                "renamed_count": renamed.len(),
+                "renamed": renamed
+            })
+        } else {
+            let duplicates = self
+                .task_service
+                .find_duplicate_ids()
+                .await
+                .map_err(|e| service_error("Failed to load tasks", e))?;
+            if !duplicates.is_empty() {
+                return Err(McpError::invalid_params(
+                    format!(
+                        "Duplicate task IDs: {}. Re-invoke deduplicate with \"fix\": true to give the later copies fresh IDs.",
+                        duplicates.join(", ")
+                    ),
+                    Some(serde_json::json!({ "duplicates": duplicates })),
+                ));
+            }
+            serde_json::json!({ "duplicates": duplicates })
+        };
+
+        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the archive_completed tool call
+    async fn handle_archive_completed(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let include_cancelled = optional_bool(&arguments, "include_cancelled")?;
+
+        let archived = self
+            .task_service
+            .archive_completed(include_cancelled)
+            .await
+            .map_err(|e| service_error("Failed to archive tasks", e))?;
+
+        let response = serde_json::json!({ "archived": archived });
+
+        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the restore_task tool call
+    async fn handle_restore_task(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let task_id = arguments
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: id", None))?;
+        let keep_status = optional_bool(&arguments, "keep_status")?;
+
+        let task = self
+            .task_service
+            .restore_task(task_id, keep_status)
+            .await
+            .map_err(|e| service_error("Failed to restore task", e))?;
+
+        let response_text = serde_json::to_string_pretty(&task).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize task: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the task_schema tool call
+    #[cfg(feature = "schema")]
+    async fn handle_task_schema(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let schema = match arguments.get("model").and_then(|v| v.as_str()) {
+            None | Some("task") => schemars::schema_for!(Task),
+            Some("collection") => schemars::schema_for!(crate::models::TaskCollection),
+            Some(other) => {
+                return Err(McpError::invalid_params(
+                    format!("Invalid model: {} (expected task or collection)", other),
+                    None,
+                ));
+            }
+        };
+
+        let response_text = serde_json::to_string_pretty(&schema).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize schema: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the compact_storage tool call
+    async fn handle_compact_storage(&self) -> Result<CallToolResult, McpError> {
+        let (before, after) = self
+            .task_service
+            .compact_storage()
+            .await
+            .map_err(|e| service_error("Failed to compact task file", e))?;
+
+        let response = serde_json::json!({
+            "bytes_before": before,
+            "bytes_after": after,
+            "bytes_saved": before.saturating_sub(after)
+        });
+
+        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Run a tool call inside a `tool_call` span recording the tool name and argument count,
+    /// then log its outcome and elapsed time
+    async fn run_tool(&self, request: CallToolRequestParam) -> Result<CallToolResult, McpError> {
+        let span = info_span!(
+            "tool_call",
+            tool = %request.name,
+            arguments = request.arguments.as_ref().map_or(0, |a| a.len()),
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+        );
+        let started = Instant::now();
+        let result = self.dispatch_tool(request).instrument(span.clone()).await;
+        let elapsed_ms = started.elapsed().as_millis() as u64;
+
+        let status = match &result {
+            Ok(result) if result.is_error != Some(true) => "ok",
+            _ => "error",
+        };
+        span.record("status", status);
+        span.record("elapsed_ms", elapsed_ms);
+        match &result {
+            Ok(_) => info!(parent: &span, status, elapsed_ms, "Tool call finished"),
+            Err(e) => {
+                warn!(parent: &span, status, elapsed_ms, error = %e.message, "Tool call failed")
+            }
+        }
+        result
+    }
+
+    /// Route a tool call to its handler
+    async fn dispatch_tool(
+        &self,
+        request: CallToolRequestParam,
+    ) -> Result<CallToolResult, McpError> {
+        if !self.offers_tool(&request.name) {
+            let reason = if self.read_only && MUTATING_TOOLS.contains(&request.name.as_ref()) {
+                "the server is in read-only mode"
+            } else {
+                "it is not enabled on this server"
+            };
+            return Err(McpError::invalid_request(
+                format!("Tool {} is not available: {}", request.name, reason),
+                None,
+            ));
+        }
+
+        match request.name.as_ref() {
+            "list_tasks" => self.handle_list_tasks(request.arguments).await,
+            "count_tasks" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_count_tasks(arguments).await
+            }
+            "group_tasks" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_group_tasks(arguments).await
+            }
+            "get_task" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_get_task(arguments).await
+            }
+            "get_tasks_batch" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_get_tasks_batch(arguments).await
+            }
+            "subtasks" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_subtasks(arguments).await
+            }
+            "delete_task" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_delete_task(arguments).await
+            }
+            "create_task" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_create_task(arguments).await
+            }
+            "update_task" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_update_task(arguments).await
+            }
+            "apply_patch" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_apply_patch(arguments).await
+            }
+            "add_comment" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_add_comment(arguments).await
+            }
+            "add_tag" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_add_tag(arguments).await
+            }
+            "remove_tag" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_remove_tag(arguments).await
+            }
+            "rename_tag" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_rename_tag(arguments).await
+            }
+            "bulk_tag" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_bulk_tag(arguments).await
+            }
+            "complete_task" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_complete_task(arguments).await
+            }
+            "start_task" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_start_task(arguments).await
+            }
+            "cancel_task" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_cancel_task(arguments).await
+            }
+            "reopen_task" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_reopen_task(arguments).await
+            }
+            "reassign_task" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_reassign_task(arguments).await
+            }
+            "set_due_date" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_set_due_date(arguments).await
+            }
+            "reorder_task" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_reorder_task(arguments).await
+            }
+            "unassign_task" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_unassign_task(arguments).await
+            }
+            "overdue_tasks" => self.handle_overdue_tasks().await,
+            "due_soon" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_due_soon(arguments).await
+            }
+            "stale_tasks" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_stale_tasks(arguments).await
+            }
+            "tasks_due_on" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_tasks_due_on(arguments).await
+            }
+            "next_task" => self.handle_next_task().await,
+            "blocked_tasks" => self.handle_blocked_tasks().await,
+            "export_csv" => self.handle_export_csv().await,
+            "import_csv" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_import_csv(arguments).await
+            }
+            "export_ical" => self.handle_export_ical().await,
+            "export_markdown" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_export_markdown(arguments).await
+            }
+            "task_stats" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_task_stats(arguments).await
+            }
+            "archive_completed" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_archive_completed(arguments).await
+            }
+            "restore_task" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_restore_task(arguments).await
+            }
+            "deduplicate" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_deduplicate(arguments).await
+            }
+            "compact_storage" => self.handle_compact_storage().await,
+            "undo" => self.handle_undo().await,
+            "workload" => self.handle_workload().await,
+            "list_facets" => self.handle_list_facets().await,
+            "daily_digest" => self.handle_daily_digest().await,
+            "search_tasks" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_search_tasks(arguments).await
+            }
+            "tasks_by_tag" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_tasks_by_tag(arguments).await
+            }
+            "health_check" => self.handle_health_check().await,
+            #[cfg(feature = "schema")]
+            "task_schema" => {
+                let arguments = request.arguments.unwrap_or_default();
+                self.handle_task_schema(arguments).await
+            }
+            _ => Err(McpError::method_not_found::<CallToolRequestMethod>()),
+        }
+    }
+
+    /// Handle the undo tool call
+    async fn handle_undo(&self) -> Result<CallToolResult, McpError> {
+        let restored = self
+            .task_service
+            .undo()
+            .await
+            .map_err(|e| service_error("Failed to undo", e))?;
+
+        let response = serde_json::json!({
+            "undone": true,
+            "task_count": restored.tasks.len()
+        });
+
+        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the tasks_by_tag tool call
+    async fn handle_tasks_by_tag(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let tag = arguments
+            .get("tag")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: tag", None))?;
+
+        let related = self
+            .task_service
+            .related_tags(tag)
+            .await
+            .map_err(|e| service_error("Failed to load tasks", e))?;
+        let task_collection = self
+            .task_service
+            .snapshot()
+            .await
+            .map_err(|e| service_error("Failed to load tasks", e))?;
+        let filter = TaskFilter {
+            tag: Some(tag.trim().to_string()),
+            ..Default::default()
+        };
+        let tasks = self
+            .task_service
+            .filter_tasks(&task_collection.tasks, &filter);
+
+        let related: Vec<serde_json::Value> = related
+            .into_iter()
+            .map(|(tag, count)| serde_json::json!({ "tag": tag, "count": count }))
+            .collect();
+        let response = serde_json::json!({
+            "tag": tag.trim(),
+            "count": tasks.len(),
+            "tasks": tasks,
+            "related_tags": related
+        });
+
+        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the search_tasks tool call
+    async fn handle_search_tasks(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult, McpError> {
+        let query = arguments
+            .get("query")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing required parameter: query", None))?;
+
+        let limit = optional_integer(&arguments, "limit")?;
+        if limit.is_some_and(|limit| limit <= 0) {
+            return Err(McpError::invalid_params(
+                "limit must be a positive integer",
+                None,
+            ));
+        }
+        let format = format_argument(&arguments)?;
+
+        let mut hits = self
+            .task_service
+            .search(query)
+            .await
+            .map_err(|e| service_error("Failed to search tasks", e))?;
+        let total = hits.len();
+        if let Some(limit) = limit {
+            hits.truncate(limit as usize);
+        }
+
+        if format == ListFormat::Markdown {
+            let response_text = format!(
+                "Found {} tasks matching \"{}\"\n\n{}",
+                total,
+                query,
+                self.task_service.format_search_hits(query, &hits)
+            );
+            return Ok(CallToolResult::success(vec![Content::text(response_text)]));
+        }
+
+        let response = serde_json::json!({
+            "query": query,
+            "total": total,
+            "results": hits
+        });
+
+        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the workload tool call
+    async fn handle_workload(&self) -> Result<CallToolResult, McpError> {
+        let workload = self
+            .task_service
+            .workload()
+            .await
+            .map_err(|e| service_error("Failed to load tasks", e))?;
+
+        let response = serde_json::json!({ "workload": workload });
+
+        let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the list_facets tool call
+    async fn handle_list_facets(&self) -> Result<CallToolResult, McpError> {
+        let facets = self
+            .task_service
+            .facets()
+            .await
+            .map_err(|e| service_error("Failed to load tasks", e))?;
+
+        let response_text = serde_json::to_string_pretty(&facets).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the daily_digest tool call
+    async fn handle_daily_digest(&self) -> Result<CallToolResult, McpError> {
+        let digest = self
+            .task_service
+            .daily_digest(self.task_service.now())
+            .await
+            .map_err(|e| service_error("Failed to load tasks", e))?;
+
+        let response_text = serde_json::to_string_pretty(&digest).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize digest: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// Handle the health_check tool call
+    async fn handle_health_check(&self) -> Result<CallToolResult, McpError> {
+        let report = self
+            .task_service
+            .health()
+            .await
+            .map_err(|e| service_error("Health check failed", e))?;
+
+        let response_text = serde_json::to_string_pretty(&report).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize health report: {}", e), None)
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response_text)]))
+    }
+
+    /// List the task collection resource followed by one resource per task
+    async fn task_resources(&self) -> Result<Vec<Resource>, McpError> {
+        let task_collection = self
+            .task_service
+            .snapshot()
+            .await
+            .map_err(|e| service_error("Failed to load tasks", e))?;
+
+        let mut collection = RawResource::new(TASKS_RESOURCE_URI, "tasks.json");
+        collection.description = Some("The full task collection".to_string());
+        collection.mime_type = Some("application/json".to_string());
+
+        let mut resources = vec![collection.no_annotation()];
+        resources.extend(task_collection.tasks.iter().map(|task| {
+            let mut resource =
+                RawResource::new(format!("{}{}", TASK_RESOURCE_PREFIX, task.id), &task.title);
+            resource.mime_type = Some("application/json".to_string());
+            resource.no_annotation()
+        }));
+        Ok(resources)
+    }
+
+    /// Read `tasks://all` or a single `task://<id>` resource as JSON
+    async fn read_task_resource(&self, uri: &str) -> Result<ReadResourceResult, McpError> {
+        let response_text = if uri == TASKS_RESOURCE_URI {
+            let task_collection = self
+                .task_service
+                .snapshot()
+                .await
+                .map_err(|e| service_error("Failed to load tasks", e))?;
+            serde_json::to_string_pretty(&task_collection)
+        } else if let Some(task_id) = uri.strip_prefix(TASK_RESOURCE_PREFIX) {
+            let task = match self.task_service.find_task_by_id(task_id).await {
+                Ok(task) => task,
+                Err(TaskError::NotFound(_)) => {
+                    return Err(McpError::resource_not_found(
+                        format!("Task not found: {}", task_id),
+                        Some(serde_json::json!({ "uri": uri })),
+                    ));
+                }
+                Err(e) => return Err(service_error("Failed to load task", e)),
+            };
+            serde_json::to_string_pretty(&task)
+        } else {
+            return Err(McpError::resource_not_found(
+                format!("Unknown resource: {}", uri),
+                Some(serde_json::json!({ "uri": uri })),
+            ));
+        }
+        .map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize resource: {}", e), None)
+        })?;
+
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::text(response_text, uri)],
+        })
+    }
+
+    /// The prompt templates offered to clients
+    fn task_prompts() -> Vec<Prompt> {
+        vec![
+            Prompt::new(
+                "overdue_summary",
+                Some("Summarize the overdue tasks and suggest what to tackle first"),
+                None,
+            ),
+            Prompt::new(
+                "daily_standup",
+                Some(
+                    "Draft a daily standup report from in-progress, recently completed, overdue and blocked tasks",
+                ),
+                None,
+            ),
+        ]
+    }
+
+    /// Render a prompt with the current task data, as of `now`
+    async fn render_prompt(
+        &self,
+        name: &str,
+        now: DateTime<Utc>,
+    ) -> Result<GetPromptResult, McpError> {
+        let overdue = self
+            .task_service
+            .overdue_tasks(now)
+            .await
+            .map_err(|e| service_error("Failed to load tasks", e))?;
+
+        let (description, text) = match name {
+            "overdue_summary" => (
+                "Summary of overdue tasks",
+                format!(
+                    "These tasks are past their due date as of {}:\n\n{}\n\n\
+                     Summarize them, point out any patterns (such as one assignee or tag \
+                     dominating), and suggest which to tackle first.",
+                    now.format("%Y-%m-%d %H:%M UTC"),
+                    self.task_service.format_task_list(&overdue)
+                ),
+            ),
+            "daily_standup" => {
+                let task_collection = self
+                    .task_service
+                    .snapshot()
+                    .await
+                    .map_err(|e| service_error("Failed to load tasks", e))?;
+                let since = now - Duration::hours(STANDUP_LOOKBACK_HOURS);
+                let in_progress: Vec<Task> = task_collection
+                    .tasks
+                    .iter()
+                    .filter(|t| t.status == TaskStatus::InProgress)
+                    .cloned()
+                    .collect();
+                let completed: Vec<Task> = task_collection
+                    .tasks
+                    .iter()
+                    .filter(|t| {
+                        t.status == TaskStatus::Completed
+                            && parse_timestamp(&t.updated_at)
+                                .is_some_and(|updated| updated >= since)
+                    })
+                    .cloned()
+                    .collect();
+                let blocked = self
+                    .task_service
+                    .blocked_tasks()
+                    .await
+                    .map_err(|e| service_error("Failed to load tasks", e))?;
+                (
+                    "Daily standup report",
+                    format!(
+                        "Write a short daily standup report for {}.\n\n\
+                         ## Completed in the last {} hours\n{}\n\n\
+                         ## In progress\n{}\n\n\
+                         ## Overdue\n{}\n\n\
+                         ## Blocked\n{}",
+                        now.format("%Y-%m-%d"),
+                        STANDUP_LOOKBACK_HOURS,
+                        self.task_service.format_task_list(&completed),
+                        self.task_service.format_task_list(&in_progress),
+                        self.task_service.format_task_list(&overdue),
+                        self.task_service.format_task_list(&blocked)
+                    ),
+                )
+            }
+            other => {
+                return Err(McpError::invalid_params(
+                    format!("Unknown prompt: {}", other),
+                    None,
+                ));
+            }
+        };
+
+        Ok(GetPromptResult {
+            description: Some(description.to_string()),
+            messages: vec![PromptMessage::new_text(PromptMessageRole::User, text)],
+        })
+    }
+}
+
+/// Input schema of a tool taking the task filter keys ([`TaskFilter::KEYS`]) plus
+/// `extra_properties`; list_tasks, count_tasks and bulk_tag share it so their filters
+/// stay the same
+fn filter_tool_schema(
+    extra_properties: serde_json::Value,
+) -> serde_json::Map<String, serde_json::Value> {
+    let mut properties = match serde_json::json!({
+        "status": {
+            "type": "string",
+            "enum": ["pending", "in_progress", "completed", "cancelled"],
+            "description": "Only tasks with this status"
+        },
+        "priority": {
+            "type": "string",
+            "enum": ["low", "medium", "high", "critical"],
+            "description": "Only tasks with this priority"
+        },
+        "assignee": {
+            "type": "string",
+            "description": "Only tasks assigned to this person"
+        },
+        "unassigned": {
+            "type": "boolean",
+            "description": "Only tasks without an assignee; can't be combined with assignee (default: false)"
+        },
+        "has_due_date": {
+            "type": "boolean",
+            "description": "true for only tasks with a due date, false for only tasks without one; omit to include both"
+        },
+        "tag": {
+            "type": "string",
+            "description": "Only tasks carrying this tag"
+        },
+        "all_tags": {
+            "type": "array",
+            "items": { "type": "string" },
+            "description": "Only tasks carrying every one of these tags"
+        },
+        "fuzzy": {
+            "type": "boolean",
+            "description": "Match assignee ignoring case and surrounding whitespace, and tag as a case-insensitive substring (default: false)"
+        },
+        "match": {
+            "type": "string",
+            "enum": ["all", "any"],
+            "description": "all (default) keeps tasks meeting every filter given; any keeps tasks meeting at least one"
+        },
+        "created_after": {
+            "type": "string",
+            "description": "Only tasks created at or after this RFC 3339 timestamp"
+        },
+        "created_before": {
+            "type": "string",
+            "description": "Only tasks created at or before this RFC 3339 timestamp"
+        },
+        "updated_after": {
+            "type": "string",
+            "description": "Only tasks updated at or after this RFC 3339 timestamp"
+        },
+        "updated_before": {
+            "type": "string",
+            "description": "Only tasks updated at or before this RFC 3339 timestamp"
+        }
+    }) {
+        serde_json::Value::Object(map) => map,
+        _ => panic!("Filter properties must be an object"),
+    };
+    if let serde_json::Value::Object(extra) = extra_properties {
+        properties.extend(extra);
+    }
+
+    let schema = serde_json::json!({
+        "type": "object",
+        "properties": properties,
+        "additionalProperties": false
+    });
+    match schema {
+        serde_json::Value::Object(map) => map,
+        _ => panic!("Schema must be an object"),
+    }
+}
+
+/// Definitions of every tool the server offers, as returned by `tools/list`
+pub fn build_tool_definitions() -> Vec<Tool> {
+    let mut tools = vec![
+        Tool {
+            name: "list_tasks".into(),
+            description: Some(
+                "List all tasks, optionally filtered by status, priority, assignee, or tag and paginated with limit/offset"
+                    .into(),
+            ),
+            input_schema: Arc::new(filter_tool_schema(serde_json::json!({
+                "limit": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "description": "Maximum number of tasks to return"
+                },
+                "offset": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "description": "Number of matching tasks to skip before the returned page"
+                },
+                "sort_by": {
+                    "type": "string",
+                    "enum": ["order"],
+                    "description": "Sort the matching tasks before paging; order sorts by manual position, with unpositioned tasks last (default: file order)"
+                },
+                "format": {
+                    "type": "string",
+                    "enum": ["json", "markdown"],
+                    "description": "Output format (default: json)"
+                }
+            }))),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "count_tasks".into(),
+            description: Some(
+                "Count tasks matching the same filters as list_tasks; returns just the number"
+                    .into(),
+            ),
+            input_schema: Arc::new(filter_tool_schema(serde_json::json!({}))),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "group_tasks".into(),
+            description: Some(
+                "Return tasks bucketed by status, priority, assignee or tag, as a map from group to tasks"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "group_by": {
+                            "type": "string",
+                            "enum": ["status", "priority", "assignee", "tag"],
+                            "description": "Field to group by; with tag, a task appears under each of its tags"
+                        }
+                    },
+                    "required": ["group_by"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "get_task".into(),
+            description: Some("Get detailed information about a specific task by ID".into()),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {
+                            "type": "string",
+                            "description": "The task ID"
+                        },
+                        "compact": {
+                            "type": "boolean",
+                            "description": "Return single-line JSON to save tokens (default false)"
+                        }
+                    },
+                    "required": ["id"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "get_tasks_batch".into(),
+            description: Some(
+                "Get several tasks by ID in one call, also reporting which IDs were not found"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "ids": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "The task IDs to fetch"
+                        }
+                    },
+                    "required": ["ids"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "subtasks".into(),
+            description: Some("List the direct subtasks of a task".into()),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {
+                            "type": "string",
+                            "description": "The parent task ID"
+                        }
+                    },
+                    "required": ["id"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "create_task".into(),
+            description: Some(
+                "Create a task; priority and status default to the server's configured values"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "title": {
+                            "type": "string",
+                            "description": "Task title"
+                        },
+                        "description": {
+                            "type": "string",
+                            "description": "Task description (default: empty)"
+                        },
+                        "status": {
+                            "type": "string",
+                            "enum": ["pending", "in_progress", "completed", "cancelled"],
+                            "description": "Initial status (default: TASKS_DEFAULT_STATUS, normally pending)"
+                        },
+                        "priority": {
+                            "type": "string",
+                            "enum": ["low", "medium", "high", "critical"],
+                            "description": "Priority (default: TASKS_DEFAULT_PRIORITY, normally medium)"
+                        }
+                    },
+                    "required": ["title"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "update_task".into(),
+            description: Some(
+                "Update selected fields of a task; fields that are omitted keep their current value"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {
+                            "type": "string",
+                            "description": "The task ID"
+                        },
+                        "title": {
+                            "type": "string",
+                            "description": "New title"
+                        },
+                        "description": {
+                            "type": "string",
+                            "description": "New description"
+                        },
+                        "status": {
+                            "type": "string",
+                            "enum": ["pending", "in_progress", "completed", "cancelled"],
+                            "description": "New status"
+                        },
+                        "priority": {
+                            "type": "string",
+                            "enum": ["low", "medium", "high", "critical"],
+                            "description": "New priority"
+                        },
+                        "assignee": {
+                            "type": "string",
+                            "description": "New assignee"
+                        },
+                        "due_date": {
+                            "type": ["string", "null"],
+                            "description": "New due date as an RFC 3339 timestamp; null or an empty string removes it"
+                        },
+                        "tags": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Replacement tag list"
+                        },
+                        "metadata": {
+                            "type": "object",
+                            "description": "Custom fields to set; other keys are kept and null removes a key"
+                        },
+                        "force": {
+                            "type": "boolean",
+                            "description": "Change the status even if the transition rules forbid it (default: false)"
+                        },
+                        "dry_run": {
+                            "type": "boolean",
+                            "description": "Report the changes that would be made without saving them"
+                        }
+                    },
+                    "required": ["id"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "apply_patch".into(),
+            description: Some(
+                "Edit a task with an RFC 6902 JSON Patch applied to its JSON form, e.g. to append a tag with /tags/-"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {
+                            "type": "string",
+                            "description": "The task ID"
+                        },
+                        "patch": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "op": {
+                                        "type": "string",
+                                        "enum": ["add", "remove", "replace", "move", "copy", "test"]
+                                    },
+                                    "path": { "type": "string" },
+                                    "from": { "type": "string" },
+                                    "value": {}
+                                },
+                                "required": ["op", "path"]
+                            },
+                            "description": "JSON Patch operations, applied in order; if any fails nothing is saved"
+                        },
+                        "force": {
+                            "type": "boolean",
+                            "description": "Change the status even if the transition rules forbid it (default: false)"
+                        }
+                    },
+                    "required": ["id", "patch"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "add_comment".into(),
+            description: Some("Append a comment to a task's notes history".into()),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {
+                            "type": "string",
+                            "description": "The task ID"
+                        },
+                        "body": {
+                            "type": "string",
+                            "description": "Comment text"
+                        },
+                        "author": {
+                            "type": "string",
+                            "description": "Who wrote the comment"
+                        }
+                    },
+                    "required": ["id", "body"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "add_tag".into(),
+            description: Some(
+                "Add a tag to a task without resending its other tags; adding an existing tag is a no-op".into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {
+                            "type": "string",
+                            "description": "The task ID"
+                        },
+                        "tag": {
+                            "type": "string",
+                            "description": "Tag to add"
+                        }
+                    },
+                    "required": ["id", "tag"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "remove_tag".into(),
+            description: Some(
+                "Remove a tag from a task; removing a tag the task doesn't have is a no-op".into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {
+                            "type": "string",
+                            "description": "The task ID"
+                        },
+                        "tag": {
+                            "type": "string",
+                            "description": "Tag to remove"
+                        }
+                    },
+                    "required": ["id", "tag"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "rename_tag".into(),
+            description: Some(
+                "Rename a tag across all tasks, merging it into the new tag where a task already has both".into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "from": {
+                            "type": "string",
+                            "description": "Tag to rename"
+                        },
+                        "to": {
+                            "type": "string",
+                            "description": "New tag name"
+                        }
+                    },
+                    "required": ["from", "to"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "bulk_tag".into(),
+            description: Some(
+                "Add and remove tags on every task matching a list_tasks-style filter, saving once; returns how many tasks changed"
+                    .into(),
+            ),
+            input_schema: Arc::new(filter_tool_schema(serde_json::json!({
+                "add_tags": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Tags to add to every matching task; tags a task already has are skipped"
+                },
+                "remove_tags": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Tags to remove from every matching task"
+                }
+            }))),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "delete_task".into(),
+            description: Some(
+                "Delete a task by ID. Requires confirm: true, since undo can only restore it until the next change"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {
+                            "type": "string",
+                            "description": "The task ID"
+                        },
+                        "confirm": {
+                            "type": "boolean",
+                            "description": "Must be true to actually delete the task"
+                        },
+                        "dry_run": {
+                            "type": "boolean",
+                            "description": "Show the task that would be deleted without deleting it"
+                        }
+                    },
+                    "required": ["id"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "complete_task".into(),
+            description: Some(
+                "Mark a task as completed and return the status it transitioned from; recurring tasks get a new pending copy with the next due date"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {
+                            "type": "string",
+                            "description": "The task ID"
+                        },
+                        "force": {
+                            "type": "boolean",
+                            "description": "Complete the task even if the transition rules forbid it, e.g. when it was cancelled (default: false)"
+                        }
+                    },
+                    "required": ["id"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "start_task".into(),
+            description: Some(
+                "Mark a task as in progress and return the status it transitioned from"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {
+                            "type": "string",
+                            "description": "The task ID"
+                        },
+                        "force": {
+                            "type": "boolean",
+                            "description": "Start the task even if the transition rules forbid it, e.g. when it was completed (default: false)"
+                        }
+                    },
+                    "required": ["id"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "cancel_task".into(),
+            description: Some(
+                "Mark a task as cancelled and return the status it transitioned from"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {
+                            "type": "string",
+                            "description": "The task ID"
+                        },
+                        "force": {
+                            "type": "boolean",
+                            "description": "Cancel the task even if the transition rules forbid it, e.g. when it was completed (default: false)"
+                        }
+                    },
+                    "required": ["id"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "reopen_task".into(),
+            description: Some(
+                "Move a task back to pending, e.g. to reopen a completed or cancelled task, and return the status it transitioned from"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {
+                            "type": "string",
+                            "description": "The task ID"
+                        },
+                        "force": {
+                            "type": "boolean",
+                            "description": "Accepted for symmetry; reopening as pending is always allowed (default: false)"
+                        }
+                    },
+                    "required": ["id"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "reassign_task".into(),
+            description: Some(
+                "Assign a task to someone else; an empty assignee unassigns the task".into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {
+                            "type": "string",
+                            "description": "The task ID"
+                        },
+                        "assignee": {
+                            "type": "string",
+                            "description": "Username of the new assignee"
+                        }
+                    },
+                    "required": ["id", "assignee"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "unassign_task".into(),
+            description: Some("Remove the assignee from a task".into()),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {
+                            "type": "string",
+                            "description": "The task ID"
+                        }
+                    },
+                    "required": ["id"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "set_due_date".into(),
+            description: Some(
+                "Set a task's due date from a date or a relative expression like \"tomorrow\", \"in 3 days\" or \"next monday\""
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {
+                            "type": "string",
+                            "description": "The task ID"
+                        },
+                        "due": {
+                            "type": ["string", "null"],
+                            "description": "RFC 3339 timestamp, YYYY-MM-DD, today, tomorrow, a weekday, next <weekday>, or in N hours/days/weeks/months; days resolve to 23:59:59 UTC. null or an empty string removes the due date"
+                        }
+                    },
+                    "required": ["id", "due"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "reorder_task".into(),
+            description: Some(
+                "Set a task's position in the manual ordering used by list_tasks with sort_by=order; tasks already at or after that position move back one place"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {
+                            "type": "string",
+                            "description": "The task ID"
+                        },
+                        "order": {
+                            "type": "integer",
+                            "description": "New position, lowest first; use a value at or below the current first to move the task to the top"
+                        }
+                    },
+                    "required": ["id", "order"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "overdue_tasks".into(),
+            description: Some(
+                "List open tasks whose due date has passed (completed and cancelled tasks are excluded)"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "due_soon".into(),
+            description: Some(
+                "List open tasks due within the next N days, soonest first".into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "days": {
+                            "type": "integer",
+                            "minimum": 0,
+                            "description": "Size of the window in days (default 7)"
+                        }
+                    },
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "stale_tasks".into(),
+            description: Some(
+                "List tasks that aren't completed and haven't been updated in more than N days, least recently updated first"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "days": {
+                            "type": "integer",
+                            "minimum": 0,
+                            "description": "Tasks last updated more than this many days ago are stale"
+                        }
+                    },
+                    "required": ["days"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "tasks_due_on".into(),
+            description: Some(
+                "List tasks of any status due on a given UTC calendar day, soonest first".into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "date": {
+                            "type": "string",
+                            "description": "The day as YYYY-MM-DD; tasks due at any time that day (UTC) are returned"
+                        }
+                    },
+                    "required": ["date"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "next_task".into(),
+            description: Some(
+                "Suggest the single open, unblocked task to work on next: highest priority, then earliest due date, then oldest"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "blocked_tasks".into(),
+            description: Some(
+                "List open tasks that can't start yet because a dependency is not completed"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "export_csv".into(),
+            description: Some(
+                "Export all tasks as CSV with a header row, for spreadsheets".into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "import_csv".into(),
+            description: Some(
+                "Import tasks from CSV in the export_csv column layout, merging by id or replacing all tasks"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "csv": {
+                            "type": "string",
+                            "description": "CSV text with a header row; id, title, status and priority columns are required"
+                        },
+                        "mode": {
+                            "type": "string",
+                            "enum": ["merge", "replace"],
+                            "description": "merge upserts by id; replace overwrites every existing task"
+                        }
+                    },
+                    "required": ["csv", "mode"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "export_ical".into(),
+            description: Some(
+                "Export tasks that have a due date as an iCalendar feed of VTODO entries".into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "export_markdown".into(),
+            description: Some(
+                "Export all tasks as a Markdown table, optionally grouped into sections by status, priority, assignee or tag"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "group_by": {
+                            "type": "string",
+                            "enum": ["status", "priority", "assignee", "tag"],
+                            "description": "Emit one table per group under its own heading"
+                        }
+                    },
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "task_stats".into(),
+            description: Some(
+                "Get statistics about tasks (counts by status, priority, etc.)".into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "format": {
+                            "type": "string",
+                            "enum": ["json", "markdown"],
+                            "description": "Output format (default: json)"
+                        }
+                    },
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "archive_completed".into(),
+            description: Some(
+                "Move completed tasks (and optionally cancelled ones) from the task file into the archive file"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "include_cancelled": {
+                            "type": "boolean",
+                            "description": "Archive cancelled tasks as well (default: false)"
+                        }
+                    },
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "restore_task".into(),
+            description: Some(
+                "Move a task from the archive file back into the task file, resetting it to pending"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {
+                            "type": "string",
+                            "description": "ID of the archived task to restore"
+                        },
+                        "keep_status": {
+                            "type": "boolean",
+                            "description": "Keep the archived status instead of resetting it to pending (default: false)"
+                        }
+                    },
+                    "required": ["id"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "deduplicate".into(),
+            description: Some(
+                "Check for tasks sharing an ID; with fix, give every later copy a fresh ID and save"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "fix": {
+                            "type": "boolean",
+                            "description": "Reassign duplicate IDs instead of reporting them as an error (default: false)"
+                        }
+                    },
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "compact_storage".into(),
+            description: Some(
+                "Rewrite the task file in its canonical layout, removing hand-edited formatting, and report the bytes saved"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "undo".into(),
+            description: Some(
+                "Undo the most recent change to the task file; only one step is kept".into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "tasks_by_tag".into(),
+            description: Some(
+                "List the tasks carrying a tag along with the other tags that appear on them, most frequent first"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "tag": {
+                            "type": "string",
+                            "description": "Tag to look up"
+                        }
+                    },
+                    "required": ["tag"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "search_tasks".into(),
+            description: Some(
+                "Search task titles and descriptions, most relevant first; title and whole-word matches rank highest"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "query": {
+                            "type": "string",
+                            "description": "Words to search for (case-insensitive)"
+                        },
+                        "limit": {
+                            "type": "integer",
+                            "minimum": 1,
+                            "description": "Maximum number of results to return"
+                        },
+                        "format": {
+                            "type": "string",
+                            "enum": ["json", "markdown"],
+                            "description": "Output format; markdown emphasizes the matched words (default: json)"
+                        }
+                    },
+                    "required": ["query"],
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "workload".into(),
+            description: Some(
+                "Report each assignee's open task count and priority-weighted load, heaviest first"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "list_facets".into(),
+            description: Some(
+                "List the distinct assignees and tags in use, sorted by name, with how many tasks carry each"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "daily_digest".into(),
+            description: Some(
+                "Standup summary in one call: overdue tasks, tasks due today, in-progress tasks and open task counts per priority"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+        Tool {
+            name: "health_check".into(),
+            description: Some(
+                "Report the task file path, whether it exists, its last-modified time, task count and schema version"
+                    .into(),
+            ),
+            input_schema: Arc::new({
+                let schema = serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                });
+                match schema {
+                    serde_json::Value::Object(map) => map,
+                    _ => panic!("Schema must be an object"),
+                }
+            }),
+            output_schema: None,
+            annotations: None,
+        },
+    ];
+    tools.extend(schema_tool_definitions());
+    tools
+}
+
+/// The task_schema tool, available when built with the `schema` feature
+#[cfg(feature = "schema")]
+fn schema_tool_definitions() -> Vec<Tool> {
+    vec![Tool {
+        name: "task_schema".into(),
+        description: Some(
+            "Get the JSON Schema of a task, or of the whole task file with model=collection".into(),
+        ),
+        input_schema: Arc::new({
+            let schema = serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "model": {
+                        "type": "string",
+                        "enum": ["task", "collection"],
+                        "description": "Which schema to return (default: task)"
+                    }
+                },
+                "additionalProperties": false
+            });
+            match schema {
+                serde_json::Value::Object(map) => map,
+                _ => panic!("Schema must be an object"),
+            }
+        }),
+        output_schema: None,
+        annotations: None,
+    }]
+}
+
+/// Without the `schema` feature there are no schema tools
+#[cfg(not(feature = "schema"))]
+fn schema_tool_definitions() -> Vec<Tool> {
+    Vec::new()
+}
+
+/// Translate a service error into an MCP error
+/// Storage failures get a specific message so the caller knows what went wrong with the task file
+fn service_error(context: &str, error: TaskError) -> McpError {
+    match &error {
+        TaskError::NotFound(_) | TaskError::Validation(_) => {
+            McpError::invalid_params(error.to_string(), None)
+        }
+        TaskError::Storage(StorageError::InvalidJson {
+            line,
+            column,
+            offset,
+            ..
+        }) => McpError::internal_error(
+            format!(
+                "{}: {}. Fix the task file at that position or restore it from a backup",
+                context, error
+            ),
+            Some(serde_json::json!({
+                "line": line,
+                "column": column,
+                "offset": offset
+            })),
+        ),
+        TaskError::Storage(StorageError::NotFound { .. }) => {
+            McpError::resource_not_found(format!("{}: {}", context, error), None)
+        }
+        _ => McpError::internal_error(format!("{}: {}", context, error), None),
+    }
+}
+
+/// Read the `id` and `tag` arguments shared by add_tag and remove_tag
+fn tag_arguments(
+    arguments: &serde_json::Map<String, serde_json::Value>,
+) -> Result<(&str, &str), McpError> {
+    let task_id = arguments
+        .get("id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| McpError::invalid_params("Missing required parameter: id", None))?;
+    let tag = arguments
+        .get("tag")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| McpError::invalid_params("Missing required parameter: tag", None))?;
+    Ok((task_id, tag))
+}
+
+/// Report a task's tags after an add_tag or remove_tag call
+fn tags_response(task_id: &str, tags: Vec<String>) -> Result<CallToolResult, McpError> {
+    let response = serde_json::json!({
+        "id": task_id,
+        "tags": tags
+    });
+
+    let response_text = serde_json::to_string_pretty(&response).map_err(|e| {
+        McpError::internal_error(format!("Failed to serialize response: {}", e), None)
+    })?;
+
+    Ok(CallToolResult::success(vec![Content::text(response_text)]))
+}
+
+/// Read an optional boolean flag such as `dry_run` or `force`, defaulting to `false` and
+/// rejecting values of the wrong type, so a string `"true"` isn't taken as `false`
+fn optional_bool(
+    arguments: &serde_json::Map<String, serde_json::Value>,
+    key: &str,
+) -> Result<bool, McpError> {
+    match arguments.get(key) {
+        None | Some(serde_json::Value::Null) => Ok(false),
+        Some(value) => value
+            .as_bool()
+            .ok_or_else(|| McpError::invalid_params(format!("{} must be a boolean", key), None)),
+    }
+}
+
+/// Describe the fields that differ between two versions of a task as `{field: {from, to}}`
+/// `updated_at` and `history` are left out, since every change bumps them
+fn task_changes(before: &Task, after: &Task) -> serde_json::Map<String, serde_json::Value> {
+    before
+        .changed_fields(after)
+        .into_iter()
+        .map(|(field, from, to)| (field, serde_json::json!({ "from": from, "to": to })))
+        .collect()
+}
+
+/// Output format for task listings and statistics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ListFormat {
+    Json,
+    Markdown,
+}
+
+/// Read the optional `format` argument, defaulting to JSON
+fn format_argument(
+    arguments: &serde_json::Map<String, serde_json::Value>,
+) -> Result<ListFormat, McpError> {
+    match arguments.get("format").and_then(|v| v.as_str()) {
+        None | Some("json") => Ok(ListFormat::Json),
+        Some("markdown") => Ok(ListFormat::Markdown),
+        Some(other) => Err(McpError::invalid_params(
+            format!("Invalid format: {} (expected json or markdown)", other),
+            None,
+        )),
+    }
+}
+
+/// Read a required array-of-strings argument
+fn required_string_array(
+    arguments: &serde_json::Map<String, serde_json::Value>,
+    key: &str,
+) -> Result<Vec<String>, McpError> {
+    let values = arguments
+        .get(key)
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| {
+            McpError::invalid_params(format!("Missing required parameter: {}", key), None)
+        })?;
+
+    values
+        .iter()
+        .map(|value| {
+            value.as_str().map(str::to_string).ok_or_else(|| {
+                McpError::invalid_params(format!("{} must contain only strings", key), None)
+            })
+        })
+        .collect()
+}
+
+/// Read an optional array-of-strings argument; leaving it out gives an empty list
+fn optional_string_array(
+    arguments: &serde_json::Map<String, serde_json::Value>,
+    key: &str,
+) -> Result<Vec<String>, McpError> {
+    match arguments.get(key) {
+        None | Some(serde_json::Value::Null) => Ok(Vec::new()),
+        Some(_) => required_string_array(arguments, key),
+    }
+}
+
+/// Line appended to a list_tasks response that was cut to fit `max_response_chars`
+fn truncation_notice(omitted: usize) -> String {
+    format!("... ({} tasks omitted, refine your filter)", omitted)
+}
+
+/// Read an optional integer argument, rejecting values of the wrong type
+fn optional_integer(
+    arguments: &serde_json::Map<String, serde_json::Value>,
+    key: &str,
+) -> Result<Option<i64>, McpError> {
+    match arguments.get(key) {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(value) => value
+            .as_i64()
+            .map(Some)
+            .ok_or_else(|| McpError::invalid_params(format!("{} must be an integer", key), None)),
+    }
+}
+
+impl ServerHandler for TaskMcpHandler {
+    fn get_info(&self) -> ServerInfo {
+        InitializeResult {
+            protocol_version: ProtocolVersion::LATEST,
+            capabilities: ServerCapabilities::builder()
+                .enable_tools()
+                .enable_resources()
+                .enable_prompts()
+                .build(),
+            server_info: Implementation::from_build_env(),
+            instructions: None,
+        }
+    }
+
+    async fn initialize(
+        &self,
+        _request: InitializeRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<InitializeResult, McpError> {
+        info!("Task Manager MCP server initialized");
+        Ok(InitializeResult {
+            protocol_version: ProtocolVersion::LATEST,
+            capabilities: self.get_info().capabilities,
+            server_info: Implementation::from_build_env(),
+            instructions: None,
+        })
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        Ok(ListToolsResult {
+            tools: self.tool_definitions(),
+            next_cursor: None,
+        })
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        self.run_tool(request).await
+    }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        Ok(ListResourcesResult {
+            resources: self.task_resources().await?,
+            next_cursor: None,
+        })
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        self.read_task_resource(&request.uri).await
+    }
+
+    async fn list_prompts(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListPromptsResult, McpError> {
+        Ok(ListPromptsResult {
+            prompts: Self::task_prompts(),
+            next_cursor: None,
+        })
+    }
+
+    async fn get_prompt(
+        &self,
+        request: GetPromptRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<GetPromptResult, McpError> {
+        self.render_prompt(&request.name, self.task_service.now())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Priority, TaskCollection};
+    use crate::storage::TaskStorage;
+    use tempfile::TempDir;
+
+    fn sample_task(id: &str) -> Task {
+        Task {
+            id: id.to_string(),
+            title: format!("Task {}", id),
+            description: "Sample task".to_string(),
+            status: TaskStatus::Pending,
+            priority: Priority::Medium,
+            created_at: "2024-01-15T09:00:00Z".to_string(),
+            updated_at: "2024-01-15T09:00:00Z".to_string(),
+            tags: vec![],
+            assignee: None,
+            due_date: None,
+            parent_id: None,
+            depends_on: vec![],
+            recurrence: None,
+            comments: vec![],
+            metadata: Default::default(),
+            history: vec![],
+            order: None,
+        }
+    }
+
+    async fn handler_with_tasks(tasks: Vec<Task>) -> (TempDir, TaskMcpHandler) {
+        let dir = TempDir::new().expect("Failed to create temp dir");
+        let storage = TaskStorage::new(dir.path().join("tasks.json"));
+        let mut collection = TaskCollection::new();
+        collection.tasks = tasks;
+        storage
+            .save_tasks(&collection)
+            .await
+            .expect("Failed to save tasks");
+        (dir, TaskMcpHandler::new(TaskService::new(storage)))
+    }
+
+    fn args(value: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+        match value {
+            serde_json::Value::Object(map) => map,
+            _ => panic!("Arguments must be an object"),
+        }
+    }
+
+    fn result_text(result: &CallToolResult) -> String {
+        result.content[0]
+            .as_text()
+            .expect("Expected text content")
+            .text
+            .clone()
+    }
+
+    #[test]
+    fn test_filter_tool_schema_adds_extra_properties() {
+        let schema = filter_tool_schema(serde_json::json!({ "limit": { "type": "integer" } }));
+        let properties = schema["properties"].as_object().unwrap();
+        for key in ["status", "priority", "assignee", "tag", "limit"] {
+            assert!(properties.contains_key(key), "schema lacks {}", key);
+        }
+        assert_eq!(schema["additionalProperties"], false);
+    }
+
+    #[tokio::test]
+    async fn test_count_tasks_matches_list_total() {
+        let mut high = sample_task("task-2");
+        high.priority = Priority::High;
+        let tasks = vec![sample_task("task-1"), high, sample_task("task-3")];
+        let (_dir, handler) = handler_with_tasks(tasks).await;
+
+        let count = result_text(
+            &handler
+                .handle_count_tasks(args(serde_json::json!({ "priority": "medium" })))
+                .await
+                .unwrap(),
+        );
+        let list = result_text(
+            &handler
+                .handle_list_tasks(Some(args(serde_json::json!({ "priority": "medium" }))))
+                .await
+                .unwrap(),
+        );
+        let list: serde_json::Value = serde_json::from_str(&list).unwrap();
+
+        assert_eq!(count, "2");
+        assert_eq!(list["total"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_get_task_compact_output() {
+        let mut task = sample_task("task-1");
+        task.tags = vec!["backend".to_string(), "api".to_string()];
+        let (_dir, handler) = handler_with_tasks(vec![task]).await;
+
+        let pretty = result_text(
+            &handler
+                .handle_get_task(args(serde_json::json!({ "id": "task-1" })))
+                .await
+                .unwrap(),
+        );
+        let compact = result_text(
+            &handler
+                .handle_get_task(args(serde_json::json!({ "id": "task-1", "compact": true })))
+                .await
+                .unwrap(),
+        );
+
+        assert!(compact.len() < pretty.len());
+        assert!(!compact.contains('\n'));
+        let pretty: serde_json::Value = serde_json::from_str(&pretty).unwrap();
+        let compact: serde_json::Value = serde_json::from_str(&compact).unwrap();
+        let task: Task = serde_json::from_value(compact["task"].clone()).unwrap();
+        assert_eq!(task.id, "task-1");
+        assert_eq!(compact, pretty);
+    }
+
+    #[tokio::test]
+    async fn test_update_task_dry_run_describes_change() {
+        let (dir, handler) = handler_with_tasks(vec![sample_task("task-1")]).await;
+        let path = dir.path().join("tasks.json");
+        let before = std::fs::read_to_string(&path).unwrap();
+
+        let result = handler
+            .handle_update_task(args(serde_json::json!({
+                "id": "task-1",
+                "priority": "high",
+                "dry_run": true
+            })))
+            .await
+            .unwrap();
+        let response: serde_json::Value = serde_json::from_str(&result_text(&result)).unwrap();
+
+        assert_eq!(response["dry_run"], true);
+        assert_eq!(
+            response["changes"],
+            serde_json::json!({ "priority": { "from": "medium", "to": "high" } })
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), before);
+    }
+
+    #[tokio::test]
+    async fn test_non_boolean_flags_are_rejected() {
+        let (dir, handler) = handler_with_tasks(vec![sample_task("task-1")]).await;
+        let path = dir.path().join("tasks.json");
+        let before = std::fs::read_to_string(&path).unwrap();
+
+        let err = handler
+            .handle_update_task(args(serde_json::json!({
+                "id": "task-1",
+                "priority": "high",
+                "dry_run": "true"
+            })))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, rmcp::model::ErrorCode::INVALID_PARAMS);
+        assert!(err.message.contains("dry_run must be a boolean"));
+
+        let err = handler
+            .handle_delete_task(args(
+                serde_json::json!({ "id": "task-1", "confirm": "yes" }),
+            ))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, rmcp::model::ErrorCode::INVALID_PARAMS);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), before);
+    }
+
+    #[tokio::test]
+    async fn test_delete_task_requires_confirmation() {
+        let (_dir, handler) = handler_with_tasks(vec![sample_task("task-1")]).await;
+
+        let result = handler
+            .handle_delete_task(args(serde_json::json!({ "id": "task-1" })))
+            .await
+            .expect("Unconfirmed delete should not error");
+        assert!(result_text(&result).contains("confirm"));
+
+        let remaining = handler.task_service.load_tasks().await.unwrap();
+        assert_eq!(remaining.tasks.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_delete_task_with_confirmation() {
+        let (_dir, handler) =
+            handler_with_tasks(vec![sample_task("task-1"), sample_task("task-2")]).await;
+
+        handler
+            .handle_delete_task(args(serde_json::json!({ "id": "task-1", "confirm": true })))
+            .await
+            .expect("Confirmed delete should succeed");
+
+        let remaining = handler.task_service.load_tasks().await.unwrap();
+        assert_eq!(remaining.tasks.len(), 1);
+        assert_eq!(remaining.tasks[0].id, "task-2");
+    }
+
+    /// Records the `tool` field of every `tool_call` span opened while it is the subscriber
+    #[derive(Clone, Default)]
+    struct SpanCapture(Arc<std::sync::Mutex<Vec<String>>>);
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for SpanCapture {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            _id: &tracing::span::Id,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            struct ToolField(Option<String>);
+            impl tracing::field::Visit for ToolField {
+                fn record_debug(&mut self, field: &field::Field, value: &dyn std::fmt::Debug) {
+                    if field.name() == "tool" {
+                        self.0 = Some(format!("{:?}", value));
+                    }
+                }
             }
-            "task_stats" => self.handle_task_stats().await,
-            _ => Err(McpError::method_not_found::<CallToolRequestMethod>()),
+
+            if attrs.metadata().name() != "tool_call" {
+                return;
+            }
+            let mut tool = ToolField(None);
+            attrs.record(&mut tool);
+            if let Some(tool) = tool.0 {
+                self.0.lock().unwrap().push(tool);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_span_records_tool_name() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let (_dir, handler) = handler_with_tasks(vec![sample_task("task-1")]).await;
+        let capture = SpanCapture::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(capture.clone()));
+
+        handler
+            .run_tool(CallToolRequestParam {
+                name: "list_tasks".into(),
+                arguments: None,
+            })
+            .await
+            .expect("list_tasks should succeed");
+
+        let tools = capture.0.lock().unwrap();
+        assert_eq!(*tools, vec!["list_tasks"]);
+    }
+
+    #[tokio::test]
+    async fn test_read_only_hides_and_refuses_mutations() {
+        let (_dir, handler) = handler_with_tasks(vec![sample_task("task-1")]).await;
+        let handler = handler.with_read_only(true);
+
+        let names: Vec<String> = handler
+            .tool_definitions()
+            .iter()
+            .map(|tool| tool.name.to_string())
+            .collect();
+        assert!(!names.iter().any(|name| name == "create_task"));
+        assert!(names.iter().any(|name| name == "list_tasks"));
+
+        let err = handler
+            .run_tool(CallToolRequestParam {
+                name: "create_task".into(),
+                arguments: Some(args(serde_json::json!({ "title": "Sneaky" }))),
+            })
+            .await
+            .expect_err("create_task should be refused");
+        assert_eq!(err.code, rmcp::model::ErrorCode::INVALID_REQUEST);
+        assert!(err.message.contains("read-only"));
+
+        handler
+            .run_tool(CallToolRequestParam {
+                name: "list_tasks".into(),
+                arguments: None,
+            })
+            .await
+            .expect("list_tasks should still work");
+        let tasks = handler.task_service.load_tasks().await.unwrap().tasks;
+        assert_eq!(tasks.len(), 1);
+
+        // Every name in MUTATING_TOOLS must be a real tool
+        let all: Vec<String> = build_tool_definitions()
+            .iter()
+            .map(|tool| tool.name.to_string())
+            .collect();
+        for name in MUTATING_TOOLS {
+            assert!(all.iter().any(|tool| tool == name), "unknown tool {}", name);
+        }
+        assert_eq!(names.len(), all.len() - MUTATING_TOOLS.len());
+    }
+
+    #[tokio::test]
+    async fn test_only_enabled_tools_are_offered() {
+        let (_dir, handler) = handler_with_tasks(vec![sample_task("task-1")]).await;
+        let handler = handler.with_enabled_tools(vec!["task_stats".to_string()]);
+
+        let names: Vec<String> = handler
+            .tool_definitions()
+            .iter()
+            .map(|tool| tool.name.to_string())
+            .collect();
+        assert_eq!(names, vec!["task_stats"]);
+
+        handler
+            .run_tool(CallToolRequestParam {
+                name: "task_stats".into(),
+                arguments: None,
+            })
+            .await
+            .expect("task_stats should be enabled");
+
+        for name in ["list_tasks", "get_task", "create_task"] {
+            let err = handler
+                .run_tool(CallToolRequestParam {
+                    name: name.into(),
+                    arguments: Some(args(serde_json::json!({ "id": "task-1" }))),
+                })
+                .await
+                .expect_err("disabled tool should be refused");
+            assert_eq!(err.code, rmcp::model::ErrorCode::INVALID_REQUEST);
+            assert!(err.message.contains("not enabled"), "{}", err.message);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_markdown_emphasizes_matches() {
+        let mut deploy = sample_task("task-1");
+        deploy.title = "Deploy API".to_string();
+        deploy.description = "Run the deployment checklist".to_string();
+        let (_dir, handler) = handler_with_tasks(vec![deploy, sample_task("task-2")]).await;
+
+        let result = handler
+            .handle_search_tasks(args(
+                serde_json::json!({ "query": "deploy", "format": "markdown" }),
+            ))
+            .await
+            .expect("search_tasks should succeed");
+        let text = result_text(&result);
+
+        assert!(text.starts_with("Found 1 tasks matching \"deploy\""));
+        assert!(text.contains("**Deploy** API (`task-1`)"), "{}", text);
+        assert!(text.contains("the **deploy**ment checklist"), "{}", text);
+        assert!(!text.contains("task-2"));
+    }
+
+    #[tokio::test]
+    async fn test_set_due_date_rejects_unparseable_expression() {
+        let (_dir, handler) = handler_with_tasks(vec![sample_task("task-1")]).await;
+
+        let err = handler
+            .handle_set_due_date(args(
+                serde_json::json!({ "id": "task-1", "due": "when pigs fly" }),
+            ))
+            .await
+            .expect_err("Unparseable due date should fail");
+        assert_eq!(err.code, rmcp::model::ErrorCode::INVALID_PARAMS);
+        assert!(err.message.contains("Unrecognized due date"));
+    }
+
+    #[tokio::test]
+    async fn test_tasks_due_on_rejects_bad_date() {
+        let (_dir, handler) = handler_with_tasks(vec![sample_task("task-1")]).await;
+
+        let err = handler
+            .handle_tasks_due_on(args(serde_json::json!({ "date": "03/01/2024" })))
+            .await
+            .expect_err("A non-ISO date should fail");
+        assert_eq!(err.code, rmcp::model::ErrorCode::INVALID_PARAMS);
+        assert!(err.message.contains("YYYY-MM-DD"));
+    }
+
+    #[tokio::test]
+    async fn test_undo_brings_back_deleted_task() {
+        let (_dir, handler) =
+            handler_with_tasks(vec![sample_task("task-1"), sample_task("task-2")]).await;
+
+        let err = handler
+            .handle_undo()
+            .await
+            .expect_err("Nothing has changed yet");
+        assert!(err.message.contains("Nothing to undo"));
+
+        handler
+            .handle_delete_task(args(serde_json::json!({ "id": "task-1", "confirm": true })))
+            .await
+            .expect("Confirmed delete should succeed");
+        let result = handler.handle_undo().await.expect("Undo should succeed");
+        let response: serde_json::Value = serde_json::from_str(&result_text(&result)).unwrap();
+        assert_eq!(response["task_count"], 2);
+
+        let task = handler
+            .task_service
+            .find_task_by_id("task-1")
+            .await
+            .unwrap();
+        assert_eq!(task.id, "task-1");
+    }
+
+    #[tokio::test]
+    async fn test_delete_nonexistent_task() {
+        let (_dir, handler) = handler_with_tasks(vec![sample_task("task-1")]).await;
+
+        let err = handler
+            .handle_delete_task(args(
+                serde_json::json!({ "id": "missing", "confirm": true }),
+            ))
+            .await
+            .expect_err("Deleting a missing task should fail");
+        assert!(err.message.contains("Task not found"));
+    }
+
+    #[tokio::test]
+    async fn test_complete_task_is_idempotent() {
+        let mut task = sample_task("task-1");
+        task.status = TaskStatus::Completed;
+        let (_dir, handler) = handler_with_tasks(vec![task]).await;
+
+        let result = handler
+            .handle_complete_task(args(serde_json::json!({ "id": "task-1" })))
+            .await
+            .expect("Completing a completed task should succeed");
+        let response: serde_json::Value = serde_json::from_str(&result_text(&result)).unwrap();
+        assert_eq!(response["previous_status"], "completed");
+
+        let task = handler
+            .task_service
+            .find_task_by_id("task-1")
+            .await
+            .unwrap();
+        assert_eq!(task.status, TaskStatus::Completed);
+    }
+
+    #[tokio::test]
+    async fn test_status_workflow_tools() {
+        let (_dir, handler) = handler_with_tasks(vec![sample_task("task-1")]).await;
+        let id = || args(serde_json::json!({ "id": "task-1" }));
+        let transition = |result: CallToolResult| {
+            let response: serde_json::Value = serde_json::from_str(&result_text(&result)).unwrap();
+            (
+                response["previous_status"].as_str().unwrap().to_string(),
+                response["status"].as_str().unwrap().to_string(),
+            )
+        };
+
+        let result = handler.handle_start_task(id()).await.unwrap();
+        assert_eq!(transition(result), ("pending".into(), "in_progress".into()));
+        let result = handler.handle_cancel_task(id()).await.unwrap();
+        assert_eq!(
+            transition(result),
+            ("in_progress".into(), "cancelled".into())
+        );
+        let result = handler.handle_reopen_task(id()).await.unwrap();
+        assert_eq!(transition(result), ("cancelled".into(), "pending".into()));
+
+        let err = handler
+            .handle_start_task(args(serde_json::json!({ "id": "missing" })))
+            .await
+            .expect_err("Starting a missing task should fail");
+        assert!(err.message.contains("Task not found"));
+    }
+
+    #[tokio::test]
+    async fn test_reopen_completed_task_under_strict_transitions() {
+        let mut task = sample_task("task-1");
+        task.status = TaskStatus::Completed;
+        let (dir, _) = handler_with_tasks(vec![task]).await;
+        let storage = TaskStorage::new(dir.path().join("tasks.json"));
+        let handler = TaskMcpHandler::new(TaskService::new(storage).with_strict_transitions(true));
+        let id = || args(serde_json::json!({ "id": "task-1" }));
+
+        let err = handler
+            .handle_start_task(id())
+            .await
+            .expect_err("A completed task can't be started directly");
+        assert!(err.message.contains("Cannot change status"));
+        let err = handler
+            .handle_cancel_task(id())
+            .await
+            .expect_err("A completed task can't be cancelled directly");
+        assert!(err.message.contains("Cannot change status"));
+
+        let result = handler.handle_reopen_task(id()).await.unwrap();
+        let response: serde_json::Value = serde_json::from_str(&result_text(&result)).unwrap();
+        assert_eq!(response["previous_status"], "completed");
+        assert_eq!(response["status"], "pending");
+        handler
+            .handle_start_task(id())
+            .await
+            .expect("A reopened task can be started");
+    }
+
+    #[tokio::test]
+    async fn test_complete_unknown_task() {
+        let (_dir, handler) = handler_with_tasks(vec![sample_task("task-1")]).await;
+
+        let err = handler
+            .handle_complete_task(args(serde_json::json!({ "id": "missing" })))
+            .await
+            .expect_err("Completing a missing task should fail");
+        assert!(err.message.contains("Task not found"));
+    }
+
+    #[tokio::test]
+    async fn test_list_tasks_rejects_non_positive_limit() {
+        let (_dir, handler) = handler_with_tasks(vec![sample_task("task-1")]).await;
+
+        for limit in [0, -5] {
+            let err = handler
+                .handle_list_tasks(Some(args(serde_json::json!({ "limit": limit }))))
+                .await
+                .expect_err("Non-positive limit should be rejected");
+            assert!(err.message.contains("limit"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_list_tasks_share_one_read() {
+        let (dir, _) = handler_with_tasks(vec![sample_task("task-1"), sample_task("task-2")]).await;
+        // A fresh storage starts with an empty cache, so the first call reads the file
+        let storage = TaskStorage::new(dir.path().join("tasks.json"));
+        let handler = TaskMcpHandler::new(TaskService::new(storage.clone()));
+        handler.handle_list_tasks(None).await.unwrap();
+        assert_eq!(storage.disk_reads(), 1);
+
+        let mut calls = tokio::task::JoinSet::new();
+        for _ in 0..32 {
+            let handler = handler.clone();
+            calls.spawn(async move { handler.handle_list_tasks(None).await });
+        }
+        while let Some(result) = calls.join_next().await {
+            result.unwrap().expect("Listing tasks should succeed");
+        }
+        assert_eq!(storage.disk_reads(), 1);
+
+        // A change swaps in a new snapshot without the next read going back to disk
+        handler
+            .handle_delete_task(args(serde_json::json!({ "id": "task-1", "confirm": true })))
+            .await
+            .unwrap();
+        let result = handler.handle_list_tasks(None).await.unwrap();
+        let response: serde_json::Value = serde_json::from_str(&result_text(&result)).unwrap();
+        assert_eq!(response["total"], 1);
+        assert_eq!(storage.disk_reads(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_list_tasks_offset_past_end() {
+        let (_dir, handler) =
+            handler_with_tasks(vec![sample_task("task-1"), sample_task("task-2")]).await;
+
+        let result = handler
+            .handle_list_tasks(Some(args(serde_json::json!({ "offset": 10, "limit": 5 }))))
+            .await
+            .expect("Offset past the end should yield an empty page");
+        let response: serde_json::Value = serde_json::from_str(&result_text(&result)).unwrap();
+        assert_eq!(response["count"], 0);
+        assert_eq!(response["total"], 2);
+        assert_eq!(response["summary"], "Showing 0 of 2 tasks");
+    }
+
+    #[tokio::test]
+    async fn test_list_tasks_json_round_trip() {
+        let mut task = sample_task("task-1");
+        task.tags = vec!["backend".to_string()];
+        task.assignee = Some("alice.smith".to_string());
+        let tasks = vec![task, sample_task("task-2")];
+        let (_dir, handler) = handler_with_tasks(tasks.clone()).await;
+
+        let result = handler
+            .handle_list_tasks(Some(args(serde_json::json!({ "format": "json" }))))
+            .await
+            .expect("Listing tasks should succeed");
+        let response: serde_json::Value = serde_json::from_str(&result_text(&result)).unwrap();
+        let parsed: Vec<Task> = serde_json::from_value(response["tasks"].clone())
+            .expect("Tasks should deserialize back into Vec<Task>");
+
+        assert_eq!(
+            serde_json::to_value(&parsed).unwrap(),
+            serde_json::to_value(&tasks).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_tasks_markdown_format() {
+        let (_dir, handler) = handler_with_tasks(vec![sample_task("task-1")]).await;
+
+        let result = handler
+            .handle_list_tasks(Some(args(serde_json::json!({ "format": "markdown" }))))
+            .await
+            .expect("Listing tasks should succeed");
+        let text = result_text(&result);
+        assert!(text.starts_with("Showing 1-1 of 1 tasks"));
+        assert!(text.contains("- **Task task-1** (`task-1`)"));
+    }
+
+    #[tokio::test]
+    async fn test_list_tasks_truncated_to_max_response_chars() {
+        let tasks = (1..=20)
+            .map(|i| sample_task(&format!("task-{}", i)))
+            .collect();
+        let (_dir, handler) = handler_with_tasks(tasks).await;
+        let handler = handler.with_max_response_chars(400);
+
+        let markdown = result_text(
+            &handler
+                .handle_list_tasks(Some(args(serde_json::json!({ "format": "markdown" }))))
+                .await
+                .unwrap(),
+        );
+        assert!(markdown.chars().count() <= 400, "too long: {}", markdown);
+        assert!(markdown.contains("tasks omitted, refine your filter"));
+        assert!(markdown.contains("- **Task task-1** (`task-1`)"));
+
+        let handler = handler.with_max_response_chars(2000);
+        let json = result_text(&handler.handle_list_tasks(None).await.unwrap());
+        assert!(json.chars().count() <= 2000, "too long: {}", json);
+        let response: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let shown = response["count"].as_u64().unwrap();
+        assert!(shown > 0);
+        assert_eq!(response["omitted"].as_u64().unwrap() + shown, 20);
+        assert_eq!(
+            response["notice"],
+            format!("... ({} tasks omitted, refine your filter)", 20 - shown)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_malformed_task_file_error() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tasks.json");
+        std::fs::write(&path, "{\"version\": \"1.0\", \"tasks\": [").unwrap();
+        let handler = TaskMcpHandler::new(TaskService::new(TaskStorage::new(path)));
+
+        let err = handler
+            .handle_list_tasks(None)
+            .await
+            .expect_err("Malformed task file should fail");
+        assert!(err.message.contains("line 1, column 29"));
+        assert!(err.message.contains("byte offset 28"));
+        assert_eq!(err.data.unwrap()["offset"], 28);
+    }
+
+    fn resource_text(result: &ReadResourceResult) -> String {
+        match &result.contents[0] {
+            ResourceContents::TextResourceContents { text, .. } => text.clone(),
+            _ => panic!("Expected text resource contents"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_resources() {
+        let (_dir, handler) =
+            handler_with_tasks(vec![sample_task("task-1"), sample_task("task-2")]).await;
+
+        let resources = handler.task_resources().await.unwrap();
+        let uris: Vec<&str> = resources.iter().map(|r| r.uri.as_str()).collect();
+        assert_eq!(uris, vec!["tasks://all", "task://task-1", "task://task-2"]);
+    }
+
+    #[tokio::test]
+    async fn test_read_collection_resource() {
+        let (_dir, handler) =
+            handler_with_tasks(vec![sample_task("task-1"), sample_task("task-2")]).await;
+
+        let result = handler.read_task_resource("tasks://all").await.unwrap();
+        let collection: TaskCollection = serde_json::from_str(&resource_text(&result)).unwrap();
+        assert_eq!(collection.tasks.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_read_task_resource() {
+        let (_dir, handler) = handler_with_tasks(vec![sample_task("task-1")]).await;
+
+        let result = handler.read_task_resource("task://task-1").await.unwrap();
+        let task: Task = serde_json::from_str(&resource_text(&result)).unwrap();
+        assert_eq!(task.id, "task-1");
+
+        let err = handler
+            .read_task_resource("task://missing")
+            .await
+            .expect_err("Unknown task should not be found");
+        assert_eq!(err.code, rmcp::model::ErrorCode::RESOURCE_NOT_FOUND);
+        assert!(err.message.contains("missing"));
+
+        let err = handler
+            .read_task_resource("file:///etc/passwd")
+            .await
+            .expect_err("Unknown scheme should not be found");
+        assert_eq!(err.code, rmcp::model::ErrorCode::RESOURCE_NOT_FOUND);
+    }
+
+    fn prompt_text(result: &GetPromptResult) -> String {
+        match &result.messages[0].content {
+            rmcp::model::PromptMessageContent::Text { text } => text.clone(),
+            _ => panic!("Expected a text prompt message"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_overdue_summary_prompt_lists_overdue_titles() {
+        let mut overdue = sample_task("task-1");
+        overdue.title = "File the quarterly report".to_string();
+        overdue.due_date = Some("2024-01-10T00:00:00Z".to_string());
+        let mut upcoming = sample_task("task-2");
+        upcoming.title = "Plan the offsite".to_string();
+        upcoming.due_date = Some("2024-03-01T00:00:00Z".to_string());
+        let (_dir, handler) = handler_with_tasks(vec![overdue, upcoming]).await;
+        let now = "2024-02-01T00:00:00Z".parse().unwrap();
+
+        let result = handler.render_prompt("overdue_summary", now).await.unwrap();
+        let text = prompt_text(&result);
+        assert!(text.contains("File the quarterly report"));
+        assert!(!text.contains("Plan the offsite"));
+    }
+
+    #[tokio::test]
+    async fn test_daily_standup_prompt_sections() {
+        let mut overdue = sample_task("task-1");
+        overdue.title = "File the quarterly report".to_string();
+        overdue.due_date = Some("2024-01-10T00:00:00Z".to_string());
+        let mut active = sample_task("task-2");
+        active.title = "Review pull requests".to_string();
+        active.status = TaskStatus::InProgress;
+        let mut done = sample_task("task-3");
+        done.title = "Ship the release".to_string();
+        done.status = TaskStatus::Completed;
+        done.updated_at = "2024-01-31T18:00:00Z".to_string();
+        let (_dir, handler) = handler_with_tasks(vec![overdue, active, done]).await;
+        let now = "2024-02-01T09:00:00Z".parse().unwrap();
+
+        let result = handler.render_prompt("daily_standup", now).await.unwrap();
+        let text = prompt_text(&result);
+        assert!(text.contains("File the quarterly report"));
+        assert!(text.contains("Review pull requests"));
+        assert!(text.contains("Ship the release"));
+
+        let err = handler
+            .render_prompt("weekly_retro", now)
+            .await
+            .unwrap_err();
+        assert!(err.message.contains("Unknown prompt"));
+    }
+
+    #[test]
+    fn test_filtering_tools_accept_every_filter_key() {
+        let tools = build_tool_definitions();
+        for name in ["list_tasks", "count_tasks", "bulk_tag"] {
+            let tool = tools.iter().find(|tool| tool.name == name).unwrap();
+            let properties = tool.input_schema["properties"].as_object().unwrap();
+            for key in crate::task_service::TaskFilter::KEYS {
+                assert!(properties.contains_key(key), "{} lacks {}", name, key);
+            }
+        }
+    }
+
+    #[test]
+    fn test_build_tool_definitions() {
+        let tools = build_tool_definitions();
+        let names: Vec<&str> = tools.iter().map(|tool| tool.name.as_ref()).collect();
+        for expected in [
+            "list_tasks",
+            "count_tasks",
+            "group_tasks",
+            "get_task",
+            "get_tasks_batch",
+            "subtasks",
+            "create_task",
+            "update_task",
+            "apply_patch",
+            "add_comment",
+            "add_tag",
+            "remove_tag",
+            "rename_tag",
+            "bulk_tag",
+            "delete_task",
+            "complete_task",
+            "start_task",
+            "cancel_task",
+            "reopen_task",
+            "reassign_task",
+            "unassign_task",
+            "set_due_date",
+            "reorder_task",
+            "overdue_tasks",
+            "due_soon",
+            "stale_tasks",
+            "tasks_due_on",
+            "next_task",
+            "blocked_tasks",
+            "import_csv",
+            "export_csv",
+            "export_ical",
+            "export_markdown",
+            "task_stats",
+            "archive_completed",
+            "restore_task",
+            "deduplicate",
+            "compact_storage",
+            "undo",
+            "search_tasks",
+            "tasks_by_tag",
+            "workload",
+            "list_facets",
+            "daily_digest",
+            "health_check",
+        ] {
+            assert!(names.contains(&expected), "missing tool {}", expected);
+        }
+
+        let mut unique = names.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), names.len(), "tool names must be unique");
+
+        for tool in &tools {
+            assert_eq!(
+                tool.input_schema.get("type").and_then(|v| v.as_str()),
+                Some("object"),
+                "{} schema must be a JSON object schema",
+                tool.name
+            );
+            assert!(
+                tool.description.is_some(),
+                "{} needs a description",
+                tool.name
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_task_stats_formats() {
+        let (_dir, handler) =
+            handler_with_tasks(vec![sample_task("task-1"), sample_task("task-2")]).await;
+
+        let result = handler
+            .handle_task_stats(serde_json::Map::new())
+            .await
+            .expect("Stats should succeed");
+        let stats: serde_json::Value = serde_json::from_str(&result_text(&result)).unwrap();
+        assert_eq!(stats["total_tasks"], 2);
+        assert_eq!(stats["status_counts"]["Pending"], 2);
+
+        let result = handler
+            .handle_task_stats(args(serde_json::json!({ "format": "markdown" })))
+            .await
+            .expect("Stats should succeed");
+        assert!(result_text(&result).contains("**Total Tasks:** 2"));
+
+        let err = handler
+            .handle_task_stats(args(serde_json::json!({ "format": "xml" })))
+            .await
+            .expect_err("Unknown format should be rejected");
+        assert!(err.message.contains("Invalid format"));
+    }
+
+    #[tokio::test]
+    async fn test_deduplicate_reports_then_fixes() {
+        let (_dir, handler) =
+            handler_with_tasks(vec![sample_task("task-001"), sample_task("task-001")]).await;
+
+        let err = handler
+            .handle_deduplicate(serde_json::Map::new())
+            .await
+            .expect_err("Duplicates should be reported as an error");
+        assert!(err.message.contains("task-001"));
+
+        let result = handler
+            .handle_deduplicate(args(serde_json::json!({ "fix": true })))
+            .await
+            .expect("Fixing duplicates should succeed");
+        let response: serde_json::Value = serde_json::from_str(&result_text(&result)).unwrap();
+        assert_eq!(response["renamed"][0]["from"], "task-001");
+        let new_id = response["renamed"][0]["to"].as_str().unwrap();
+        assert!(
+            uuid::Uuid::parse_str(new_id).is_ok(),
+            "not a UUID: {}",
+            new_id
+        );
+
+        let result = handler
+            .handle_deduplicate(serde_json::Map::new())
+            .await
+            .expect("No duplicates should remain");
+        let response: serde_json::Value = serde_json::from_str(&result_text(&result)).unwrap();
+        assert_eq!(response["duplicates"], serde_json::json!([]));
+    }
+
+    #[cfg(feature = "schema")]
+    #[tokio::test]
+    async fn test_task_schema() {
+        let (_dir, handler) = handler_with_tasks(vec![]).await;
+
+        let result = handler
+            .handle_task_schema(serde_json::Map::new())
+            .await
+            .expect("Schema should be generated");
+        let schema: serde_json::Value = serde_json::from_str(&result_text(&result)).unwrap();
+        let properties = schema["properties"]
+            .as_object()
+            .expect("Task schema should list properties");
+        for key in [
+            "id",
+            "title",
+            "description",
+            "status",
+            "priority",
+            "created_at",
+            "updated_at",
+            "tags",
+            "assignee",
+            "due_date",
+            "metadata",
+        ] {
+            assert!(properties.contains_key(key), "missing property {}", key);
         }
+        let required = schema["required"].as_array().unwrap();
+        assert!(required.contains(&serde_json::json!("id")));
+        assert!(!required.contains(&serde_json::json!("metadata")));
+
+        let result = handler
+            .handle_task_schema(args(serde_json::json!({ "model": "collection" })))
+            .await
+            .expect("Schema should be generated");
+        let schema: serde_json::Value = serde_json::from_str(&result_text(&result)).unwrap();
+        assert!(schema["properties"]["tasks"].is_object());
     }
 }
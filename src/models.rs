@@ -1,7 +1,17 @@
+use chrono::{DateTime, Datelike, Days, Duration, Months, NaiveDate, Utc, Weekday};
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::clock::TimestampFormat;
+use crate::error::TaskError;
 
 /// Task priority levels
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "lowercase")]
 pub enum Priority {
     Low,
@@ -10,8 +20,85 @@ pub enum Priority {
     Critical,
 }
 
+impl Priority {
+    /// Every priority, lowest first
+    pub const ALL: [Priority; 4] = [
+        Priority::Low,
+        Priority::Medium,
+        Priority::High,
+        Priority::Critical,
+    ];
+
+    /// Default effort weight used when scoring tasks: low 1, medium 2, high 4, critical 8
+    pub fn weight(&self) -> u32 {
+        match self {
+            Priority::Low => 1,
+            Priority::Medium => 2,
+            Priority::High => 4,
+            Priority::Critical => 8,
+        }
+    }
+
+    /// Stable numeric severity, low 0 up to critical 3, for sorting and scoring
+    /// `Ord` compares by this rank, so it doesn't depend on variant declaration order
+    pub fn rank(&self) -> u8 {
+        match self {
+            Priority::Low => 0,
+            Priority::Medium => 1,
+            Priority::High => 2,
+            Priority::Critical => 3,
+        }
+    }
+}
+
+impl Ord for Priority {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank().cmp(&other.rank())
+    }
+}
+
+impl PartialOrd for Priority {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Priority::Low => "Low",
+            Priority::Medium => "Medium",
+            Priority::High => "High",
+            Priority::Critical => "Critical",
+        };
+        f.write_str(label)
+    }
+}
+
+impl FromStr for Priority {
+    type Err = TaskError;
+
+    /// Parse the same lowercase names used in the JSON file, ignoring case and surrounding whitespace
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s.to_ascii_lowercase().as_str() {
+            "low" => Ok(Priority::Low),
+            "medium" => Ok(Priority::Medium),
+            "high" => Ok(Priority::High),
+            "critical" => Ok(Priority::Critical),
+            _ => Err(TaskError::Validation(format!(
+                "Invalid priority: {} (expected low, medium, high or critical)",
+                s
+            ))),
+        }
+    }
+}
+
 /// Task status
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+///
+/// Ordered by workflow progression: `Pending < InProgress < Completed < Cancelled`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum TaskStatus {
     Pending,
@@ -20,8 +107,143 @@ pub enum TaskStatus {
     Cancelled,
 }
 
+impl TaskStatus {
+    /// Every status, in workflow order
+    pub const ALL: [TaskStatus; 4] = [
+        TaskStatus::Pending,
+        TaskStatus::InProgress,
+        TaskStatus::Completed,
+        TaskStatus::Cancelled,
+    ];
+
+    /// Whether the transition rules allow moving from this status to `to`
+    /// A finished (completed or cancelled) task can only be reopened as pending
+    pub fn can_transition_to(&self, to: &TaskStatus) -> bool {
+        match self {
+            TaskStatus::Pending | TaskStatus::InProgress => true,
+            TaskStatus::Completed | TaskStatus::Cancelled => {
+                to == self || *to == TaskStatus::Pending
+            }
+        }
+    }
+
+    /// Workflow rank used for ordering, independent of variant declaration order
+    fn rank(&self) -> u8 {
+        match self {
+            TaskStatus::Pending => 0,
+            TaskStatus::InProgress => 1,
+            TaskStatus::Completed => 2,
+            TaskStatus::Cancelled => 3,
+        }
+    }
+}
+
+impl Ord for TaskStatus {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank().cmp(&other.rank())
+    }
+}
+
+impl PartialOrd for TaskStatus {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for TaskStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            TaskStatus::Pending => "Pending",
+            TaskStatus::InProgress => "In Progress",
+            TaskStatus::Completed => "Completed",
+            TaskStatus::Cancelled => "Cancelled",
+        };
+        f.write_str(label)
+    }
+}
+
+impl FromStr for TaskStatus {
+    type Err = TaskError;
+
+    /// Parse the same snake_case names used in the JSON file, ignoring case and surrounding whitespace
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s.to_ascii_lowercase().as_str() {
+            "pending" => Ok(TaskStatus::Pending),
+            "in_progress" => Ok(TaskStatus::InProgress),
+            "completed" => Ok(TaskStatus::Completed),
+            "cancelled" => Ok(TaskStatus::Cancelled),
+            _ => Err(TaskError::Validation(format!(
+                "Invalid status: {} (expected pending, in_progress, completed or cancelled)",
+                s
+            ))),
+        }
+    }
+}
+
+/// How often a recurring task comes back after it is completed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recurrence {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl Recurrence {
+    /// The next occurrence after `from`
+    /// Monthly steps clamp to the end of shorter months (Jan 31 -> Feb 29)
+    pub fn next_after(&self, from: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Recurrence::Daily => from.checked_add_signed(Duration::days(1)),
+            Recurrence::Weekly => from.checked_add_signed(Duration::weeks(1)),
+            Recurrence::Monthly => from.checked_add_months(Months::new(1)),
+        }
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "daily" => Ok(Recurrence::Daily),
+            "weekly" => Ok(Recurrence::Weekly),
+            "monthly" => Ok(Recurrence::Monthly),
+            other => Err(TaskError::Validation(format!(
+                "Invalid recurrence: {} (expected daily, weekly or monthly)",
+                other
+            ))),
+        }
+    }
+}
+
+/// A note left on a task
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TaskComment {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    pub body: String,
+    pub created_at: String,
+}
+
+/// One field edit in a task's history
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TaskChange {
+    /// Name of the changed field as it appears in the task file, e.g. `priority`
+    pub field: String,
+    /// Value before the edit, `null` if the field was unset
+    pub old: serde_json::Value,
+    /// Value after the edit, `null` if the field was cleared
+    pub new: serde_json::Value,
+    /// When the edit was made, matching the task's `updated_at`
+    pub at: String,
+}
+
 /// Individual task structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Task {
     pub id: String,
     pub title: String,
@@ -33,10 +255,472 @@ pub struct Task {
     pub tags: Vec<String>,
     pub assignee: Option<String>,
     pub due_date: Option<String>,
+    /// ID of the parent task when this task is a subtask
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    /// IDs of tasks that must be completed before this task can start
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// Recurrence rule (`daily`, `weekly` or `monthly`) for tasks that regenerate on completion
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<String>,
+    /// Notes history, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub comments: Vec<TaskComment>,
+    /// Custom fields not covered by the schema, e.g. story points or ticket URLs
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, serde_json::Value>,
+    /// Field-level edits made through the server, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<TaskChange>,
+    /// Position in a hand-ordered list, lowest first; see `reorder_task`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<i64>,
+}
+
+impl Task {
+    /// Create a pending task with a fresh UUID id, stamped with the current time
+    /// Optional fields are left `None` or empty for the caller to fill in
+    pub fn new_pending(
+        title: impl Into<String>,
+        description: impl Into<String>,
+        priority: Priority,
+    ) -> Self {
+        let now = TimestampFormat::default().format(Utc::now());
+        Self {
+            id: Uuid::new_v4().to_string(),
+            title: title.into(),
+            description: description.into(),
+            status: TaskStatus::Pending,
+            priority,
+            created_at: now.clone(),
+            updated_at: now,
+            tags: Vec::new(),
+            assignee: None,
+            due_date: None,
+            parent_id: None,
+            depends_on: Vec::new(),
+            recurrence: None,
+            comments: Vec::new(),
+            metadata: HashMap::new(),
+            history: Vec::new(),
+            order: None,
+        }
+    }
+
+    /// Fields whose values differ between this task and `after`, as `(field, old, new)` in
+    /// field-name order; `updated_at` and `history` are left out, since every edit changes them
+    pub fn changed_fields(
+        &self,
+        after: &Task,
+    ) -> Vec<(String, serde_json::Value, serde_json::Value)> {
+        let to_map = |task: &Task| match serde_json::to_value(task) {
+            Ok(serde_json::Value::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        };
+        let before = to_map(self);
+        let after = to_map(after);
+
+        let mut fields: Vec<&String> = before.keys().chain(after.keys()).collect();
+        fields.sort();
+        fields.dedup();
+
+        fields
+            .into_iter()
+            .filter(|field| !matches!(field.as_str(), "updated_at" | "history"))
+            .filter(|field| before.get(*field) != after.get(*field))
+            .map(|field| {
+                let value = |map: &serde_json::Map<String, serde_json::Value>| {
+                    map.get(field).cloned().unwrap_or(serde_json::Value::Null)
+                };
+                (field.clone(), value(&before), value(&after))
+            })
+            .collect()
+    }
+
+    /// Append a history entry stamped `at` for every field that differs from `before`
+    pub fn record_changes(&mut self, before: &Task, at: &str) {
+        let changes = before.changed_fields(self);
+        self.history
+            .extend(changes.into_iter().map(|(field, old, new)| TaskChange {
+                field,
+                old,
+                new,
+                at: at.to_string(),
+            }));
+    }
+
+    /// Parse `created_at` as an RFC 3339 timestamp
+    pub fn created_datetime(&self) -> Option<DateTime<Utc>> {
+        parse_timestamp(&self.created_at)
+    }
+
+    /// Parse `updated_at` as an RFC 3339 timestamp
+    pub fn updated_datetime(&self) -> Option<DateTime<Utc>> {
+        parse_timestamp(&self.updated_at)
+    }
+
+    /// Parse `due_date` as an RFC 3339 timestamp
+    /// Returns `None` if the task has no due date or it cannot be parsed
+    pub fn due_datetime(&self) -> Option<DateTime<Utc>> {
+        self.due_date.as_deref().and_then(parse_timestamp)
+    }
+
+    /// UTC calendar day of `due_date`, which may be a full RFC 3339 timestamp or a bare
+    /// `YYYY-MM-DD` date
+    pub fn due_day(&self) -> Option<NaiveDate> {
+        let due_date = self.due_date.as_deref()?.trim();
+        match parse_timestamp(due_date) {
+            Some(due) => Some(due.date_naive()),
+            None => NaiveDate::parse_from_str(due_date, "%Y-%m-%d").ok(),
+        }
+    }
+
+    /// Whether the task is still open and its due date lies before `now`
+    pub fn is_overdue(&self, now: DateTime<Utc>) -> bool {
+        if matches!(self.status, TaskStatus::Completed | TaskStatus::Cancelled) {
+            return false;
+        }
+        self.due_datetime().is_some_and(|due| due < now)
+    }
+
+    /// Whether the task is still open and due between `from` and `until` (inclusive)
+    pub fn is_due_between(&self, from: DateTime<Utc>, until: DateTime<Utc>) -> bool {
+        if matches!(self.status, TaskStatus::Completed | TaskStatus::Cancelled) {
+            return false;
+        }
+        self.due_datetime()
+            .is_some_and(|due| from <= due && due <= until)
+    }
+
+    /// Parse the recurrence rule, if the task has one
+    pub fn recurrence_rule(&self) -> Result<Option<Recurrence>, TaskError> {
+        self.recurrence.as_deref().map(str::parse).transpose()
+    }
+
+    /// Set a single metadata key, leaving the others untouched
+    /// A JSON `null` value removes the key instead
+    pub fn set_metadata(&mut self, key: &str, value: serde_json::Value) {
+        if value.is_null() {
+            self.metadata.remove(key);
+        } else {
+            self.metadata.insert(key.to_string(), value);
+        }
+    }
+
+    /// Check the task against size limits, naming the first field that exceeds them
+    pub fn validate_limits(&self, limits: &TaskLimits) -> Result<(), TaskError> {
+        let too_long = |field: &str, value: &str, max: usize| {
+            let length = value.chars().count();
+            if length > max {
+                Err(TaskError::Validation(format!(
+                    "{} is too long: {} characters (maximum {})",
+                    field, length, max
+                )))
+            } else {
+                Ok(())
+            }
+        };
+        too_long("title", &self.title, limits.max_title_length)?;
+        too_long(
+            "description",
+            &self.description,
+            limits.max_description_length,
+        )?;
+        if self.tags.len() > limits.max_tags {
+            return Err(TaskError::Validation(format!(
+                "tags has too many entries: {} (maximum {})",
+                self.tags.len(),
+                limits.max_tags
+            )));
+        }
+        Ok(())
+    }
+
+    /// Whether the task lists itself as its own parent
+    pub fn is_own_parent(&self) -> bool {
+        self.parent_id.as_deref() == Some(self.id.as_str())
+    }
+
+    /// Describe the problem when the task is due before it was created; `None` when it
+    /// isn't, or when either timestamp doesn't parse
+    pub fn due_before_creation(&self) -> Option<String> {
+        match (self.created_datetime(), self.due_datetime()) {
+            (Some(created), Some(due)) if due < created => Some(format!(
+                "due_date {} is before created_at {}",
+                self.due_date.as_deref().unwrap_or_default(),
+                self.created_at
+            )),
+            _ => None,
+        }
+    }
+
+    /// Check that timestamp fields are valid RFC 3339, the recurrence rule is known and
+    /// the task is not its own parent, returning every problem found
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+
+        if self.created_datetime().is_none() {
+            problems.push(format!(
+                "Task {}: invalid created_at timestamp: {}",
+                self.id, self.created_at
+            ));
+        }
+        if self.updated_datetime().is_none() {
+            problems.push(format!(
+                "Task {}: invalid updated_at timestamp: {}",
+                self.id, self.updated_at
+            ));
+        }
+        if let Some(due_date) = &self.due_date
+            && self.due_datetime().is_none()
+        {
+            problems.push(format!(
+                "Task {}: invalid due_date timestamp: {}",
+                self.id, due_date
+            ));
+        }
+        if let Err(e) = self.recurrence_rule() {
+            problems.push(format!("Task {}: {}", self.id, e));
+        }
+        if self.is_own_parent() {
+            problems.push(format!("Task {}: lists itself as its own parent", self.id));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+}
+
+/// Longest slug [`IdStrategy::Slug`] takes from a title, before any numeric suffix
+const MAX_SLUG_LENGTH: usize = 60;
+
+/// How created tasks get their ID
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdStrategy {
+    /// A random UUID, e.g. `1b4e28ba-2fa1-11d2-883f-0016d3cca427`
+    #[default]
+    Uuid,
+    /// The title as a lowercase slug, e.g. `write-release-notes`, with `-2`, `-3`, ...
+    /// appended when that ID is taken
+    Slug,
+}
+
+impl IdStrategy {
+    /// ID for a new task titled `title` that no task in `tasks` already uses
+    pub fn generate(&self, title: &str, tasks: &[Task]) -> String {
+        match self {
+            IdStrategy::Uuid => Uuid::new_v4().to_string(),
+            IdStrategy::Slug => {
+                let base = slugify(title);
+                let taken: HashSet<&str> = tasks.iter().map(|t| t.id.as_str()).collect();
+                if !taken.contains(base.as_str()) {
+                    return base;
+                }
+                (2..)
+                    .map(|n| format!("{}-{}", base, n))
+                    .find(|id| !taken.contains(id.as_str()))
+                    .expect("some numeric suffix is always free")
+            }
+        }
+    }
+}
+
+impl FromStr for IdStrategy {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uuid" => Ok(IdStrategy::Uuid),
+            "slug" => Ok(IdStrategy::Slug),
+            other => Err(TaskError::Validation(format!(
+                "Invalid id strategy: {} (expected uuid or slug)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Lowercase ASCII letters and digits of `title`, with every other run of characters turned
+/// into a single `-`; a title without any gives `task`
+fn slugify(title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.truncate(MAX_SLUG_LENGTH);
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "task".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// Size limits applied to tasks created or changed through the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskLimits {
+    /// Maximum title length in characters
+    pub max_title_length: usize,
+    /// Maximum description length in characters
+    pub max_description_length: usize,
+    /// Maximum number of tags on one task
+    pub max_tags: usize,
+}
+
+impl Default for TaskLimits {
+    fn default() -> Self {
+        Self {
+            max_title_length: 500,
+            max_description_length: 20_000,
+            max_tags: 100,
+        }
+    }
+}
+
+/// Task file schema version written by this build
+///
+/// - `1.0`: the original task fields
+/// - `1.1`: optional `parent_id`, `depends_on`, `recurrence`, `comments` and `metadata`
+/// - `1.2`: optional `history`
+pub const SCHEMA_VERSION: &str = "1.2";
+
+/// Parse a `major.minor` schema version for comparison
+pub fn parse_schema_version(version: &str) -> Option<(u32, u32)> {
+    let (major, minor) = version.split_once('.')?;
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// Find a cycle in the `depends_on` graph, returned as the IDs along the cycle
+/// with the first ID repeated at the end (e.g. `["a", "b", "a"]`)
+/// Dependencies on IDs that don't exist are ignored here
+pub fn find_dependency_cycle(tasks: &[Task]) -> Option<Vec<String>> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Visit {
+        InProgress,
+        Done,
+    }
+
+    fn visit<'a>(
+        id: &'a str,
+        graph: &HashMap<&'a str, &'a [String]>,
+        state: &mut HashMap<&'a str, Visit>,
+        path: &mut Vec<&'a str>,
+    ) -> Option<Vec<String>> {
+        match state.get(id) {
+            Some(Visit::Done) => return None,
+            Some(Visit::InProgress) => {
+                let start = path.iter().position(|p| *p == id).unwrap_or(0);
+                let mut cycle: Vec<String> = path[start..].iter().map(|p| p.to_string()).collect();
+                cycle.push(id.to_string());
+                return Some(cycle);
+            }
+            None => {}
+        }
+
+        state.insert(id, Visit::InProgress);
+        path.push(id);
+        for dependency in graph.get(id).copied().unwrap_or_default() {
+            if graph.contains_key(dependency.as_str())
+                && let Some(cycle) = visit(dependency, graph, state, path)
+            {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        state.insert(id, Visit::Done);
+        None
+    }
+
+    let graph: HashMap<&str, &[String]> = tasks
+        .iter()
+        .map(|task| (task.id.as_str(), task.depends_on.as_slice()))
+        .collect();
+    let mut state = HashMap::new();
+
+    tasks
+        .iter()
+        .find_map(|task| visit(&task.id, &graph, &mut state, &mut Vec::new()))
+}
+
+/// Parse an RFC 3339 timestamp into UTC
+pub fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|datetime| datetime.with_timezone(&Utc))
+}
+
+/// Resolve a due date given as an RFC 3339 timestamp, a `YYYY-MM-DD` date, or a relative
+/// expression: `today`, `tomorrow`, a weekday or `next <weekday>` (the next one after today),
+/// or `in N hours`/`days`/`weeks`/`months`
+///
+/// Anything naming a day resolves to the last second of that UTC day, so a task due today
+/// isn't overdue until the day is over; `in N hours` is exact. Matching ignores case.
+pub fn parse_due_date(expr: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, TaskError> {
+    if let Some(timestamp) = parse_timestamp(expr.trim()) {
+        return Ok(timestamp);
+    }
+
+    let expr = expr.trim().to_lowercase();
+    let words: Vec<&str> = expr.split_whitespace().collect();
+    let today = now.date_naive();
+    let day = match words.as_slice() {
+        ["today"] => Some(today),
+        ["tomorrow"] => today.succ_opt(),
+        ["next", weekday] => next_weekday(today, weekday),
+        [word] => {
+            next_weekday(today, word).or_else(|| NaiveDate::parse_from_str(word, "%Y-%m-%d").ok())
+        }
+        ["in", count, unit] => {
+            let count: u32 = count.parse().map_err(|_| invalid_due_date(&expr))?;
+            match unit.trim_end_matches('s') {
+                "hour" => {
+                    return now
+                        .checked_add_signed(Duration::hours(i64::from(count)))
+                        .ok_or_else(|| invalid_due_date(&expr));
+                }
+                "day" => today.checked_add_days(Days::new(u64::from(count))),
+                "week" => today.checked_add_days(Days::new(7 * u64::from(count))),
+                "month" => today.checked_add_months(Months::new(count)),
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+
+    day.and_then(|day| day.and_hms_opt(23, 59, 59))
+        .map(|end_of_day| end_of_day.and_utc())
+        .ok_or_else(|| invalid_due_date(&expr))
+}
+
+/// The first date after `today` falling on the named weekday (`monday` or `mon`)
+fn next_weekday(today: NaiveDate, name: &str) -> Option<NaiveDate> {
+    let weekday: Weekday = name.parse().ok()?;
+    let ahead = (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+    let ahead = if ahead == 0 { 7 } else { ahead };
+    today.checked_add_days(Days::new(u64::from(ahead)))
+}
+
+/// Validation error for a due date expression that couldn't be understood
+fn invalid_due_date(expr: &str) -> TaskError {
+    TaskError::Validation(format!(
+        "Unrecognized due date: {} (expected an RFC 3339 timestamp, YYYY-MM-DD, today, tomorrow, \
+         next <weekday>, or in N hours/days/weeks/months)",
+        expr
+    ))
 }
 
 /// Container for all tasks
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct TaskCollection {
     pub tasks: Vec<Task>,
     pub version: String,
@@ -47,7 +731,47 @@ impl TaskCollection {
     pub fn new() -> Self {
         Self {
             tasks: Vec::new(),
-            version: "1.0".to_string(),
+            version: SCHEMA_VERSION.to_string(),
+        }
+    }
+
+    /// Check the collection for malformed entries, returning every problem found
+    ///
+    /// Flags empty or duplicate IDs, empty titles, invalid timestamps, tasks due before they
+    /// were created and dependency cycles.
+    /// Unknown status or priority values never reach this point, since deserializing the
+    /// file rejects them.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+        let mut seen_ids = HashSet::new();
+
+        for (index, task) in self.tasks.iter().enumerate() {
+            if task.id.trim().is_empty() {
+                problems.push(format!("Task at index {}: empty id", index));
+            } else if !seen_ids.insert(task.id.as_str()) {
+                problems.push(format!("Task {}: duplicate id", task.id));
+            }
+
+            if task.title.trim().is_empty() {
+                problems.push(format!("Task {}: empty title", task.id));
+            }
+
+            if let Err(task_problems) = task.validate() {
+                problems.extend(task_problems);
+            }
+            if let Some(problem) = task.due_before_creation() {
+                problems.push(format!("Task {}: {}", task.id, problem));
+            }
+        }
+
+        if let Some(cycle) = find_dependency_cycle(&self.tasks) {
+            problems.push(format!("Dependency cycle: {}", cycle.join(" -> ")));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
         }
     }
 }
@@ -57,3 +781,415 @@ impl Default for TaskCollection {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn sample_task() -> Task {
+        Task {
+            id: "task-1".to_string(),
+            title: "Sample".to_string(),
+            description: "Sample task".to_string(),
+            status: TaskStatus::Pending,
+            priority: Priority::Medium,
+            created_at: "2024-01-15T09:00:00Z".to_string(),
+            updated_at: "2024-01-15T09:00:00+02:00".to_string(),
+            tags: vec![],
+            assignee: None,
+            due_date: Some("2024-01-20T17:00:00Z".to_string()),
+            parent_id: None,
+            depends_on: vec![],
+            recurrence: None,
+            comments: vec![],
+            metadata: Default::default(),
+            history: vec![],
+            order: None,
+        }
+    }
+
+    #[test]
+    fn test_priority_ordering() {
+        assert!(Priority::Critical > Priority::Low);
+        assert!(Priority::High > Priority::Medium);
+
+        let mut priorities = vec![
+            Priority::High,
+            Priority::Low,
+            Priority::Critical,
+            Priority::Medium,
+        ];
+        priorities.sort();
+        assert_eq!(
+            priorities,
+            vec![
+                Priority::Low,
+                Priority::Medium,
+                Priority::High,
+                Priority::Critical
+            ]
+        );
+    }
+
+    #[test]
+    fn test_priority_rank() {
+        let ranks: Vec<u8> = Priority::ALL.iter().map(Priority::rank).collect();
+        assert_eq!(ranks, vec![0, 1, 2, 3]);
+
+        let mut priorities = vec![
+            Priority::Critical,
+            Priority::Low,
+            Priority::High,
+            Priority::Medium,
+        ];
+        priorities.sort_by_key(Priority::rank);
+        assert_eq!(priorities, Priority::ALL);
+        assert!(
+            priorities
+                .windows(2)
+                .all(|pair| pair[0] < pair[1] && pair[0].weight() < pair[1].weight())
+        );
+    }
+
+    #[test]
+    fn test_status_ordering() {
+        assert!(TaskStatus::Pending < TaskStatus::InProgress);
+        assert!(TaskStatus::InProgress < TaskStatus::Completed);
+        assert!(TaskStatus::Completed < TaskStatus::Cancelled);
+    }
+
+    #[test]
+    fn test_parse_priority() {
+        assert_eq!("low".parse::<Priority>().unwrap(), Priority::Low);
+        assert_eq!("medium".parse::<Priority>().unwrap(), Priority::Medium);
+        assert_eq!("high".parse::<Priority>().unwrap(), Priority::High);
+        assert_eq!("critical".parse::<Priority>().unwrap(), Priority::Critical);
+
+        assert_eq!("High".parse::<Priority>().unwrap(), Priority::High);
+        assert_eq!(" high ".parse::<Priority>().unwrap(), Priority::High);
+
+        let err = "urgent".parse::<Priority>().unwrap_err();
+        assert!(err.to_string().contains("Invalid priority: urgent"));
+    }
+
+    #[test]
+    fn test_parse_status() {
+        assert_eq!(
+            "pending".parse::<TaskStatus>().unwrap(),
+            TaskStatus::Pending
+        );
+        assert_eq!(
+            "in_progress".parse::<TaskStatus>().unwrap(),
+            TaskStatus::InProgress
+        );
+        assert_eq!(
+            "completed".parse::<TaskStatus>().unwrap(),
+            TaskStatus::Completed
+        );
+        assert_eq!(
+            "cancelled".parse::<TaskStatus>().unwrap(),
+            TaskStatus::Cancelled
+        );
+
+        assert_eq!(
+            "Pending".parse::<TaskStatus>().unwrap(),
+            TaskStatus::Pending
+        );
+        assert_eq!(
+            " IN_PROGRESS\n".parse::<TaskStatus>().unwrap(),
+            TaskStatus::InProgress
+        );
+
+        let err = "done".parse::<TaskStatus>().unwrap_err();
+        assert!(err.to_string().contains("Invalid status: done"));
+    }
+
+    #[test]
+    fn test_display_labels() {
+        assert_eq!(Priority::Low.to_string(), "Low");
+        assert_eq!(Priority::Medium.to_string(), "Medium");
+        assert_eq!(Priority::High.to_string(), "High");
+        assert_eq!(Priority::Critical.to_string(), "Critical");
+
+        assert_eq!(TaskStatus::Pending.to_string(), "Pending");
+        assert_eq!(TaskStatus::InProgress.to_string(), "In Progress");
+        assert_eq!(TaskStatus::Completed.to_string(), "Completed");
+        assert_eq!(TaskStatus::Cancelled.to_string(), "Cancelled");
+    }
+
+    #[test]
+    fn test_parse_relative_due_dates() {
+        // A Thursday
+        let now = Utc.with_ymd_and_hms(2024, 2, 1, 12, 0, 0).unwrap();
+        let end_of = |y, m, d| Utc.with_ymd_and_hms(y, m, d, 23, 59, 59).unwrap();
+
+        assert_eq!(parse_due_date("tomorrow", now).unwrap(), end_of(2024, 2, 2));
+        assert_eq!(
+            parse_due_date("in 3 days", now).unwrap(),
+            end_of(2024, 2, 4)
+        );
+        assert_eq!(
+            parse_due_date(" In 1 Week ", now).unwrap(),
+            end_of(2024, 2, 8)
+        );
+        assert_eq!(
+            parse_due_date("next monday", now).unwrap(),
+            end_of(2024, 2, 5)
+        );
+        assert_eq!(parse_due_date("thu", now).unwrap(), end_of(2024, 2, 8));
+        assert_eq!(
+            parse_due_date("2024-03-01", now).unwrap(),
+            end_of(2024, 3, 1)
+        );
+        assert_eq!(
+            parse_due_date("in 4 hours", now).unwrap(),
+            Utc.with_ymd_and_hms(2024, 2, 1, 16, 0, 0).unwrap()
+        );
+        assert_eq!(
+            parse_due_date("2024-02-10T09:00:00+02:00", now).unwrap(),
+            Utc.with_ymd_and_hms(2024, 2, 10, 7, 0, 0).unwrap()
+        );
+
+        for garbage in ["someday", "in three days", "next", "in 2 fortnights", ""] {
+            assert!(
+                parse_due_date(garbage, now).is_err(),
+                "{:?} parsed",
+                garbage
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_valid_timestamps() {
+        let task = sample_task();
+        assert_eq!(
+            task.due_datetime(),
+            Some(Utc.with_ymd_and_hms(2024, 1, 20, 17, 0, 0).unwrap())
+        );
+        assert_eq!(
+            task.updated_datetime(),
+            Some(Utc.with_ymd_and_hms(2024, 1, 15, 7, 0, 0).unwrap())
+        );
+        assert!(task.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_garbage_timestamps() {
+        let mut task = sample_task();
+        task.created_at = "yesterday".to_string();
+        task.due_date = Some("2024-13-45".to_string());
+
+        assert_eq!(task.due_datetime(), None);
+        let problems = task.validate().unwrap_err();
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].contains("created_at"));
+        assert!(problems[1].contains("due_date"));
+    }
+
+    #[test]
+    fn test_validate_flags_due_date_before_creation() {
+        let mut task = sample_task();
+        task.due_date = Some("2024-01-14T09:00:00Z".to_string());
+
+        assert!(task.validate().is_ok());
+        let collection = TaskCollection {
+            tasks: vec![task.clone()],
+            version: "1.0".to_string(),
+        };
+        assert_eq!(
+            collection.validate().unwrap_err(),
+            vec![
+                "Task task-1: due_date 2024-01-14T09:00:00Z is before created_at 2024-01-15T09:00:00Z"
+                    .to_string()
+            ]
+        );
+
+        task.due_date = Some("2024-01-15T09:00:00Z".to_string());
+        assert_eq!(task.due_before_creation(), None);
+    }
+
+    #[test]
+    fn test_collection_validate_duplicate_ids_and_empty_titles() {
+        let mut untitled = sample_task();
+        untitled.id = "task-2".to_string();
+        untitled.title = "  ".to_string();
+        let collection = TaskCollection {
+            tasks: vec![sample_task(), sample_task(), untitled],
+            version: "1.0".to_string(),
+        };
+
+        let problems = collection.validate().unwrap_err();
+        assert_eq!(
+            problems,
+            vec![
+                "Task task-1: duplicate id".to_string(),
+                "Task task-2: empty title".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collection_validate_ok() {
+        let collection = TaskCollection {
+            tasks: vec![sample_task()],
+            version: "1.0".to_string(),
+        };
+        assert!(collection.validate().is_ok());
+    }
+
+    #[test]
+    fn test_new_pending_fills_id_and_stamps() {
+        let task = Task::new_pending("Write docs", "", Priority::High);
+        assert!(!task.id.is_empty());
+        assert_eq!(task.status, TaskStatus::Pending);
+        assert_eq!(task.created_at, task.updated_at);
+        assert!(task.created_datetime().is_some());
+        assert!(task.assignee.is_none() && task.tags.is_empty());
+        assert!(task.validate().is_ok());
+
+        let other = Task::new_pending("Write docs", "", Priority::High);
+        assert_ne!(task.id, other.id);
+    }
+
+    #[test]
+    fn test_slug_ids_avoid_collisions() {
+        let strategy: IdStrategy = "slug".parse().unwrap();
+        let mut tasks = Vec::new();
+        for _ in 0..3 {
+            let mut task = Task::new_pending("Write release notes!", "", Priority::Medium);
+            task.id = strategy.generate(&task.title, &tasks);
+            tasks.push(task);
+        }
+        let ids: Vec<&str> = tasks.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "write-release-notes",
+                "write-release-notes-2",
+                "write-release-notes-3"
+            ]
+        );
+
+        assert_eq!(
+            strategy.generate("  Été: v2.0 -- ship ", &[]),
+            "t-v2-0-ship"
+        );
+        assert_eq!(strategy.generate("???", &[]), "task");
+        assert!(Uuid::parse_str(&IdStrategy::Uuid.generate("Write", &tasks)).is_ok());
+        assert!("random".parse::<IdStrategy>().is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_self_parent() {
+        let mut task = sample_task();
+        task.parent_id = Some(task.id.clone());
+
+        assert!(task.is_own_parent());
+        let problems = task.validate().unwrap_err();
+        assert_eq!(
+            problems,
+            vec!["Task task-1: lists itself as its own parent"]
+        );
+    }
+
+    #[test]
+    fn test_parent_id_defaults_to_none() {
+        let json = r#"{
+            "id": "task-1",
+            "title": "Legacy",
+            "description": "Written before subtasks existed",
+            "status": "pending",
+            "priority": "low",
+            "created_at": "2024-01-15T09:00:00Z",
+            "updated_at": "2024-01-15T09:00:00Z",
+            "tags": [],
+            "assignee": null,
+            "due_date": null
+        }"#;
+        let task: Task = serde_json::from_str(json).unwrap();
+        assert_eq!(task.parent_id, None);
+        assert!(!serde_json::to_string(&task).unwrap().contains("parent_id"));
+    }
+
+    #[test]
+    fn test_find_dependency_cycle() {
+        let mut a = sample_task();
+        a.id = "a".to_string();
+        a.depends_on = vec!["b".to_string()];
+        let mut b = sample_task();
+        b.id = "b".to_string();
+
+        assert_eq!(find_dependency_cycle(&[a.clone(), b.clone()]), None);
+
+        b.depends_on = vec!["a".to_string()];
+        assert_eq!(
+            find_dependency_cycle(&[a, b]),
+            Some(vec!["a".to_string(), "b".to_string(), "a".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_recurrence_next_after() {
+        let from = parse_timestamp("2024-01-31T09:00:00Z").unwrap();
+
+        assert_eq!(
+            Recurrence::Daily.next_after(from),
+            parse_timestamp("2024-02-01T09:00:00Z")
+        );
+        assert_eq!(
+            Recurrence::Weekly.next_after(from),
+            parse_timestamp("2024-02-07T09:00:00Z")
+        );
+        assert_eq!(
+            Recurrence::Monthly.next_after(from),
+            parse_timestamp("2024-02-29T09:00:00Z")
+        );
+        assert!("hourly".parse::<Recurrence>().is_err());
+    }
+
+    #[test]
+    fn test_empty_metadata_is_not_serialized() {
+        let mut task = sample_task();
+        let json = serde_json::to_value(&task).unwrap();
+        assert!(json.get("metadata").is_none());
+
+        task.set_metadata("points", serde_json::json!(3));
+        let json = serde_json::to_value(&task).unwrap();
+        assert_eq!(json["metadata"]["points"], 3);
+    }
+
+    #[test]
+    fn test_status_transitions() {
+        assert!(TaskStatus::Pending.can_transition_to(&TaskStatus::Cancelled));
+        assert!(TaskStatus::InProgress.can_transition_to(&TaskStatus::Completed));
+        assert!(TaskStatus::Completed.can_transition_to(&TaskStatus::Pending));
+        assert!(TaskStatus::Cancelled.can_transition_to(&TaskStatus::Cancelled));
+        assert!(!TaskStatus::Cancelled.can_transition_to(&TaskStatus::InProgress));
+        assert!(!TaskStatus::Completed.can_transition_to(&TaskStatus::Cancelled));
+    }
+
+    #[test]
+    fn test_validate_limits() {
+        let limits = TaskLimits {
+            max_title_length: 10,
+            max_description_length: 100,
+            max_tags: 2,
+        };
+        let mut task = sample_task();
+        task.title = "Short".to_string();
+        assert!(task.validate_limits(&limits).is_ok());
+
+        task.title = "Much too long a title".to_string();
+        let err = task.validate_limits(&limits).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "title is too long: 21 characters (maximum 10)"
+        );
+
+        task.title = "Short".to_string();
+        task.tags = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let err = task.validate_limits(&limits).unwrap_err();
+        assert_eq!(err.to_string(), "tags has too many entries: 3 (maximum 2)");
+    }
+}
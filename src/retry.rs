@@ -0,0 +1,153 @@
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::error::StorageError;
+
+/// How storage operations are retried after transient I/O errors
+///
+/// Only [`StorageError::Io`] is retried; a missing file, invalid JSON or an unsupported
+/// version fails right away, since trying again would give the same answer. The delay
+/// doubles after every failed attempt, starting at `base_delay`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total number of tries, including the first; 1 disables retrying
+    pub max_attempts: u32,
+    /// Wait before the first retry
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Policy making `max_attempts` tries (at least one), waiting `base_delay`, then twice
+    /// as long, and so on between them
+    pub fn new(max_attempts: u32, base_delay: Duration) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            base_delay,
+        }
+    }
+
+    /// Policy that tries once and never retries
+    pub fn none() -> Self {
+        Self::new(1, Duration::ZERO)
+    }
+
+    /// Wait before retry number `retry` (1 for the first retry)
+    fn delay(&self, retry: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+    }
+
+    /// Run `operation` until it succeeds, fails with an error that isn't transient, or
+    /// the attempts run out; the last error is returned
+    pub async fn run<T, F, Fut>(&self, mut operation: F) -> Result<T, StorageError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, StorageError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match operation().await {
+                Err(e @ StorageError::Io { .. }) if attempt < self.max_attempts => {
+                    let delay = self.delay(attempt);
+                    warn!(
+                        "Storage attempt {} of {} failed, retrying in {:?}: {}",
+                        attempt, self.max_attempts, delay, e
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TaskCollection;
+    use crate::storage::MemoryStorage;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Storage whose loads fail with an I/O error a set number of times before working
+    struct FlakyStorage {
+        failures_left: AtomicU32,
+        attempts: AtomicU32,
+        memory: MemoryStorage,
+    }
+
+    impl FlakyStorage {
+        fn new(failures: u32) -> Self {
+            Self {
+                failures_left: AtomicU32::new(failures),
+                attempts: AtomicU32::new(0),
+                memory: MemoryStorage::new(),
+            }
+        }
+
+        async fn load_tasks(&self) -> Result<TaskCollection, StorageError> {
+            self.attempts.fetch_add(1, Ordering::SeqCst);
+            let failing = self
+                .failures_left
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+            if failing {
+                return Err(StorageError::io(
+                    PathBuf::from("tasks.json"),
+                    std::io::Error::new(std::io::ErrorKind::TimedOut, "mount not responding"),
+                ));
+            }
+            Ok(self.memory.load_tasks())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors_within_budget() {
+        let policy = RetryPolicy::new(3, Duration::from_millis(1));
+
+        let flaky = FlakyStorage::new(2);
+        assert!(policy.run(|| flaky.load_tasks()).await.is_ok());
+        assert_eq!(flaky.attempts.load(Ordering::SeqCst), 3);
+
+        let flakier = FlakyStorage::new(3);
+        let err = policy.run(|| flakier.load_tasks()).await.unwrap_err();
+        assert!(matches!(err, StorageError::Io { .. }));
+        assert_eq!(flakier.attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_parse_errors_are_not_retried() {
+        let policy = RetryPolicy::new(5, Duration::from_millis(1));
+        let attempts = AtomicU32::new(0);
+
+        let result: Result<(), _> = policy
+            .run(|| async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                let source = serde_json::from_str::<TaskCollection>("{").unwrap_err();
+                Err(StorageError::invalid_json(
+                    PathBuf::from("tasks.json"),
+                    "{",
+                    source,
+                ))
+            })
+            .await;
+        assert!(matches!(result, Err(StorageError::InvalidJson { .. })));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_delay_doubles() {
+        let policy = RetryPolicy::new(4, Duration::from_millis(100));
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(3), Duration::from_millis(400));
+        assert_eq!(RetryPolicy::new(0, Duration::ZERO).max_attempts, 1);
+    }
+}
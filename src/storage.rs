@@ -1,53 +1,980 @@
-use anyhow::Result;
-use std::path::PathBuf;
+use fs2::FileExt;
+use std::fs::{File, Metadata, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 use tokio::fs;
 
-use crate::models::TaskCollection;
+use tracing::{info, warn};
+
+use crate::error::StorageError;
+use crate::models::{SCHEMA_VERSION, Task, TaskCollection, parse_schema_version};
+use crate::retry::RetryPolicy;
 
 /// Task storage handler responsible for persisting and loading tasks
+///
+/// Tasks live in a JSON file by default. Loaded tasks are cached in memory and only re-read
+/// from disk when the file's modification time or size changes. Clones share the same cache,
+/// and [`TaskStorage::load_snapshot`] hands out the cached collection itself rather than a copy.
 #[derive(Debug, Clone)]
 pub struct TaskStorage {
+    backend: Backend,
+}
+
+/// Where a [`TaskStorage`] keeps its tasks
+#[derive(Debug, Clone)]
+enum Backend {
+    File(FileStorage),
+    /// Collection served instead of a file, see [`TaskStorage::in_memory`]
+    #[cfg(any(test, feature = "testing"))]
+    Memory(MemoryStorage),
+}
+
+/// Task file on disk with its cache, backups and locking
+#[derive(Debug, Clone)]
+struct FileStorage {
     file_path: PathBuf,
+    cache: Arc<Mutex<Option<CachedTasks>>>,
+    disk_reads: Arc<AtomicUsize>,
+    strict_versions: bool,
+    backup_count: usize,
+    pretty: bool,
+    retry: RetryPolicy,
+}
+
+/// Task collection held in memory instead of a file, so tests can run a [`TaskStorage`]
+/// without touching the filesystem; clones share the same collection
+#[cfg(any(test, feature = "testing"))]
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStorage {
+    tasks: Arc<Mutex<TaskCollection>>,
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl MemoryStorage {
+    /// Create an empty in-memory collection
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an in-memory collection holding `tasks`
+    pub fn with_tasks(tasks: TaskCollection) -> Self {
+        Self {
+            tasks: Arc::new(Mutex::new(tasks)),
+        }
+    }
+
+    /// Copy of the collection as currently stored
+    pub fn load_tasks(&self) -> TaskCollection {
+        self.tasks.lock().unwrap().clone()
+    }
+
+    /// Replace the stored collection
+    pub fn save_tasks(&self, tasks: &TaskCollection) {
+        *self.tasks.lock().unwrap() = tasks.clone();
+    }
+
+    /// Apply a read-modify-write under the lock, keeping the change only when `modify`
+    /// returns `Some`, like the file-backed storage
+    fn modify_tasks<T>(&self, modify: impl FnOnce(&mut TaskCollection) -> Option<T>) -> Option<T> {
+        let mut stored = self.tasks.lock().unwrap();
+        let mut tasks = stored.clone();
+        let result = modify(&mut tasks);
+        if result.is_some() {
+            *stored = tasks;
+        }
+        result
+    }
+}
+
+/// Tasks as last read from or written to disk, keyed on the file state at that time
+#[derive(Debug)]
+struct CachedTasks {
+    modified: SystemTime,
+    len: u64,
+    tasks: Arc<TaskCollection>,
+}
+
+impl CachedTasks {
+    fn is_fresh(&self, metadata: &Metadata) -> bool {
+        metadata
+            .modified()
+            .is_ok_and(|modified| modified == self.modified)
+            && metadata.len() == self.len
+    }
 }
 
 impl TaskStorage {
     /// Create a new task storage instance with the specified file path
     pub fn new(file_path: PathBuf) -> Self {
-        Self { file_path }
+        Self {
+            backend: Backend::File(FileStorage::new(file_path)),
+        }
+    }
+
+    /// Create a storage backed by `memory` rather than a file
+    /// File settings such as backups and retries don't apply, and backup restores and
+    /// compaction fail; the path reported is `:memory:`
+    #[cfg(any(test, feature = "testing"))]
+    pub fn in_memory(memory: MemoryStorage) -> Self {
+        Self {
+            backend: Backend::Memory(memory),
+        }
+    }
+
+    /// Apply a setting of the file backend; in-memory storage has none
+    fn configure(mut self, apply: impl FnOnce(&mut FileStorage)) -> Self {
+        match &mut self.backend {
+            Backend::File(file) => apply(file),
+            #[cfg(any(test, feature = "testing"))]
+            Backend::Memory(_) => {}
+        }
+        self
+    }
+
+    /// Reject task files with a newer or unreadable schema version instead of logging a warning
+    pub fn with_strict_versions(self, strict_versions: bool) -> Self {
+        self.configure(|file| file.strict_versions = strict_versions)
+    }
+
+    /// Keep the previous file contents as `<file>.bak.1` ... `<file>.bak.<count>` on every save,
+    /// newest first; 0 (the default) disables backups
+    pub fn with_backups(self, backup_count: usize) -> Self {
+        self.configure(|file| file.backup_count = backup_count)
+    }
+
+    /// Write the task file as indented JSON (the default) or, with `false`, as compact
+    /// single-line JSON; loading accepts either
+    pub fn with_pretty(self, pretty: bool) -> Self {
+        self.configure(|file| file.pretty = pretty)
+    }
+
+    /// Retry reads and writes of the task file that fail with an I/O error, as on a flaky
+    /// network mount; by default nothing is retried
+    pub fn with_retry(self, retry: RetryPolicy) -> Self {
+        self.configure(|file| file.retry = retry)
     }
 
     /// Load tasks from the JSON file
     /// If the file doesn't exist, returns an empty task collection
-    pub async fn load_tasks(&self) -> Result<TaskCollection> {
+    /// Holds a shared lock while reading, so loads wait for any in-progress save
+    /// Served from the in-memory cache when the file hasn't changed since it was last read or written
+    pub async fn load_tasks(&self) -> Result<TaskCollection, StorageError> {
+        Ok(Arc::unwrap_or_clone(self.load_snapshot().await?))
+    }
+
+    /// Load tasks like [`TaskStorage::load_tasks`], but share the cached collection instead of
+    /// copying it; every save swaps in a new snapshot, so one that was handed out never changes
+    pub async fn load_snapshot(&self) -> Result<Arc<TaskCollection>, StorageError> {
+        match &self.backend {
+            Backend::File(file) => file.load_snapshot().await,
+            #[cfg(any(test, feature = "testing"))]
+            Backend::Memory(memory) => Ok(Arc::new(memory.load_tasks())),
+        }
+    }
+
+    /// Save tasks to the JSON file
+    /// The content is written to a temporary file next to the target and then renamed over it,
+    /// so a crash mid-write never leaves a truncated task file behind
+    /// Holds an exclusive lock for the duration of the write
+    pub async fn save_tasks(&self, tasks: &TaskCollection) -> Result<(), StorageError> {
+        match &self.backend {
+            Backend::File(file) => file.save_tasks(tasks).await,
+            #[cfg(any(test, feature = "testing"))]
+            Backend::Memory(memory) => {
+                memory.save_tasks(tasks);
+                Ok(())
+            }
+        }
+    }
+
+    /// Apply a read-modify-write to the stored collection while holding the exclusive lock
+    /// The file is only rewritten when `modify` returns `Some`
+    pub async fn modify_tasks<T>(
+        &self,
+        modify: impl FnOnce(&mut TaskCollection) -> Option<T>,
+    ) -> Result<Option<T>, StorageError> {
+        match &self.backend {
+            Backend::File(file) => file.modify_tasks(modify).await,
+            #[cfg(any(test, feature = "testing"))]
+            Backend::Memory(memory) => Ok(memory.modify_tasks(modify)),
+        }
+    }
+
+    /// Insert a task, or replace the stored task with the same ID
+    /// Returns `true` if an existing task was replaced
+    pub async fn upsert_task(&self, task: &Task) -> Result<bool, StorageError> {
+        let replaced = self
+            .modify_tasks(|collection| {
+                match collection.tasks.iter_mut().find(|t| t.id == task.id) {
+                    Some(existing) => {
+                        *existing = task.clone();
+                        Some(true)
+                    }
+                    None => {
+                        collection.tasks.push(task.clone());
+                        Some(false)
+                    }
+                }
+            })
+            .await?;
+        Ok(replaced.unwrap_or(false))
+    }
+
+    /// Remove the task with the given ID
+    /// Returns `false` (without rewriting the file) if no such task exists
+    pub async fn remove_task(&self, task_id: &str) -> Result<bool, StorageError> {
+        let removed = self
+            .modify_tasks(|collection| {
+                let original_len = collection.tasks.len();
+                collection.tasks.retain(|t| t.id != task_id);
+                (collection.tasks.len() != original_len).then_some(())
+            })
+            .await?;
+        Ok(removed.is_some())
+    }
+
+    /// Replace the task file with the contents of backup `n` (1 is the most recent)
+    /// The backup must parse as a task collection; the file being replaced is itself
+    /// rotated into the backups, so a restore can be undone by restoring `1` again
+    pub async fn restore_backup(&self, n: usize) -> Result<TaskCollection, StorageError> {
+        match &self.backend {
+            Backend::File(file) => file.restore_backup(n).await,
+            #[cfg(any(test, feature = "testing"))]
+            Backend::Memory(_) => Err(StorageError::Unsupported {
+                operation: "restoring a backup",
+            }),
+        }
+    }
+
+    /// Rewrite the task file in the canonical layout every save uses, dropping hand-edited
+    /// indentation and whitespace; returns the file size in bytes before and after
+    pub async fn compact(&self) -> Result<(usize, usize), StorageError> {
+        match &self.backend {
+            Backend::File(file) => file.compact().await,
+            #[cfg(any(test, feature = "testing"))]
+            Backend::Memory(_) => Err(StorageError::Unsupported {
+                operation: "compaction",
+            }),
+        }
+    }
+
+    /// Path of the task file
+    pub fn file_path(&self) -> &Path {
+        match &self.backend {
+            Backend::File(file) => &file.file_path,
+            #[cfg(any(test, feature = "testing"))]
+            Backend::Memory(_) => Path::new(":memory:"),
+        }
+    }
+
+    /// Whether the task file currently exists on disk
+    pub fn file_exists(&self) -> bool {
+        match &self.backend {
+            Backend::File(file) => file.file_path.exists(),
+            #[cfg(any(test, feature = "testing"))]
+            Backend::Memory(_) => true,
+        }
+    }
+
+    /// Last modification time of the task file, or `None` if it doesn't exist yet
+    pub async fn modified_at(&self) -> Result<Option<SystemTime>, StorageError> {
+        match &self.backend {
+            Backend::File(file) => file.modified_at().await,
+            #[cfg(any(test, feature = "testing"))]
+            Backend::Memory(_) => Ok(None),
+        }
+    }
+
+    /// Number of times the task file has actually been read from disk
+    pub fn disk_reads(&self) -> usize {
+        match &self.backend {
+            Backend::File(file) => file.disk_reads.load(Ordering::Relaxed),
+            #[cfg(any(test, feature = "testing"))]
+            Backend::Memory(_) => 0,
+        }
+    }
+}
+
+impl FileStorage {
+    fn new(file_path: PathBuf) -> Self {
+        Self {
+            file_path,
+            cache: Arc::new(Mutex::new(None)),
+            disk_reads: Arc::new(AtomicUsize::new(0)),
+            strict_versions: false,
+            backup_count: 0,
+            pretty: true,
+            retry: RetryPolicy::none(),
+        }
+    }
+
+    /// Load the task file, holding a shared lock while reading so loads wait for any
+    /// in-progress save; served from the cache when the file hasn't changed
+    async fn load_snapshot(&self) -> Result<Arc<TaskCollection>, StorageError> {
         if !self.file_path.exists() {
             // Return empty collection if file doesn't exist
-            return Ok(TaskCollection::new());
+            return Ok(Arc::new(TaskCollection::new()));
+        }
+
+        let _lock = self.lock(false).await?;
+        self.retry.run(|| self.read_locked()).await
+    }
+
+    /// Save tasks to the task file, holding an exclusive lock for the duration of the write
+    async fn save_tasks(&self, tasks: &TaskCollection) -> Result<(), StorageError> {
+        self.ensure_parent_dir().await?;
+        let _lock = self.lock(true).await?;
+        self.save_locked(tasks).await
+    }
+
+    /// Apply a read-modify-write to the task file while holding the exclusive lock
+    async fn modify_tasks<T>(
+        &self,
+        modify: impl FnOnce(&mut TaskCollection) -> Option<T>,
+    ) -> Result<Option<T>, StorageError> {
+        self.ensure_parent_dir().await?;
+        let _lock = self.lock(true).await?;
+
+        let mut tasks = if self.file_path.exists() {
+            Arc::unwrap_or_clone(self.retry.run(|| self.read_locked()).await?)
+        } else {
+            TaskCollection::new()
+        };
+
+        let result = modify(&mut tasks);
+        if result.is_some() {
+            self.save_locked(&tasks).await?;
+        }
+        Ok(result)
+    }
+
+    async fn restore_backup(&self, n: usize) -> Result<TaskCollection, StorageError> {
+        // Lock before reading so a concurrent save can't rotate the backup out from under us
+        self.ensure_parent_dir().await?;
+        let _lock = self.lock(true).await?;
+
+        let backup_path = self.backup_path(n);
+        let content = self
+            .retry
+            .run(|| async {
+                fs::read_to_string(&backup_path)
+                    .await
+                    .map_err(|e| StorageError::io(backup_path.clone(), e))
+            })
+            .await?;
+        let mut tasks: TaskCollection = serde_json::from_str(&content)
+            .map_err(|e| StorageError::invalid_json(backup_path, &content, e))?;
+        self.migrate(&mut tasks)?;
+
+        self.save_locked(&tasks).await?;
+        Ok(tasks)
+    }
+
+    async fn compact(&self) -> Result<(usize, usize), StorageError> {
+        let _lock = self.lock(true).await?;
+        let before = fs::metadata(&self.file_path)
+            .await
+            .map_err(|e| self.io_error(e))?
+            .len();
+        let tasks = self.read_locked().await?;
+        self.save_locked(&tasks).await?;
+        let after = fs::metadata(&self.file_path)
+            .await
+            .map_err(|e| self.io_error(e))?
+            .len();
+        Ok((before as usize, after as usize))
+    }
+
+    /// Read the task file, assuming the caller holds a lock
+    async fn read_locked(&self) -> Result<Arc<TaskCollection>, StorageError> {
+        let metadata = fs::metadata(&self.file_path)
+            .await
+            .map_err(|e| self.io_error(e))?;
+        if let Some(cached) = self.cache.lock().unwrap().as_ref()
+            && cached.is_fresh(&metadata)
+        {
+            return Ok(cached.tasks.clone());
         }
 
-        let content = fs::read_to_string(&self.file_path).await?;
-        let tasks: TaskCollection = serde_json::from_str(&content)?;
+        let content = fs::read_to_string(&self.file_path)
+            .await
+            .map_err(|e| self.io_error(e))?;
+        self.disk_reads.fetch_add(1, Ordering::Relaxed);
+        let mut tasks: TaskCollection = serde_json::from_str(&content)
+            .map_err(|e| StorageError::invalid_json(self.file_path.clone(), &content, e))?;
+        self.migrate(&mut tasks)?;
+        let tasks = Arc::new(tasks);
+        self.update_cache(&metadata, Arc::clone(&tasks));
         Ok(tasks)
     }
 
-    /// Save tasks to the JSON file
-    pub async fn save_tasks(&self, tasks: &TaskCollection) -> Result<()> {
-        // Ensure the parent directory exists
-        if let Some(parent) = self.file_path.parent() {
-            fs::create_dir_all(parent).await?;
+    /// Upgrade a collection read from an older schema version to [`SCHEMA_VERSION`]
+    ///
+    /// Fields added since `1.0` are optional and already filled with defaults during
+    /// deserialization, so upgrading only bumps the version; the file itself is rewritten
+    /// in the new format on the next save. Newer or unreadable versions are loaded as-is
+    /// with a warning, or rejected when strict versions are enabled.
+    fn migrate(&self, tasks: &mut TaskCollection) -> Result<(), StorageError> {
+        let current = parse_schema_version(SCHEMA_VERSION);
+        match parse_schema_version(&tasks.version) {
+            Some(version) if Some(version) == current => Ok(()),
+            Some(version) if Some(version) < current => {
+                info!(
+                    "Migrating task file {} from schema version {} to {}",
+                    self.file_path.display(),
+                    tasks.version,
+                    SCHEMA_VERSION
+                );
+                tasks.version = SCHEMA_VERSION.to_string();
+                Ok(())
+            }
+            _ if self.strict_versions => Err(StorageError::UnsupportedVersion {
+                path: self.file_path.clone(),
+                version: tasks.version.clone(),
+            }),
+            _ => {
+                warn!(
+                    "Task file {} has unsupported schema version {} (this build supports up to {}); loading it anyway",
+                    self.file_path.display(),
+                    tasks.version,
+                    SCHEMA_VERSION
+                );
+                Ok(())
+            }
         }
+    }
+
+    /// Back up the current file and write `tasks` over it, assuming the caller holds the
+    /// exclusive lock; only the write is retried, so a retry doesn't rotate the backups twice
+    async fn save_locked(&self, tasks: &TaskCollection) -> Result<(), StorageError> {
+        self.rotate_backups().await?;
+        self.retry.run(|| self.write_locked(tasks)).await
+    }
 
-        let content = serde_json::to_string_pretty(tasks)?;
-        fs::write(&self.file_path, content).await?;
+    /// Atomically write the task file, assuming the caller holds the exclusive lock
+    async fn write_locked(&self, tasks: &TaskCollection) -> Result<(), StorageError> {
+        let content = if self.pretty {
+            serde_json::to_string_pretty(tasks)
+        } else {
+            serde_json::to_string(tasks)
+        }
+        .map_err(StorageError::Serialize)?;
+        let temp_path = self.sibling_path(".tmp");
+        fs::write(&temp_path, content)
+            .await
+            .map_err(|e| self.io_error(e))?;
+
+        if let Err(e) = fs::rename(&temp_path, &self.file_path).await {
+            // Best effort cleanup; the rename error is the one worth reporting
+            let _ = fs::remove_file(&temp_path).await;
+            return Err(self.io_error(e));
+        }
+
+        let metadata = fs::metadata(&self.file_path)
+            .await
+            .map_err(|e| self.io_error(e))?;
+        self.update_cache(&metadata, Arc::new(tasks.clone()));
         Ok(())
     }
 
-    /// Get the file path being used for storage
-    pub fn file_path(&self) -> &PathBuf {
-        &self.file_path
+    /// Shift existing backups up by one, dropping the oldest, and copy the current file to
+    /// `<file>.bak.1`; assumes the caller holds the exclusive lock
+    async fn rotate_backups(&self) -> Result<(), StorageError> {
+        if self.backup_count == 0 || !self.file_path.exists() {
+            return Ok(());
+        }
+
+        for n in (1..self.backup_count).rev() {
+            let from = self.backup_path(n);
+            if from.exists() {
+                let to = self.backup_path(n + 1);
+                fs::rename(&from, &to)
+                    .await
+                    .map_err(|e| StorageError::io(from, e))?;
+            }
+        }
+
+        let newest = self.backup_path(1);
+        fs::copy(&self.file_path, &newest)
+            .await
+            .map_err(|e| StorageError::io(newest, e))?;
+        Ok(())
     }
 
-    /// Check if the storage file exists
-    pub fn file_exists(&self) -> bool {
-        self.file_path.exists()
+    /// Path of the `n`th most recent backup, e.g. `tasks.json.bak.1`
+    fn backup_path(&self, n: usize) -> PathBuf {
+        self.sibling_path(&format!(".bak.{}", n))
+    }
+
+    /// Ensure the parent directory exists so the task and lock files can be created
+    async fn ensure_parent_dir(&self) -> Result<(), StorageError> {
+        if let Some(parent) = self.file_path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|e| self.io_error(e))?;
+        }
+        Ok(())
+    }
+
+    fn io_error(&self, source: std::io::Error) -> StorageError {
+        StorageError::io(self.file_path.clone(), source)
+    }
+
+    async fn modified_at(&self) -> Result<Option<SystemTime>, StorageError> {
+        match fs::metadata(&self.file_path).await {
+            Ok(metadata) => metadata.modified().map(Some).map_err(|e| self.io_error(e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(self.io_error(e)),
+        }
+    }
+
+    fn update_cache(&self, metadata: &Metadata, tasks: Arc<TaskCollection>) {
+        let mut cache = self.cache.lock().unwrap();
+        *cache = metadata.modified().ok().map(|modified| CachedTasks {
+            modified,
+            len: metadata.len(),
+            tasks,
+        });
+    }
+
+    /// Path next to the task file with the given suffix appended, e.g. `tasks.json.tmp`
+    fn sibling_path(&self, suffix: &str) -> PathBuf {
+        let mut file_name = self
+            .file_path
+            .file_name()
+            .map(|name| name.to_os_string())
+            .unwrap_or_default();
+        file_name.push(suffix);
+        self.file_path.with_file_name(file_name)
+    }
+
+    /// Acquire an advisory lock on the sidecar `<file>.lock` file
+    ///
+    /// A separate lock file is used because saves replace the task file by rename, which would
+    /// orphan a lock held on the old file. Acquisition blocks (on a blocking thread) until the
+    /// lock is available rather than failing; the lock is released when the guard is dropped.
+    async fn lock(&self, exclusive: bool) -> Result<FileLock, StorageError> {
+        let lock_path = self.sibling_path(".lock");
+        let locked = tokio::task::spawn_blocking(move || {
+            let file = OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .read(true)
+                .write(true)
+                .open(&lock_path)?;
+            if exclusive {
+                FileExt::lock_exclusive(&file)?;
+            } else {
+                FileExt::lock_shared(&file)?;
+            }
+            Ok(FileLock { file })
+        })
+        .await
+        .map_err(std::io::Error::other)
+        .and_then(|locked| locked);
+        locked.map_err(|e| self.io_error(e))
+    }
+}
+
+/// Guard holding an advisory file lock, released on drop
+struct FileLock {
+    file: File,
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Priority, TaskStatus};
+    use tempfile::TempDir;
+
+    fn sample_collection(count: usize) -> TaskCollection {
+        let mut collection = TaskCollection::new();
+        collection.tasks = (1..=count)
+            .map(|i| Task {
+                id: format!("task-{}", i),
+                title: format!("Task {}", i),
+                description: "Description with, commas and \"quotes\"".to_string(),
+                status: TaskStatus::Pending,
+                priority: Priority::Medium,
+                created_at: "2024-01-15T09:00:00Z".to_string(),
+                updated_at: "2024-01-15T09:00:00Z".to_string(),
+                tags: vec!["sample".to_string()],
+                assignee: None,
+                due_date: None,
+                parent_id: None,
+                depends_on: vec![],
+                recurrence: None,
+                comments: vec![],
+                metadata: Default::default(),
+                history: vec![],
+                order: None,
+            })
+            .collect();
+        collection
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_round_trip() {
+        let memory = MemoryStorage::new();
+        let storage = TaskStorage::in_memory(memory.clone());
+        assert!(storage.load_tasks().await.unwrap().tasks.is_empty());
+
+        storage.save_tasks(&sample_collection(3)).await.unwrap();
+        let loaded = storage.load_tasks().await.unwrap();
+        assert_eq!(loaded.tasks.len(), 3);
+        assert_eq!(loaded.tasks[2].id, "task-3");
+        assert_eq!(memory.load_tasks().tasks.len(), 3);
+
+        // A modification that reports no change leaves the stored collection alone
+        let unchanged: Option<()> = storage
+            .modify_tasks(|collection| {
+                collection.tasks.clear();
+                None
+            })
+            .await
+            .unwrap();
+        assert!(unchanged.is_none());
+        assert_eq!(storage.load_tasks().await.unwrap().tasks.len(), 3);
+
+        assert!(storage.remove_task("task-1").await.unwrap());
+        assert_eq!(memory.load_tasks().tasks.len(), 2);
+        assert!(!std::path::Path::new(":memory:").exists());
+        assert!(matches!(
+            storage.compact().await,
+            Err(StorageError::Unsupported { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_atomic_save_writes_complete_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("nested").join("tasks.json");
+        let storage = TaskStorage::new(path.clone());
+
+        storage.save_tasks(&sample_collection(3)).await.unwrap();
+        storage.save_tasks(&sample_collection(100)).await.unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let parsed: TaskCollection =
+            serde_json::from_str(&content).expect("Saved file should be valid JSON");
+        assert_eq!(parsed.tasks.len(), 100);
+        assert!(!path.with_file_name("tasks.json.tmp").exists());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_saves_do_not_corrupt_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tasks.json");
+        let storage = TaskStorage::new(path.clone());
+
+        let handles: Vec<_> = (1..=8)
+            .map(|i| {
+                let storage = storage.clone();
+                tokio::spawn(async move { storage.save_tasks(&sample_collection(i * 25)).await })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap().unwrap();
+        }
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let parsed: TaskCollection =
+            serde_json::from_str(&content).expect("Saved file should be valid JSON");
+        assert_eq!(parsed.tasks.len() % 25, 0);
+        assert!(parsed.tasks.iter().all(|task| task.tags == ["sample"]));
+    }
+
+    #[tokio::test]
+    async fn test_load_uses_cache_until_file_changes() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tasks.json");
+        let collection = sample_collection(2);
+        std::fs::write(&path, serde_json::to_string(&collection).unwrap()).unwrap();
+        let storage = TaskStorage::new(path.clone());
+
+        assert_eq!(storage.load_tasks().await.unwrap().tasks.len(), 2);
+        assert_eq!(storage.load_tasks().await.unwrap().tasks.len(), 2);
+        assert_eq!(storage.disk_reads(), 1);
+
+        // An external edit changes the file size and modification time
+        std::fs::write(&path, serde_json::to_string(&sample_collection(3)).unwrap()).unwrap();
+        assert_eq!(storage.load_tasks().await.unwrap().tasks.len(), 3);
+        assert_eq!(storage.disk_reads(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_save_updates_cache() {
+        let dir = TempDir::new().unwrap();
+        let storage = TaskStorage::new(dir.path().join("tasks.json"));
+
+        storage.save_tasks(&sample_collection(4)).await.unwrap();
+        assert_eq!(storage.load_tasks().await.unwrap().tasks.len(), 4);
+        assert_eq!(storage.disk_reads(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_upsert_new_and_existing_task() {
+        let dir = TempDir::new().unwrap();
+        let storage = TaskStorage::new(dir.path().join("tasks.json"));
+        let mut task = sample_collection(1).tasks.remove(0);
+
+        assert!(!storage.upsert_task(&task).await.unwrap());
+
+        task.title = "Renamed".to_string();
+        assert!(storage.upsert_task(&task).await.unwrap());
+
+        let stored = storage.load_tasks().await.unwrap();
+        assert_eq!(stored.tasks.len(), 1);
+        assert_eq!(stored.tasks[0].title, "Renamed");
+    }
+
+    #[tokio::test]
+    async fn test_remove_missing_task() {
+        let dir = TempDir::new().unwrap();
+        let storage = TaskStorage::new(dir.path().join("tasks.json"));
+        storage.save_tasks(&sample_collection(2)).await.unwrap();
+
+        assert!(!storage.remove_task("missing").await.unwrap());
+        assert!(storage.remove_task("task-1").await.unwrap());
+
+        let stored = storage.load_tasks().await.unwrap();
+        assert_eq!(stored.tasks.len(), 1);
+        assert_eq!(stored.tasks[0].id, "task-2");
+    }
+
+    #[tokio::test]
+    async fn test_truncated_json_reports_parse_position() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tasks.json");
+        std::fs::write(&path, "{\n  \"version\": \"1.0\",\n  \"tasks\": [").unwrap();
+        let storage = TaskStorage::new(path);
+
+        let err = storage.load_tasks().await.unwrap_err();
+        match &err {
+            StorageError::InvalidJson {
+                line,
+                column,
+                offset,
+                ..
+            } => {
+                assert_eq!(*line, 3);
+                assert_eq!(*column, 12);
+                assert_eq!(*offset, 33);
+            }
+            other => panic!("Expected InvalidJson, got {:?}", other),
+        }
+        let message = err.to_string();
+        assert!(message.contains("line 3, column 12"));
+        assert!(message.contains("byte offset 33"));
+    }
+
+    #[tokio::test]
+    async fn test_load_migrates_version_1_0_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tasks.json");
+        std::fs::write(
+            &path,
+            r#"{
+                "version": "1.0",
+                "tasks": [{
+                    "id": "task-1",
+                    "title": "Old task",
+                    "description": "",
+                    "status": "pending",
+                    "priority": "low",
+                    "created_at": "2024-01-15T09:00:00Z",
+                    "updated_at": "2024-01-15T09:00:00Z",
+                    "tags": [],
+                    "assignee": null,
+                    "due_date": null
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        let collection = TaskStorage::new(path).load_tasks().await.unwrap();
+        assert_eq!(collection.version, SCHEMA_VERSION);
+        let task = &collection.tasks[0];
+        assert_eq!(task.parent_id, None);
+        assert!(task.depends_on.is_empty());
+        assert_eq!(task.recurrence, None);
+        assert!(task.comments.is_empty());
+        assert!(task.metadata.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_newer_version_rejected_when_strict() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tasks.json");
+        std::fs::write(&path, r#"{"version": "9.0", "tasks": []}"#).unwrap();
+
+        let lenient = TaskStorage::new(path.clone());
+        assert_eq!(lenient.load_tasks().await.unwrap().version, "9.0");
+
+        let err = TaskStorage::new(path)
+            .with_strict_versions(true)
+            .load_tasks()
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            StorageError::UnsupportedVersion { ref version, .. } if version == "9.0"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_save_backs_up_previous_contents() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tasks.json");
+        let storage = TaskStorage::new(path.clone()).with_backups(3);
+
+        storage.save_tasks(&sample_collection(1)).await.unwrap();
+        assert!(!path.with_file_name("tasks.json.bak.1").exists());
+
+        storage.save_tasks(&sample_collection(2)).await.unwrap();
+        let backup = std::fs::read_to_string(path.with_file_name("tasks.json.bak.1")).unwrap();
+        let backup: TaskCollection = serde_json::from_str(&backup).unwrap();
+        assert_eq!(backup.tasks.len(), 1);
+
+        let restored = storage.restore_backup(1).await.unwrap();
+        assert_eq!(restored.tasks.len(), 1);
+        assert_eq!(storage.load_tasks().await.unwrap().tasks.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_backup_rotation_keeps_last_n() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tasks.json");
+        let storage = TaskStorage::new(path.clone()).with_backups(2);
+
+        for count in 1..=5 {
+            storage.save_tasks(&sample_collection(count)).await.unwrap();
+        }
+
+        let backup_len = |n: usize| {
+            let content =
+                std::fs::read_to_string(path.with_file_name(format!("tasks.json.bak.{}", n)))
+                    .unwrap();
+            serde_json::from_str::<TaskCollection>(&content)
+                .unwrap()
+                .tasks
+                .len()
+        };
+        assert_eq!(backup_len(1), 4);
+        assert_eq!(backup_len(2), 3);
+        assert!(!path.with_file_name("tasks.json.bak.3").exists());
+        assert!(matches!(
+            storage.restore_backup(3).await,
+            Err(StorageError::NotFound { .. })
+        ));
+
+        // Restoring reads the backup before rotation shifts the live file into its slot
+        assert_eq!(storage.restore_backup(1).await.unwrap().tasks.len(), 4);
+        assert_eq!(storage.load_tasks().await.unwrap().tasks.len(), 4);
+        assert_eq!(backup_len(1), 5);
+        assert_eq!(backup_len(2), 4);
+    }
+
+    #[tokio::test]
+    async fn test_retried_write_rotates_backups_once() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tasks.json");
+        let storage = TaskStorage::new(path.clone())
+            .with_backups(3)
+            .with_retry(RetryPolicy::new(3, std::time::Duration::from_millis(200)));
+        storage.save_tasks(&sample_collection(1)).await.unwrap();
+        storage.save_tasks(&sample_collection(2)).await.unwrap();
+
+        // A directory in the way of the temp file fails the first write until it's removed
+        let temp_path = path.with_file_name("tasks.json.tmp");
+        std::fs::create_dir(&temp_path).unwrap();
+        let blocker = tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            std::fs::remove_dir(&temp_path).unwrap();
+        });
+        storage.save_tasks(&sample_collection(3)).await.unwrap();
+        blocker.await.unwrap();
+
+        let backup_len = |n: usize| {
+            let content =
+                std::fs::read_to_string(path.with_file_name(format!("tasks.json.bak.{}", n)))
+                    .unwrap();
+            serde_json::from_str::<TaskCollection>(&content)
+                .unwrap()
+                .tasks
+                .len()
+        };
+        assert_eq!(storage.load_tasks().await.unwrap().tasks.len(), 3);
+        assert_eq!(backup_len(1), 2);
+        assert_eq!(backup_len(2), 1);
+        assert!(!path.with_file_name("tasks.json.bak.3").exists());
+    }
+
+    #[tokio::test]
+    async fn test_compact_json_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tasks.json");
+        let compact = TaskStorage::new(path.clone()).with_pretty(false);
+        compact.save_tasks(&sample_collection(3)).await.unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 1);
+        let pretty = TaskStorage::new(path.clone());
+        let loaded = pretty.load_tasks().await.unwrap();
+        assert_eq!(loaded.tasks.len(), 3);
+        assert_eq!(
+            loaded.tasks[0].description,
+            sample_collection(1).tasks[0].description
+        );
+
+        pretty.save_tasks(&loaded).await.unwrap();
+        assert!(std::fs::read_to_string(&path).unwrap().lines().count() > 1);
+        assert_eq!(compact.load_tasks().await.unwrap().tasks.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_compact_preserves_content() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tasks.json");
+        let original = r#"{
+
+        "version" :   "1.2",
+        "tasks": [
+            {
+                "id": "task-1",   "title": "Bloated",
+                "description": "",
+                "status": "pending",
+                "priority": "low",
+                "created_at": "2024-01-15T09:00:00Z",
+                "updated_at": "2024-01-15T09:00:00Z",
+                "tags": [   ],
+                "assignee": null,
+                "due_date": null
+            }
+        ]
+}
+
+
+"#;
+        std::fs::write(&path, original).unwrap();
+        let storage = TaskStorage::new(path.clone());
+
+        let (before, after) = storage.compact().await.unwrap();
+        assert_eq!(before, original.len());
+        assert!(after < before);
+
+        let rewritten = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(rewritten.len(), after);
+        let original: serde_json::Value = serde_json::from_str(original).unwrap();
+        let rewritten: serde_json::Value = serde_json::from_str(&rewritten).unwrap();
+        assert_eq!(original, rewritten);
     }
 }
@@ -1,145 +1,5096 @@
-use anyhow::Result;
-use std::collections::HashMap;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tracing::warn;
 
-use crate::models::{Priority, Task, TaskCollection, TaskStatus};
+use crate::clock::{Clock, SystemClock, TimestampFormat};
+use crate::error::TaskError;
+use crate::event_log::{EventLog, TaskEvent};
+use crate::models::{
+    IdStrategy, Priority, Recurrence, Task, TaskCollection, TaskComment, TaskLimits, TaskStatus,
+    find_dependency_cycle, parse_due_date, parse_timestamp,
+};
 use crate::storage::TaskStorage;
+#[cfg(feature = "webhooks")]
+use crate::webhook::Webhook;
 
 /// Service for managing task operations and business logic
 #[derive(Debug, Clone)]
 pub struct TaskService {
     storage: TaskStorage,
+    archive: Option<TaskStorage>,
+    strict_validation: bool,
+    strict_filters: bool,
+    strict_transitions: bool,
+    limits: TaskLimits,
+    priority_weights: HashMap<Priority, u32>,
+    clock: Arc<dyn Clock>,
+    timestamp_format: TimestampFormat,
+    default_priority: Priority,
+    default_status: TaskStatus,
+    id_strategy: IdStrategy,
+    event_log: Option<EventLog>,
+    #[cfg(feature = "webhooks")]
+    webhook: Option<Webhook>,
+    undo: Arc<Mutex<Option<TaskCollection>>>,
 }
 
 impl TaskService {
     /// Create a new task service with the given storage
     pub fn new(storage: TaskStorage) -> Self {
-        Self { storage }
+        Self {
+            storage,
+            archive: None,
+            strict_validation: false,
+            strict_filters: false,
+            strict_transitions: false,
+            limits: TaskLimits::default(),
+            priority_weights: HashMap::new(),
+            clock: Arc::new(SystemClock),
+            timestamp_format: TimestampFormat::default(),
+            default_priority: Priority::Medium,
+            default_status: TaskStatus::Pending,
+            id_strategy: IdStrategy::default(),
+            event_log: None,
+            #[cfg(feature = "webhooks")]
+            webhook: None,
+            undo: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Choose whether loading a malformed task file fails (strict) or only logs warnings (lenient)
+    pub fn with_strict_validation(mut self, strict_validation: bool) -> Self {
+        self.strict_validation = strict_validation;
+        self
+    }
+
+    /// Choose whether unknown filter keys are rejected (strict) or ignored (lenient)
+    pub fn with_strict_filters(mut self, strict_filters: bool) -> Self {
+        self.strict_filters = strict_filters;
+        self
+    }
+
+    /// Enforce [`TaskStatus::can_transition_to`] on status changes unless the caller forces them
+    pub fn with_strict_transitions(mut self, strict_transitions: bool) -> Self {
+        self.strict_transitions = strict_transitions;
+        self
+    }
+
+    /// Set the size limits enforced when tasks are imported or changed
+    pub fn with_limits(mut self, limits: TaskLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Set the storage archive_completed moves finished tasks into
+    pub fn with_archive(mut self, archive: TaskStorage) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    /// Choose how create_task picks IDs: random UUIDs (the default) or slugs of the title
+    pub fn with_id_strategy(mut self, id_strategy: IdStrategy) -> Self {
+        self.id_strategy = id_strategy;
+        self
+    }
+
+    /// Append an event to `event_log` for every task created, changed or deleted through
+    /// this service
+    pub fn with_event_log(mut self, event_log: EventLog) -> Self {
+        self.event_log = Some(event_log);
+        self
+    }
+
+    /// Post an event to `webhook` for every task created, changed or deleted through this
+    /// service, without waiting for the request to finish
+    #[cfg(feature = "webhooks")]
+    pub fn with_webhook(mut self, webhook: Webhook) -> Self {
+        self.webhook = Some(webhook);
+        self
+    }
+
+    /// Whether changes need to be turned into [`TaskEvent`]s for an event log or webhook
+    fn publishes_events(&self) -> bool {
+        #[cfg(feature = "webhooks")]
+        if self.webhook.is_some() {
+            return true;
+        }
+        self.event_log.is_some()
+    }
+
+    /// Override the priority weights used by workload and next_task; priorities missing from
+    /// the map keep [`Priority::weight`]
+    pub fn with_priority_weights(mut self, priority_weights: HashMap<Priority, u32>) -> Self {
+        self.priority_weights = priority_weights;
+        self
+    }
+
+    /// Weight of a priority, honouring configured overrides
+    pub fn priority_weight(&self, priority: &Priority) -> u32 {
+        self.priority_weights
+            .get(priority)
+            .copied()
+            .unwrap_or_else(|| priority.weight())
+    }
+
+    /// Use the given clock instead of the system clock for stamping and due-date checks
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Set how `created_at`/`updated_at` stamps are written
+    pub fn with_timestamp_format(mut self, timestamp_format: TimestampFormat) -> Self {
+        self.timestamp_format = timestamp_format;
+        self
+    }
+
+    /// Set the priority and status given to created tasks that don't specify them
+    pub fn with_task_defaults(mut self, priority: Priority, status: TaskStatus) -> Self {
+        self.default_priority = priority;
+        self.default_status = status;
+        self
+    }
+
+    /// Current time according to the service's clock
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// Current time formatted for the task file's `created_at`/`updated_at` fields
+    pub fn timestamp(&self) -> String {
+        self.timestamp_format.format(self.now())
+    }
+
+    /// Load all tasks from storage and validate them
+    /// In strict mode validation problems are returned as an error, otherwise they are logged
+    pub async fn load_tasks(&self) -> Result<TaskCollection, TaskError> {
+        Ok(Arc::unwrap_or_clone(self.snapshot().await?))
     }
 
-    /// Load all tasks from storage
-    pub async fn load_tasks(&self) -> Result<TaskCollection> {
-        self.storage.load_tasks().await
+    /// Validated tasks shared with storage's cache, for read-only use
+    /// Concurrent readers get the same `Arc` until a change swaps in a new collection,
+    /// so reads neither re-parse the file nor copy the tasks
+    pub async fn snapshot(&self) -> Result<Arc<TaskCollection>, TaskError> {
+        let task_collection = self.storage.load_snapshot().await?;
+
+        if let Err(problems) = task_collection.validate() {
+            if self.strict_validation {
+                return Err(TaskError::Validation(format!(
+                    "Invalid task file: {}",
+                    problems.join("; ")
+                )));
+            }
+            for problem in &problems {
+                warn!("Invalid task file: {}", problem);
+            }
+        }
+
+        Ok(task_collection)
     }
 
     /// Save tasks to storage
-    pub async fn save_tasks(&self, tasks: &TaskCollection) -> Result<()> {
-        self.storage.save_tasks(tasks).await
+    pub async fn save_tasks(&self, tasks: &TaskCollection) -> Result<(), TaskError> {
+        self.modify_tasks(false, |collection| {
+            *collection = tasks.clone();
+            Some(())
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Put the task file back the way it was before the most recent change made through this
+    /// service, returning the restored collection
+    ///
+    /// Only one step is kept and undoing uses it up, so a second undo fails until something
+    /// changes again. Only the task file is restored; tasks moved into the archive stay there.
+    pub async fn undo(&self) -> Result<TaskCollection, TaskError> {
+        let snapshot = self
+            .undo
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or_else(|| TaskError::Validation("Nothing to undo".to_string()))?;
+        let mut events = Vec::new();
+        let saved = self
+            .storage
+            .modify_tasks(|collection| {
+                if self.publishes_events() {
+                    events = TaskEvent::between(collection, &snapshot, &self.timestamp());
+                }
+                *collection = snapshot.clone();
+                Some(())
+            })
+            .await;
+        if let Err(e) = saved {
+            *self.undo.lock().unwrap() = Some(snapshot);
+            return Err(e.into());
+        }
+        self.publish_events(events).await;
+        Ok(snapshot)
+    }
+
+    /// Append `events` to the event log and post them to the webhook, where configured
+    /// The task file is already saved at this point, so failures are only logged
+    async fn publish_events(&self, events: Vec<TaskEvent>) {
+        if let Some(event_log) = &self.event_log
+            && let Err(e) = event_log.append(&events).await
+        {
+            warn!(
+                "Failed to append {} event(s) to {}: {}",
+                events.len(),
+                event_log.path().display(),
+                e
+            );
+        }
+        #[cfg(feature = "webhooks")]
+        if let Some(webhook) = &self.webhook {
+            webhook.notify(events);
+        }
+    }
+
+    /// Build a filter from raw MCP tool arguments
+    ///
+    /// `extra_keys` lists the non-filter arguments the tool accepts (e.g. pagination options).
+    /// In strict-filter mode any other unrecognized key is an error; otherwise it is ignored.
+    pub fn filter_from_arguments(
+        &self,
+        arguments: &serde_json::Map<String, serde_json::Value>,
+        extra_keys: &[&str],
+    ) -> Result<TaskFilter, TaskError> {
+        if self.strict_filters {
+            let unknown: Vec<&str> = arguments
+                .keys()
+                .map(String::as_str)
+                .filter(|key| !TaskFilter::KEYS.contains(key) && !extra_keys.contains(key))
+                .collect();
+            if !unknown.is_empty() {
+                return Err(TaskError::Validation(format!(
+                    "Unknown filter keys: {} (expected {})",
+                    unknown.join(", "),
+                    TaskFilter::KEYS.join(", ")
+                )));
+            }
+        }
+        TaskFilter::from_arguments(arguments)
     }
 
     /// Filter tasks based on criteria
-    pub fn filter_tasks(&self, tasks: &[Task], filters: &HashMap<String, String>) -> Vec<Task> {
+    pub fn filter_tasks(&self, tasks: &[Task], filter: &TaskFilter) -> Vec<Task> {
         tasks
             .iter()
-            .filter(|task| {
-                for (key, value) in filters {
-                    match key.as_str() {
-                        "status" => {
-                            let status_match = match value.as_str() {
-                                "pending" => task.status == TaskStatus::Pending,
-                                "in_progress" => task.status == TaskStatus::InProgress,
-                                "completed" => task.status == TaskStatus::Completed,
-                                "cancelled" => task.status == TaskStatus::Cancelled,
-                                _ => false,
-                            };
-                            if !status_match {
-                                return false;
-                            }
-                        }
-                        "priority" => {
-                            let priority_match = match value.as_str() {
-                                "low" => task.priority == Priority::Low,
-                                "medium" => task.priority == Priority::Medium,
-                                "high" => task.priority == Priority::High,
-                                "critical" => task.priority == Priority::Critical,
-                                _ => false,
-                            };
-                            if !priority_match {
-                                return false;
-                            }
-                        }
-                        "assignee" => {
-                            if task.assignee.as_ref() != Some(value) {
-                                return false;
-                            }
-                        }
-                        "tag" => {
-                            if !task.tags.contains(value) {
-                                return false;
+            .filter(|task| filter.matches(task))
+            .cloned()
+            .collect()
+    }
+
+    /// Count the tasks that match a filter
+    pub async fn count(&self, filter: &TaskFilter) -> Result<usize, TaskError> {
+        let task_collection = self.snapshot().await?;
+        Ok(self.filter_tasks(&task_collection.tasks, filter).len())
+    }
+
+    /// Sort tasks in place; the sort is stable, so ties keep their file order
+    pub fn sort_tasks(&self, tasks: &mut [Task], sort_by: SortBy) {
+        match sort_by {
+            // Tasks without a position go after the ordered ones
+            SortBy::Order => tasks.sort_by_key(|task| (task.order.is_none(), task.order)),
+        }
+    }
+
+    /// Take a single page out of an already filtered and sorted task list
+    /// An offset past the end yields an empty page
+    pub fn paginate(&self, tasks: Vec<Task>, offset: usize, limit: Option<usize>) -> TaskPage {
+        let total = tasks.len();
+        let tasks = tasks
+            .into_iter()
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect();
+
+        TaskPage {
+            tasks,
+            total,
+            offset,
+        }
+    }
+
+    /// Format tasks as a markdown bullet list
+    pub fn format_task_list(&self, tasks: &[Task]) -> String {
+        if tasks.is_empty() {
+            return "No tasks found.".to_string();
+        }
+
+        tasks
+            .iter()
+            .map(|task| {
+                let mut line = format!(
+                    "- **{}** (`{}`) - status: {}, priority: {}",
+                    task.title, task.id, task.status, task.priority
+                );
+                if let Some(assignee) = &task.assignee {
+                    line.push_str(&format!(", assignee: {}", assignee));
+                }
+                if let Some(due_date) = &task.due_date {
+                    line.push_str(&format!(", due: {}", due_date));
+                }
+                if !task.tags.is_empty() {
+                    line.push_str(&format!(", tags: {}", task.tags.join(", ")));
+                }
+                line
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Import tasks from CSV in the same column layout as [`TaskService::export_csv`]
+    ///
+    /// `id`, `title`, `status` and `priority` columns are required. Merge upserts rows by ID,
+    /// keeping fields CSV can't carry (comments, metadata, recurrence) on existing tasks;
+    /// replace overwrites the whole collection. Any invalid row rejects the whole import,
+    /// with every problem listed in the error, and so does a dependency cycle in the
+    /// imported result.
+    pub async fn import_csv(
+        &self,
+        data: &str,
+        mode: ImportMode,
+    ) -> Result<ImportSummary, TaskError> {
+        let rows = parse_csv_tasks(data, &self.timestamp(), &self.limits)?;
+        for row in &rows {
+            self.check_due_date(row)?;
+        }
+
+        let summary = self
+            .try_modify_tasks(false, |collection| {
+                let mut summary = ImportSummary::default();
+                match mode {
+                    ImportMode::Replace => {
+                        summary.created = rows.len();
+                        collection.tasks = rows;
+                    }
+                    ImportMode::Merge => {
+                        for row in rows {
+                            match collection.tasks.iter_mut().find(|t| t.id == row.id) {
+                                Some(existing) => {
+                                    merge_csv_row(existing, row);
+                                    summary.updated += 1;
+                                }
+                                None => {
+                                    collection.tasks.push(row);
+                                    summary.created += 1;
+                                }
                             }
                         }
-                        _ => {} // Ignore unknown filters
                     }
                 }
-                true
+                self.validate_dependencies(&collection.tasks)?;
+                summary.total = collection.tasks.len();
+                Ok(Some(summary))
             })
-            .cloned()
-            .collect()
+            .await?;
+        Ok(summary.unwrap_or_default())
+    }
+
+    /// Render tasks that have a due date as an iCalendar feed of VTODO entries
+    /// Tasks without a (parseable) due date are skipped
+    pub async fn export_ical(&self) -> Result<String, TaskError> {
+        let task_collection = self.snapshot().await?;
+        let stamp = self.now().format(ICAL_DATETIME_FORMAT).to_string();
+
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            "PRODID:-//mcp_todo_task//Task Manager//EN".to_string(),
+        ];
+        for task in &task_collection.tasks {
+            let Some(due) = task.due_datetime() else {
+                continue;
+            };
+            lines.push("BEGIN:VTODO".to_string());
+            lines.push(format!("UID:{}", ical_text(&task.id)));
+            lines.push(format!("DTSTAMP:{}", stamp));
+            lines.push(format!("SUMMARY:{}", ical_text(&task.title)));
+            if !task.description.is_empty() {
+                lines.push(format!("DESCRIPTION:{}", ical_text(&task.description)));
+            }
+            lines.push(format!("DUE:{}", due.format(ICAL_DATETIME_FORMAT)));
+            lines.push(format!("PRIORITY:{}", ical_priority(&task.priority)));
+            lines.push(format!("STATUS:{}", ical_status(&task.status)));
+            if !task.tags.is_empty() {
+                let tags: Vec<String> = task.tags.iter().map(|t| ical_text(t)).collect();
+                lines.push(format!("CATEGORIES:{}", tags.join(",")));
+            }
+            lines.push("END:VTODO".to_string());
+        }
+        lines.push("END:VCALENDAR".to_string());
+
+        Ok(lines
+            .iter()
+            .map(|line| fold_ical_line(line))
+            .collect::<Vec<_>>()
+            .join("\r\n")
+            + "\r\n")
+    }
+
+    /// Render every task as a GitHub-flavored Markdown table, or with `group_by`
+    /// (`status`, `priority`, `assignee` or `tag`) as one table per group under its own heading
+    pub async fn export_markdown(&self, group_by: Option<&str>) -> Result<String, TaskError> {
+        let group_by = group_by.map(str::parse::<GroupBy>).transpose()?;
+        let tasks = self.load_tasks().await?.tasks;
+        if tasks.is_empty() {
+            return Ok("No tasks found.".to_string());
+        }
+
+        let sections: Vec<(String, Vec<Task>)> = match group_by {
+            None => return Ok(markdown_table(&tasks)),
+            Some(GroupBy::Status) => group_tasks(tasks, |t| t.status.clone())
+                .into_iter()
+                .map(|(status, tasks)| (format!("Status: {}", status), tasks))
+                .collect(),
+            Some(GroupBy::Priority) => group_tasks(tasks, |t| t.priority.clone())
+                .into_iter()
+                .rev()
+                .map(|(priority, tasks)| (format!("Priority: {}", priority), tasks))
+                .collect(),
+            Some(GroupBy::Assignee) => group_tasks(tasks, |t| {
+                t.assignee
+                    .clone()
+                    .unwrap_or_else(|| UNASSIGNED_BUCKET.to_string())
+            })
+            .into_iter()
+            .map(|(assignee, tasks)| (format!("Assignee: {}", assignee), tasks))
+            .collect(),
+            Some(GroupBy::Tag) => group_tasks_by_tag(tasks)
+                .into_iter()
+                .map(|(tag, tasks)| (format!("Tag: {}", tag), tasks))
+                .collect(),
+        };
+
+        Ok(sections
+            .iter()
+            .map(|(heading, tasks)| format!("## {}\n\n{}", heading, markdown_table(tasks)))
+            .collect::<Vec<_>>()
+            .join("\n\n"))
+    }
+
+    /// Bucket every task by a field, keyed by the field's display value
+    /// Unassigned and untagged tasks go under [`UNASSIGNED_BUCKET`] and [`UNTAGGED_BUCKET`],
+    /// and a task with several tags appears under each of them
+    pub async fn group_by(&self, field: GroupBy) -> Result<BTreeMap<String, Vec<Task>>, TaskError> {
+        let tasks = self.load_tasks().await?.tasks;
+        Ok(match field {
+            GroupBy::Status => group_tasks(tasks, |t| t.status.to_string()),
+            GroupBy::Priority => group_tasks(tasks, |t| t.priority.to_string()),
+            GroupBy::Assignee => group_tasks(tasks, |t| {
+                t.assignee
+                    .clone()
+                    .unwrap_or_else(|| UNASSIGNED_BUCKET.to_string())
+            }),
+            GroupBy::Tag => group_tasks_by_tag(tasks),
+        })
     }
 
     /// Find a task by ID
-    pub async fn find_task_by_id(&self, task_id: &str) -> Result<Option<Task>> {
-        let task_collection = self.load_tasks().await?;
-        Ok(task_collection
+    pub async fn find_task_by_id(&self, task_id: &str) -> Result<Task, TaskError> {
+        let task_collection = self.snapshot().await?;
+        task_collection
             .tasks
             .iter()
             .find(|t| t.id == task_id)
-            .cloned())
+            .cloned()
+            .ok_or_else(|| TaskError::NotFound(task_id.to_string()))
     }
 
-    /// Get task statistics
-    pub async fn get_task_statistics(&self) -> Result<TaskStatistics> {
-        let task_collection = self.load_tasks().await?;
-        let stats = TaskStatistics::from_tasks(&task_collection.tasks);
-        Ok(stats)
+    /// Find several tasks by ID in one pass
+    /// Returns the tasks that were found (in the requested order) and the IDs that were not
+    pub async fn find_tasks_by_ids(
+        &self,
+        task_ids: &[String],
+    ) -> Result<(Vec<Task>, Vec<String>), TaskError> {
+        let task_collection = self.snapshot().await?;
+        let mut found = Vec::new();
+        let mut missing = Vec::new();
+
+        for task_id in task_ids {
+            match task_collection.tasks.iter().find(|t| &t.id == task_id) {
+                Some(task) => found.push(task.clone()),
+                None => missing.push(task_id.clone()),
+            }
+        }
+
+        Ok((found, missing))
     }
-}
 
-/// Statistics about tasks
-#[derive(Debug, Clone)]
-pub struct TaskStatistics {
-    pub total_tasks: usize,
-    pub status_counts: HashMap<String, usize>,
-    pub priority_counts: HashMap<String, usize>,
-}
+    /// Find the direct children of a task
+    /// A task that lists itself as its own parent is never reported as its own child
+    pub async fn children_of(&self, parent_id: &str) -> Result<Vec<Task>, TaskError> {
+        let task_collection = self.snapshot().await?;
+        if !task_collection.tasks.iter().any(|t| t.id == parent_id) {
+            return Err(TaskError::NotFound(parent_id.to_string()));
+        }
 
-impl TaskStatistics {
-    /// Create task statistics from a collection of tasks
-    pub fn from_tasks(tasks: &[Task]) -> Self {
-        let total_tasks = tasks.len();
-        let mut status_counts = HashMap::new();
-        let mut priority_counts = HashMap::new();
+        Ok(task_collection
+            .tasks
+            .iter()
+            .filter(|t| t.parent_id.as_deref() == Some(parent_id) && !t.is_own_parent())
+            .cloned()
+            .collect())
+    }
 
-        for task in tasks {
-            *status_counts
-                .entry(format!("{:?}", task.status))
-                .or_insert(0) += 1;
-            *priority_counts
-                .entry(format!("{:?}", task.priority))
-                .or_insert(0) += 1;
+    /// Apply a partial update to a task and bump its `updated_at` timestamp
+    /// Fields not set in the update, including comments, are left untouched
+    /// With `dry_run` the updated task is returned but nothing is saved
+    pub async fn update_task(
+        &self,
+        task_id: &str,
+        update: &TaskUpdate,
+        dry_run: bool,
+    ) -> Result<Task, TaskError> {
+        update.validate()?;
+
+        let updated = self
+            .try_modify_tasks(dry_run, |collection| {
+                let Some(task) = collection.tasks.iter_mut().find(|t| t.id == task_id) else {
+                    return Ok(None);
+                };
+                if let Some(status) = &update.status {
+                    self.check_transition(&task.status, status, update.force)?;
+                }
+                let before = task.clone();
+                update.apply(task);
+                task.validate_limits(&self.limits)?;
+                self.check_due_date(task)?;
+                let now = self.timestamp();
+                task.record_changes(&before, &now);
+                task.updated_at = now;
+                Ok(Some(task.clone()))
+            })
+            .await?;
+        updated.ok_or_else(|| TaskError::NotFound(task_id.to_string()))
+    }
+
+    /// Apply an RFC 6902 JSON Patch to a task's JSON form and save the result
+    ///
+    /// The patched task must still deserialize, keep its ID, pass validation and the size
+    /// limits, and not create a dependency cycle; otherwise nothing is saved. `history` and
+    /// `updated_at` are managed here, so patches to them are overwritten. Status changes
+    /// follow the transition rules unless `force` is set.
+    pub async fn apply_patch(
+        &self,
+        task_id: &str,
+        patch: &json_patch::Patch,
+        force: bool,
+    ) -> Result<Task, TaskError> {
+        let patched = self
+            .try_modify_tasks(false, |collection| {
+                let Some(index) = collection.tasks.iter().position(|t| t.id == task_id) else {
+                    return Ok(None);
+                };
+                let before = collection.tasks[index].clone();
+
+                let mut value = serde_json::to_value(&before).map_err(|e| {
+                    TaskError::Validation(format!("Failed to serialize task: {}", e))
+                })?;
+                json_patch::patch(&mut value, patch)
+                    .map_err(|e| TaskError::Validation(format!("Invalid patch: {}", e)))?;
+                let mut task: Task = serde_json::from_value(value).map_err(|e| {
+                    TaskError::Validation(format!("Patched task is not valid: {}", e))
+                })?;
+
+                if task.id != before.id {
+                    return Err(TaskError::Validation(
+                        "A patch cannot change the task ID".to_string(),
+                    ));
+                }
+                if task.status != before.status {
+                    self.check_transition(&before.status, &task.status, force)?;
+                }
+                task.validate().map_err(|problems| {
+                    TaskError::Validation(format!(
+                        "Patched task is not valid: {}",
+                        problems.join("; ")
+                    ))
+                })?;
+                task.validate_limits(&self.limits)?;
+                self.check_due_date(&task)?;
+
+                task.history = before.history.clone();
+                let now = self.timestamp();
+                task.record_changes(&before, &now);
+                task.updated_at = now;
+                collection.tasks[index] = task.clone();
+                self.validate_dependencies(&collection.tasks)?;
+                Ok(Some(task))
+            })
+            .await?;
+        patched.ok_or_else(|| TaskError::NotFound(task_id.to_string()))
+    }
+
+    /// Run a read-modify-write against storage, or with `dry_run` against a loaded copy
+    /// that is thrown away afterwards
+    /// A real change keeps the collection as it was beforehand for [`TaskService::undo`]
+    async fn modify_tasks<T>(
+        &self,
+        dry_run: bool,
+        modify: impl FnOnce(&mut TaskCollection) -> Option<T>,
+    ) -> Result<Option<T>, TaskError> {
+        if dry_run {
+            let mut task_collection = self.storage.load_tasks().await?;
+            return Ok(modify(&mut task_collection));
         }
 
-        Self {
-            total_tasks,
-            status_counts,
-            priority_counts,
+        let mut events = Vec::new();
+        let result = self
+            .storage
+            .modify_tasks(|collection| {
+                let before = collection.clone();
+                let result = modify(collection);
+                if result.is_some() {
+                    if self.publishes_events() {
+                        events = TaskEvent::between(&before, collection, &self.timestamp());
+                    }
+                    *self.undo.lock().unwrap() = Some(before);
+                }
+                result
+            })
+            .await?;
+        self.publish_events(events).await;
+        Ok(result)
+    }
+
+    /// Like `modify_tasks`, but `modify` may fail, in which case nothing is saved and
+    /// its error is returned
+    async fn try_modify_tasks<T>(
+        &self,
+        dry_run: bool,
+        modify: impl FnOnce(&mut TaskCollection) -> Result<Option<T>, TaskError>,
+    ) -> Result<Option<T>, TaskError> {
+        let mut failure = None;
+        let result = self
+            .modify_tasks(dry_run, |collection| {
+                modify(collection).unwrap_or_else(|e| {
+                    failure = Some(e);
+                    None
+                })
+            })
+            .await?;
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(result),
         }
     }
 
-    /// Format statistics as a human-readable string
-    pub fn format_stats(&self) -> String {
-        format!(
-            "## Task Statistics\n\n**Total Tasks:** {}\n\n### By Status:\n{}\n\n### By Priority:\n{}",
-            self.total_tasks,
-            self.status_counts
-                .iter()
-                .map(|(status, count)| format!("- {}: {}", status, count))
-                .collect::<Vec<_>>()
-                .join("\n"),
-            self.priority_counts
+    /// Set one metadata key on a task, keeping its other keys
+    /// Passing JSON `null` removes the key
+    pub async fn set_metadata(
+        &self,
+        task_id: &str,
+        key: &str,
+        value: serde_json::Value,
+    ) -> Result<Task, TaskError> {
+        if key.trim().is_empty() {
+            return Err(TaskError::Validation(
+                "Metadata key must not be empty".to_string(),
+            ));
+        }
+
+        let updated = self
+            .modify_tasks(false, |collection| {
+                let task = collection.tasks.iter_mut().find(|t| t.id == task_id)?;
+                task.set_metadata(key, value);
+                task.updated_at = self.timestamp();
+                Some(task.clone())
+            })
+            .await?;
+        updated.ok_or_else(|| TaskError::NotFound(task_id.to_string()))
+    }
+
+    /// Append a comment to a task's notes history and save
+    pub async fn add_comment(
+        &self,
+        task_id: &str,
+        comment: TaskComment,
+    ) -> Result<Task, TaskError> {
+        if comment.body.trim().is_empty() {
+            return Err(TaskError::Validation(
+                "Comment body must not be empty".to_string(),
+            ));
+        }
+
+        let updated = self
+            .modify_tasks(false, |collection| {
+                let task = collection.tasks.iter_mut().find(|t| t.id == task_id)?;
+                task.updated_at = comment.created_at.clone();
+                task.comments.push(comment);
+                Some(task.clone())
+            })
+            .await?;
+        updated.ok_or_else(|| TaskError::NotFound(task_id.to_string()))
+    }
+
+    /// Whether any of the task's dependencies is not yet completed
+    /// A dependency on an ID that doesn't exist in `tasks` counts as unfinished
+    pub fn is_blocked(&self, task: &Task, tasks: &[Task]) -> bool {
+        task.depends_on.iter().any(|dependency_id| {
+            tasks
                 .iter()
-                .map(|(priority, count)| format!("- {}: {}", priority, count))
-                .collect::<Vec<_>>()
-                .join("\n")
-        )
+                .find(|t| &t.id == dependency_id)
+                .is_none_or(|dependency| dependency.status != TaskStatus::Completed)
+        })
+    }
+
+    /// Find open tasks that can't start yet because a dependency is unfinished
+    pub async fn blocked_tasks(&self) -> Result<Vec<Task>, TaskError> {
+        let task_collection = self.snapshot().await?;
+        Ok(task_collection
+            .tasks
+            .iter()
+            .filter(|task| {
+                !matches!(task.status, TaskStatus::Completed | TaskStatus::Cancelled)
+                    && self.is_blocked(task, &task_collection.tasks)
+            })
+            .cloned()
+            .collect())
+    }
+
+    /// Format search hits as a markdown list, emphasizing the words of `query` wherever they
+    /// appear in each title and description
+    pub fn format_search_hits(&self, query: &str, hits: &[SearchHit]) -> String {
+        if hits.is_empty() {
+            return "No tasks found.".to_string();
+        }
+
+        let terms = words(query);
+        hits.iter()
+            .map(|hit| {
+                let task = &hit.task;
+                let mut entry = format!(
+                    "- {} (`{}`) - score: {}, status: {}, priority: {}",
+                    highlight(&task.title, &terms),
+                    task.id,
+                    hit.score,
+                    task.status,
+                    task.priority
+                );
+                if !task.description.trim().is_empty() {
+                    entry.push_str(&format!(
+                        "\n  {}",
+                        highlight(&task.description, &terms).replace('\n', "\n  ")
+                    ));
+                }
+                entry
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Find tasks matching any word of `query` (case-insensitive), most relevant first
+    ///
+    /// Each query word scores [`TITLE_WORD_SCORE`] when it is a whole word of the title and
+    /// [`TITLE_PARTIAL_SCORE`] when it only appears inside one, and likewise (lower) for the
+    /// description. Ties are broken by priority, highest first, then by file order.
+    pub async fn search(&self, query: &str) -> Result<Vec<SearchHit>, TaskError> {
+        let terms = words(query);
+        if terms.is_empty() {
+            return Err(TaskError::Validation(
+                "query must contain at least one word".to_string(),
+            ));
+        }
+
+        let task_collection = self.snapshot().await?;
+        let mut hits: Vec<SearchHit> = task_collection
+            .tasks
+            .iter()
+            .filter_map(|task| {
+                let score = relevance(task, &terms);
+                (score > 0).then(|| SearchHit {
+                    task: task.clone(),
+                    score,
+                })
+            })
+            .collect();
+        hits.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| b.task.priority.cmp(&a.task.priority))
+        });
+        Ok(hits)
+    }
+
+    /// Other tags found on tasks carrying `tag`, with how many of those tasks carry each,
+    /// most frequent first (ties by name); `tag` itself is left out
+    pub async fn related_tags(&self, tag: &str) -> Result<Vec<(String, usize)>, TaskError> {
+        let tag = normalize_tag(tag)?;
+        let task_collection = self.snapshot().await?;
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for task in task_collection
+            .tasks
+            .iter()
+            .filter(|t| t.tags.contains(&tag))
+        {
+            let others: HashSet<&String> = task.tags.iter().filter(|t| **t != tag).collect();
+            for other in others {
+                *counts.entry(other.clone()).or_default() += 1;
+            }
+        }
+
+        let mut related: Vec<(String, usize)> = counts.into_iter().collect();
+        related.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(related)
+    }
+
+    /// Distinct assignees and tags across all tasks, each sorted by name with the number of
+    /// tasks carrying it; unassigned tasks are left out and a tag repeated on one task counts once
+    pub async fn facets(&self) -> Result<Facets, TaskError> {
+        let task_collection = self.snapshot().await?;
+        let mut assignees: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut tags: BTreeMap<&str, usize> = BTreeMap::new();
+        for task in &task_collection.tasks {
+            if let Some(assignee) = task.assignee.as_deref() {
+                *assignees.entry(assignee).or_default() += 1;
+            }
+            let task_tags: HashSet<&str> = task.tags.iter().map(String::as_str).collect();
+            for tag in task_tags {
+                *tags.entry(tag).or_default() += 1;
+            }
+        }
+
+        let counts = |map: BTreeMap<&str, usize>| {
+            map.into_iter()
+                .map(|(value, count)| FacetCount {
+                    value: value.to_string(),
+                    count,
+                })
+                .collect()
+        };
+        Ok(Facets {
+            assignees: counts(assignees),
+            tags: counts(tags),
+        })
+    }
+
+    /// Open (not completed or cancelled) tasks per assignee with their priority-weighted load,
+    /// heaviest first; unassigned open tasks are counted under [`UNASSIGNED_BUCKET`]
+    pub async fn workload(&self) -> Result<Vec<AssigneeWorkload>, TaskError> {
+        let task_collection = self.snapshot().await?;
+        let mut by_assignee: BTreeMap<&str, AssigneeWorkload> = BTreeMap::new();
+        for task in &task_collection.tasks {
+            if matches!(task.status, TaskStatus::Completed | TaskStatus::Cancelled) {
+                continue;
+            }
+            let assignee = task.assignee.as_deref().unwrap_or(UNASSIGNED_BUCKET);
+            let entry = by_assignee
+                .entry(assignee)
+                .or_insert_with(|| AssigneeWorkload {
+                    assignee: assignee.to_string(),
+                    open_tasks: 0,
+                    weighted_load: 0,
+                });
+            entry.open_tasks += 1;
+            entry.weighted_load += self.priority_weight(&task.priority);
+        }
+
+        let mut workload: Vec<AssigneeWorkload> = by_assignee.into_values().collect();
+        workload.sort_by(|a, b| {
+            b.weighted_load
+                .cmp(&a.weighted_load)
+                .then_with(|| b.open_tasks.cmp(&a.open_tasks))
+        });
+        Ok(workload)
+    }
+
+    /// Pick the open, unblocked task to work on next
+    /// Highest priority weight wins, then the earliest due date (tasks without one come last),
+    /// then the oldest `created_at`
+    pub async fn next_task(&self) -> Result<Option<Task>, TaskError> {
+        let task_collection = self.snapshot().await?;
+        let tasks = &task_collection.tasks;
+        Ok(tasks
+            .iter()
+            .filter(|task| {
+                !matches!(task.status, TaskStatus::Completed | TaskStatus::Cancelled)
+                    && !self.is_blocked(task, tasks)
+            })
+            .min_by(|a, b| {
+                self.priority_weight(&b.priority)
+                    .cmp(&self.priority_weight(&a.priority))
+                    .then_with(|| match (a.due_datetime(), b.due_datetime()) {
+                        (Some(a_due), Some(b_due)) => a_due.cmp(&b_due),
+                        (Some(_), None) => Ordering::Less,
+                        (None, Some(_)) => Ordering::Greater,
+                        (None, None) => Ordering::Equal,
+                    })
+                    .then_with(|| a.created_datetime().cmp(&b.created_datetime()))
+            })
+            .cloned())
+    }
+
+    /// Reject dependency graphs that contain a cycle, naming the tasks involved
+    pub fn validate_dependencies(&self, tasks: &[Task]) -> Result<(), TaskError> {
+        match find_dependency_cycle(tasks) {
+            Some(cycle) => Err(TaskError::Validation(format!(
+                "Dependency cycle: {}",
+                cycle.join(" -> ")
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Reject a due date earlier than the task's creation time in strict mode; otherwise
+    /// only log it, as [`TaskService::snapshot`] does for a loaded task file
+    fn check_due_date(&self, task: &Task) -> Result<(), TaskError> {
+        let Some(problem) = task.due_before_creation() else {
+            return Ok(());
+        };
+        if self.strict_validation {
+            return Err(TaskError::Validation(format!(
+                "Task {}: {}",
+                task.id, problem
+            )));
+        }
+        warn!("Task {}: {}", task.id, problem);
+        Ok(())
+    }
+
+    /// Create a task with a fresh ID, chosen by the configured [`IdStrategy`], and persist it
+    /// Priority and status left unset take the service's configured defaults
+    pub async fn create_task(&self, new_task: &NewTask) -> Result<Task, TaskError> {
+        let mut task = Task::new_pending(
+            new_task.title.trim(),
+            new_task.description.clone(),
+            new_task
+                .priority
+                .clone()
+                .unwrap_or_else(|| self.default_priority.clone()),
+        );
+        task.status = new_task
+            .status
+            .clone()
+            .unwrap_or_else(|| self.default_status.clone());
+        task.created_at = self.timestamp();
+        task.updated_at = task.created_at.clone();
+        task.validate_limits(&self.limits)?;
+
+        let created = self
+            .modify_tasks(false, |collection| {
+                if self.id_strategy != IdStrategy::Uuid {
+                    task.id = self.id_strategy.generate(&task.title, &collection.tasks);
+                }
+                collection.tasks.push(task.clone());
+                Some(task)
+            })
+            .await?;
+        Ok(created.expect("creating a task always saves"))
+    }
+
+    /// Insert a new task or replace the existing task with the same ID
+    /// Returns `true` if an existing task was replaced
+    pub async fn upsert_task(&self, task: &Task) -> Result<bool, TaskError> {
+        let replaced = self
+            .modify_tasks(false, |collection| {
+                match collection.tasks.iter_mut().find(|t| t.id == task.id) {
+                    Some(existing) => {
+                        *existing = task.clone();
+                        Some(true)
+                    }
+                    None => {
+                        collection.tasks.push(task.clone());
+                        Some(false)
+                    }
+                }
+            })
+            .await?;
+        Ok(replaced.unwrap_or(false))
+    }
+
+    /// Delete a task by ID and persist the change
+    /// Returns `false` if no task with the given ID exists
+    /// With `dry_run` only reports whether the task would be deleted
+    pub async fn delete_task(&self, task_id: &str, dry_run: bool) -> Result<bool, TaskError> {
+        let removed = self
+            .modify_tasks(dry_run, |collection| {
+                let original_len = collection.tasks.len();
+                collection.tasks.retain(|t| t.id != task_id);
+                (collection.tasks.len() != original_len).then_some(())
+            })
+            .await?;
+        Ok(removed.is_some())
+    }
+
+    /// IDs shared by more than one task, sorted
+    /// Reads the file directly, so it works even when strict validation would refuse to load it
+    pub async fn find_duplicate_ids(&self) -> Result<Vec<String>, TaskError> {
+        let task_collection = self.storage.load_tasks().await?;
+        Ok(duplicate_ids(&task_collection.tasks))
+    }
+
+    /// Give every task whose ID repeats an earlier task's ID a fresh ID from the configured
+    /// [`IdStrategy`] (a UUID by default) and persist the change; the first task with each
+    /// ID keeps it, so references keep pointing at it
+    /// Returns the `(old, new)` ID pairs in file order
+    pub async fn deduplicate_ids(&self) -> Result<Vec<(String, String)>, TaskError> {
+        let renamed = self
+            .modify_tasks(false, |collection| {
+                let mut seen = HashSet::new();
+                let mut renamed = Vec::new();
+                for index in 0..collection.tasks.len() {
+                    let id = collection.tasks[index].id.clone();
+                    if seen.insert(id.clone()) {
+                        continue;
+                    }
+                    let title = &collection.tasks[index].title;
+                    let new_id = self.id_strategy.generate(title, &collection.tasks);
+                    seen.insert(new_id.clone());
+                    let task = &mut collection.tasks[index];
+                    task.id = new_id.clone();
+                    task.updated_at = self.timestamp();
+                    renamed.push((id, new_id));
+                }
+                (!renamed.is_empty()).then_some(renamed)
+            })
+            .await?;
+        Ok(renamed.unwrap_or_default())
+    }
+
+    /// Move every completed task (and cancelled ones too with `include_cancelled`) out of the
+    /// task file into the archive, returning how many were moved
+    ///
+    /// The archive is written first, so a failure part-way leaves tasks in both files rather
+    /// than in neither. Archived tasks replace any archived task with the same ID.
+    pub async fn archive_completed(&self, include_cancelled: bool) -> Result<usize, TaskError> {
+        let archive = self
+            .archive
+            .as_ref()
+            .ok_or_else(|| TaskError::Validation("No archive file is configured".to_string()))?;
+        let is_finished = |task: &Task| {
+            task.status == TaskStatus::Completed
+                || (include_cancelled && task.status == TaskStatus::Cancelled)
+        };
+
+        let finished: Vec<Task> = self
+            .storage
+            .load_tasks()
+            .await?
+            .tasks
+            .into_iter()
+            .filter(|task| is_finished(task))
+            .collect();
+        if finished.is_empty() {
+            return Ok(0);
+        }
+
+        archive
+            .modify_tasks(|collection| {
+                collection
+                    .tasks
+                    .retain(|archived| !finished.iter().any(|task| task.id == archived.id));
+                collection.tasks.extend(finished.iter().cloned());
+                Some(())
+            })
+            .await?;
+
+        let moved = self
+            .modify_tasks(false, |collection| {
+                let before = collection.tasks.len();
+                collection.tasks.retain(|task| {
+                    !(is_finished(task) && finished.iter().any(|moved| moved.id == task.id))
+                });
+                Some(before - collection.tasks.len())
+            })
+            .await?;
+        Ok(moved.unwrap_or(0))
+    }
+
+    /// Move a task from the archive back into the task file, resetting it to pending unless
+    /// `keep_status` is set, and return the restored task
+    ///
+    /// Fails if the archive has no such task or the task file already has a task with its ID.
+    /// Like archiving, the destination is written first.
+    pub async fn restore_task(&self, task_id: &str, keep_status: bool) -> Result<Task, TaskError> {
+        let archive = self
+            .archive
+            .as_ref()
+            .ok_or_else(|| TaskError::Validation("No archive file is configured".to_string()))?;
+
+        let mut task = archive
+            .load_tasks()
+            .await?
+            .tasks
+            .into_iter()
+            .find(|t| t.id == task_id)
+            .ok_or_else(|| TaskError::NotFound(format!("{} (in archive)", task_id)))?;
+        if !keep_status {
+            task.status = TaskStatus::Pending;
+        }
+        task.updated_at = self.timestamp();
+
+        let inserted = self
+            .modify_tasks(false, |collection| {
+                if collection.tasks.iter().any(|t| t.id == task_id) {
+                    return None;
+                }
+                collection.tasks.push(task.clone());
+                Some(())
+            })
+            .await?;
+        if inserted.is_none() {
+            return Err(TaskError::Validation(format!(
+                "Cannot restore {}: a task with that ID already exists",
+                task_id
+            )));
+        }
+
+        archive.remove_task(task_id).await?;
+        Ok(task)
+    }
+
+    /// Set the status of a task, bump its `updated_at` timestamp and persist the change
+    /// `force` bypasses the transition rules. Returns the previous status
+    pub async fn set_status(
+        &self,
+        task_id: &str,
+        status: TaskStatus,
+        force: bool,
+    ) -> Result<TaskStatus, TaskError> {
+        let previous_status = self
+            .try_modify_tasks(false, |collection| {
+                let Some(task) = collection.tasks.iter_mut().find(|t| t.id == task_id) else {
+                    return Ok(None);
+                };
+                self.check_transition(&task.status, &status, force)?;
+                let before = task.clone();
+                task.status = status;
+                let now = self.timestamp();
+                task.record_changes(&before, &now);
+                task.updated_at = now;
+                Ok(Some(before.status))
+            })
+            .await?;
+        previous_status.ok_or_else(|| TaskError::NotFound(task_id.to_string()))
+    }
+
+    /// Reject a status change the transition rules forbid, when they are enforced and not forced
+    fn check_transition(
+        &self,
+        from: &TaskStatus,
+        to: &TaskStatus,
+        force: bool,
+    ) -> Result<(), TaskError> {
+        if !self.strict_transitions || force || from.can_transition_to(to) {
+            return Ok(());
+        }
+        Err(TaskError::Validation(format!(
+            "Cannot change status from {} to {}; reopen the task as Pending first or pass force to override",
+            from, to
+        )))
+    }
+
+    /// Mark a task completed and, if it recurs, add a pending copy due one interval later
+    ///
+    /// The next due date is counted from the current due date, or from now if the task
+    /// has none; a task completed late skips the occurrences already past, so the copy is
+    /// never due before it was created. The recurrence rule moves to the new task, so
+    /// completing the original again doesn't spawn another copy. Returns the previous
+    /// status and the new task.
+    pub async fn complete_and_reschedule(
+        &self,
+        task_id: &str,
+        force: bool,
+    ) -> Result<(TaskStatus, Option<Task>), TaskError> {
+        let rule = self.find_task_by_id(task_id).await?.recurrence_rule()?;
+
+        let outcome = self
+            .try_modify_tasks(false, |collection| {
+                let now = self.timestamp();
+                let Some(task) = collection.tasks.iter_mut().find(|t| t.id == task_id) else {
+                    return Ok(None);
+                };
+                self.check_transition(&task.status, &TaskStatus::Completed, force)?;
+
+                let before = task.clone();
+                task.status = TaskStatus::Completed;
+                task.updated_at = now.clone();
+                task.record_changes(&before, &now);
+                let previous_status = before.status;
+
+                let next_task = rule.map(|rule| {
+                    let due = task.due_datetime().unwrap_or_else(|| self.now());
+                    let mut next_task = task.clone();
+                    next_task.status = TaskStatus::Pending;
+                    next_task.created_at = now.clone();
+                    next_task.updated_at = now;
+                    next_task.history = Vec::new();
+                    next_task.due_date = next_occurrence(rule, due, self.now())
+                        .map(|d| d.to_rfc3339_opts(SecondsFormat::Secs, true));
+                    task.recurrence = None;
+                    next_task
+                });
+                let next_task = next_task.map(|mut next_task| {
+                    next_task.id = self
+                        .id_strategy
+                        .generate(&next_task.title, &collection.tasks);
+                    next_task
+                });
+                if let Some(next_task) = &next_task {
+                    self.check_due_date(next_task)?;
+                    collection.tasks.push(next_task.clone());
+                }
+
+                Ok(Some((previous_status, next_task)))
+            })
+            .await?;
+        outcome.ok_or_else(|| TaskError::NotFound(task_id.to_string()))
+    }
+
+    /// Assign a task to someone, or unassign it with `None`, returning the previous assignee
+    /// A blank assignee is treated as unassigning rather than stored as an empty name
+    pub async fn set_assignee(
+        &self,
+        task_id: &str,
+        assignee: Option<String>,
+    ) -> Result<Option<String>, TaskError> {
+        let assignee = assignee
+            .map(|a| a.trim().to_string())
+            .filter(|a| !a.is_empty());
+
+        let previous_assignee = self
+            .modify_tasks(false, |collection| {
+                let task = collection.tasks.iter_mut().find(|t| t.id == task_id)?;
+                task.updated_at = self.timestamp();
+                Some(std::mem::replace(&mut task.assignee, assignee))
+            })
+            .await?;
+        previous_assignee.ok_or_else(|| TaskError::NotFound(task_id.to_string()))
+    }
+
+    /// Set a task's due date from an absolute date or a relative expression such as
+    /// `tomorrow` or `in 3 days`, resolved against `now` (see [`parse_due_date`]), and return
+    /// the updated task; a blank expression removes the due date
+    pub async fn set_due_date(
+        &self,
+        task_id: &str,
+        expr: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Task, TaskError> {
+        let due = if expr.trim().is_empty() {
+            None
+        } else {
+            Some(self.timestamp_format.format(parse_due_date(expr, now)?))
+        };
+
+        let updated = self
+            .try_modify_tasks(false, |collection| {
+                let Some(task) = collection.tasks.iter_mut().find(|t| t.id == task_id) else {
+                    return Ok(None);
+                };
+                task.due_date = due;
+                self.check_due_date(task)?;
+                task.updated_at = self.timestamp();
+                Ok(Some(task.clone()))
+            })
+            .await?;
+        updated.ok_or_else(|| TaskError::NotFound(task_id.to_string()))
+    }
+
+    /// Give a task a position in the manual ordering and return the updated task
+    /// If another task already holds `order`, every other task at or after it moves back
+    /// one place, so no two tasks share a position
+    pub async fn reorder_task(&self, task_id: &str, order: i64) -> Result<Task, TaskError> {
+        let updated = self
+            .modify_tasks(false, |collection| {
+                let index = collection.tasks.iter().position(|t| t.id == task_id)?;
+                let now = self.timestamp();
+                let taken = collection
+                    .tasks
+                    .iter()
+                    .enumerate()
+                    .any(|(i, t)| i != index && t.order == Some(order));
+                if taken {
+                    for (i, task) in collection.tasks.iter_mut().enumerate() {
+                        if i != index && task.order.is_some_and(|o| o >= order) {
+                            task.order = task.order.map(|o| o.saturating_add(1));
+                            task.updated_at = now.clone();
+                        }
+                    }
+                }
+
+                let task = &mut collection.tasks[index];
+                task.order = Some(order);
+                task.updated_at = now;
+                Some(task.clone())
+            })
+            .await?;
+        updated.ok_or_else(|| TaskError::NotFound(task_id.to_string()))
+    }
+
+    /// Add a tag to a task, returning the task's tags afterwards
+    /// Adding a tag the task already has changes nothing
+    pub async fn add_tag(&self, task_id: &str, tag: &str) -> Result<Vec<String>, TaskError> {
+        let tag = normalize_tag(tag)?;
+        self.edit_tags(task_id, |tags| add_tag_to(tags, &tag)).await
+    }
+
+    /// Remove a tag from a task, returning the task's tags afterwards
+    /// Removing a tag the task doesn't have changes nothing
+    pub async fn remove_tag(&self, task_id: &str, tag: &str) -> Result<Vec<String>, TaskError> {
+        let tag = normalize_tag(tag)?;
+        self.edit_tags(task_id, |tags| remove_tag_from(tags, &tag))
+            .await
+    }
+
+    /// Add and remove tags on every task matching `filter`, saving once, and return how many
+    /// tasks changed; tags a task already has (or lacks) are skipped, so no duplicates arise
+    /// The same tag can't be both added and removed
+    pub async fn bulk_tag(
+        &self,
+        filter: &TaskFilter,
+        add: &[String],
+        remove: &[String],
+    ) -> Result<usize, TaskError> {
+        let add = add
+            .iter()
+            .map(|tag| normalize_tag(tag))
+            .collect::<Result<Vec<_>, _>>()?;
+        let remove = remove
+            .iter()
+            .map(|tag| normalize_tag(tag))
+            .collect::<Result<Vec<_>, _>>()?;
+        if add.is_empty() && remove.is_empty() {
+            return Err(TaskError::Validation(
+                "Nothing to do: add_tags and remove_tags are both empty".to_string(),
+            ));
+        }
+        if let Some(tag) = add.iter().find(|tag| remove.contains(tag)) {
+            return Err(TaskError::Validation(format!(
+                "Tag {} can't be both added and removed",
+                tag
+            )));
+        }
+
+        let updated = self
+            .try_modify_tasks(false, |collection| {
+                let mut updated = 0;
+                for task in collection.tasks.iter_mut().filter(|t| filter.matches(t)) {
+                    let mut changed = false;
+                    for tag in &remove {
+                        changed |= remove_tag_from(&mut task.tags, tag);
+                    }
+                    for tag in &add {
+                        changed |= add_tag_to(&mut task.tags, tag);
+                    }
+                    if changed {
+                        task.validate_limits(&self.limits)?;
+                        task.updated_at = self.timestamp();
+                        updated += 1;
+                    }
+                }
+                Ok((updated > 0).then_some(updated))
+            })
+            .await?;
+        Ok(updated.unwrap_or(0))
+    }
+
+    /// Rename a tag on every task that has it, returning how many tasks were touched
+    /// A task that already has `to` keeps a single copy of it
+    pub async fn rename_tag(&self, from: &str, to: &str) -> Result<usize, TaskError> {
+        let from = normalize_tag(from)?;
+        let to = normalize_tag(to)?;
+        if from == to {
+            return Ok(0);
+        }
+
+        let touched = self
+            .modify_tasks(false, |collection| {
+                let mut touched = 0;
+                for task in collection
+                    .tasks
+                    .iter_mut()
+                    .filter(|t| t.tags.contains(&from))
+                {
+                    for tag in task.tags.iter_mut().filter(|t| **t == from) {
+                        *tag = to.clone();
+                    }
+                    let mut seen = HashSet::new();
+                    task.tags.retain(|t| seen.insert(t.clone()));
+                    task.updated_at = self.timestamp();
+                    touched += 1;
+                }
+                (touched > 0).then_some(touched)
+            })
+            .await?;
+        Ok(touched.unwrap_or(0))
+    }
+
+    /// Apply `edit` to a task's tags, bumping `updated_at` only if it reports a change
+    async fn edit_tags(
+        &self,
+        task_id: &str,
+        edit: impl FnOnce(&mut Vec<String>) -> bool,
+    ) -> Result<Vec<String>, TaskError> {
+        let tags = self
+            .try_modify_tasks(false, |collection| {
+                let Some(task) = collection.tasks.iter_mut().find(|t| t.id == task_id) else {
+                    return Ok(None);
+                };
+                if edit(&mut task.tags) {
+                    task.validate_limits(&self.limits)?;
+                    task.updated_at = self.timestamp();
+                }
+                Ok(Some(task.tags.clone()))
+            })
+            .await?;
+        tags.ok_or_else(|| TaskError::NotFound(task_id.to_string()))
+    }
+
+    /// Find open tasks whose due date lies before `now`
+    /// Completed and cancelled tasks are never overdue
+    pub async fn overdue_tasks(&self, now: DateTime<Utc>) -> Result<Vec<Task>, TaskError> {
+        let task_collection = self.snapshot().await?;
+        Ok(task_collection
+            .tasks
+            .iter()
+            .filter(|task| task.is_overdue(now))
+            .cloned()
+            .collect())
+    }
+
+    /// Find open tasks due between `now` and `now + days`, soonest first
+    /// Tasks without a due date and completed or cancelled tasks are excluded
+    pub async fn due_within(&self, now: DateTime<Utc>, days: u32) -> Result<Vec<Task>, TaskError> {
+        let until = now + Duration::days(i64::from(days));
+        let task_collection = self.snapshot().await?;
+        let mut due: Vec<Task> = task_collection
+            .tasks
+            .iter()
+            .filter(|task| task.is_due_between(now, until))
+            .cloned()
+            .collect();
+        due.sort_by_key(|task| task.due_datetime());
+        Ok(due)
+    }
+
+    /// Find tasks other than completed ones whose `updated_at` lies more than `days` days
+    /// before `now`, least recently updated first
+    /// Tasks whose `updated_at` can't be parsed are left out
+    pub async fn stale(&self, now: DateTime<Utc>, days: u32) -> Result<Vec<Task>, TaskError> {
+        let cutoff = now - Duration::days(i64::from(days));
+        let task_collection = self.snapshot().await?;
+        let mut stale: Vec<(DateTime<Utc>, Task)> = task_collection
+            .tasks
+            .iter()
+            .filter(|task| task.status != TaskStatus::Completed)
+            .filter_map(|task| {
+                let updated = task.updated_datetime()?;
+                (updated < cutoff).then(|| (updated, task.clone()))
+            })
+            .collect();
+        stale.sort_by_key(|(updated, _)| *updated);
+        Ok(stale.into_iter().map(|(_, task)| task).collect())
+    }
+
+    /// Find tasks, whatever their status, whose due date falls on the UTC calendar day `date`,
+    /// soonest first; date-only due dates count as due on that day and sort before timed ones
+    pub async fn due_on(&self, date: NaiveDate) -> Result<Vec<Task>, TaskError> {
+        let task_collection = self.snapshot().await?;
+        let mut due: Vec<Task> = task_collection
+            .tasks
+            .iter()
+            .filter(|task| task.due_day() == Some(date))
+            .cloned()
+            .collect();
+        due.sort_by_key(|task| task.due_datetime());
+        Ok(due)
+    }
+
+    /// Gather a standup summary as of `now`: overdue tasks, open tasks still due on `now`'s
+    /// UTC day (both soonest first), in-progress tasks (highest priority first) and the
+    /// number of open tasks per priority
+    pub async fn daily_digest(&self, now: DateTime<Utc>) -> Result<DailyDigest, TaskError> {
+        let end_of_day = now.date_naive().and_time(NaiveTime::MIN).and_utc() + Duration::days(1)
+            - Duration::nanoseconds(1);
+        let task_collection = self.snapshot().await?;
+
+        let mut overdue = Vec::new();
+        let mut due_today = Vec::new();
+        let mut in_progress = Vec::new();
+        let mut open_by_priority: BTreeMap<Priority, usize> =
+            Priority::ALL.iter().map(|p| (p.clone(), 0)).collect();
+        for task in &task_collection.tasks {
+            if matches!(task.status, TaskStatus::Pending | TaskStatus::InProgress) {
+                *open_by_priority.entry(task.priority.clone()).or_default() += 1;
+            }
+            if task.status == TaskStatus::InProgress {
+                in_progress.push(task.clone());
+            }
+            if task.is_overdue(now) {
+                overdue.push(task.clone());
+            } else if task.is_due_between(now, end_of_day) {
+                due_today.push(task.clone());
+            }
+        }
+        overdue.sort_by_key(|task| task.due_datetime());
+        due_today.sort_by_key(|task| task.due_datetime());
+        in_progress.sort_by(|a, b| b.priority.cmp(&a.priority));
+
+        Ok(DailyDigest {
+            date: now.format("%Y-%m-%d").to_string(),
+            overdue,
+            due_today,
+            in_progress,
+            open_by_priority,
+        })
+    }
+
+    /// Render every task as CSV with a header row
+    /// Tags and dependencies are joined with `;`, and missing optional fields are empty cells
+    pub async fn export_csv(&self) -> Result<String, TaskError> {
+        let task_collection = self.snapshot().await?;
+        let mut writer = csv::Writer::from_writer(Vec::new());
+
+        writer.write_record(CSV_HEADER)?;
+        for task in &task_collection.tasks {
+            writer.write_record([
+                task.id.as_str(),
+                task.title.as_str(),
+                task.description.as_str(),
+                &task.status.to_string(),
+                &task.priority.to_string(),
+                task.assignee.as_deref().unwrap_or_default(),
+                task.due_date.as_deref().unwrap_or_default(),
+                &task.tags.join(";"),
+                task.parent_id.as_deref().unwrap_or_default(),
+                &task.depends_on.join(";"),
+                task.created_at.as_str(),
+                task.updated_at.as_str(),
+            ])?;
+        }
+
+        let bytes = writer
+            .into_inner()
+            .map_err(|e| csv::Error::from(e.into_error()))?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Rewrite the task file in its canonical layout, returning its size in bytes before and after
+    pub async fn compact_storage(&self) -> Result<(usize, usize), TaskError> {
+        Ok(self.storage.compact().await?)
+    }
+
+    /// Stream every task to `writer` as newline-delimited JSON, one compact task per line,
+    /// returning the number of tasks written
+    ///
+    /// Each line is serialized and written on its own, so the formatted export is never held
+    /// in memory as a whole. The writer is flushed but not shut down.
+    pub async fn export_ndjson<W>(&self, writer: &mut W) -> Result<usize, TaskError>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let task_collection = self.snapshot().await?;
+        let mut line = Vec::new();
+        for task in &task_collection.tasks {
+            line.clear();
+            serde_json::to_writer(&mut line, task)?;
+            line.push(b'\n');
+            writer.write_all(&line).await?;
+        }
+        writer.flush().await?;
+        Ok(task_collection.tasks.len())
+    }
+
+    /// Report where the task file is, whether it exists, when it last changed, and what it holds
+    pub async fn health(&self) -> Result<HealthReport, TaskError> {
+        let file_exists = self.storage.file_exists();
+        let last_modified = self.storage.modified_at().await?.map(DateTime::<Utc>::from);
+        let task_collection = self.snapshot().await?;
+
+        Ok(HealthReport {
+            file_path: self.storage.file_path().to_path_buf(),
+            file_exists,
+            task_count: task_collection.tasks.len(),
+            last_modified,
+            schema_version: task_collection.version.clone(),
+        })
+    }
+
+    /// Get task statistics
+    pub async fn get_task_statistics(&self) -> Result<TaskStatistics, TaskError> {
+        let task_collection = self.snapshot().await?;
+        let stats = TaskStatistics::from_tasks_at(&task_collection.tasks, self.now());
+        Ok(stats)
+    }
+}
+
+/// Typed criteria for filtering tasks; every set field must match
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TaskFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<TaskStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assignee: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Tags a task must all carry; empty means no restriction
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub all_tags: Vec<String>,
+    /// Inclusive lower bound on `created_at`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_after: Option<DateTime<Utc>>,
+    /// Inclusive upper bound on `created_at`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_before: Option<DateTime<Utc>>,
+    /// Inclusive lower bound on `updated_at`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_after: Option<DateTime<Utc>>,
+    /// Inclusive upper bound on `updated_at`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_before: Option<DateTime<Utc>>,
+    /// Compare `assignee` ignoring case and surrounding whitespace, and match `tag` and each
+    /// of `all_tags` as a case-insensitive substring of any tag
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub fuzzy: bool,
+    /// Only match tasks without an assignee; can't be combined with `assignee`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub unassigned: bool,
+    /// `Some(true)` keeps only tasks with a due date, `Some(false)` only tasks without one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_due_date: Option<bool>,
+    /// Whether a task must meet every criterion above or just one
+    #[serde(rename = "match", skip_serializing_if = "MatchMode::is_all")]
+    pub match_mode: MatchMode,
+}
+
+/// How [`TaskFilter`] combines its criteria
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    /// Every set criterion must match
+    #[default]
+    All,
+    /// At least one set criterion must match
+    Any,
+}
+
+impl MatchMode {
+    fn is_all(&self) -> bool {
+        *self == MatchMode::All
+    }
+}
+
+impl FromStr for MatchMode {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "all" => Ok(MatchMode::All),
+            "any" => Ok(MatchMode::Any),
+            other => Err(TaskError::Validation(format!(
+                "Invalid match mode: {} (expected all or any)",
+                other
+            ))),
+        }
+    }
+}
+
+impl TaskFilter {
+    /// Argument names recognized as filters
+    pub const KEYS: [&str; 13] = [
+        "status",
+        "priority",
+        "assignee",
+        "tag",
+        "all_tags",
+        "created_after",
+        "created_before",
+        "updated_after",
+        "updated_before",
+        "fuzzy",
+        "unassigned",
+        "has_due_date",
+        "match",
+    ];
+
+    /// Build a filter from raw MCP tool arguments
+    /// Keys that are not filters (e.g. pagination options) are ignored
+    pub fn from_arguments(
+        arguments: &serde_json::Map<String, serde_json::Value>,
+    ) -> Result<Self, TaskError> {
+        let status = string_argument(arguments, "status")?
+            .map(str::parse::<TaskStatus>)
+            .transpose()?;
+        let priority = string_argument(arguments, "priority")?
+            .map(str::parse::<Priority>)
+            .transpose()?;
+        let assignee = string_argument(arguments, "assignee")?.map(str::to_string);
+        let unassigned = bool_argument(arguments, "unassigned")?;
+        if unassigned && assignee.is_some() {
+            return Err(TaskError::Validation(
+                "assignee and unassigned cannot be combined".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            status,
+            priority,
+            assignee,
+            tag: string_argument(arguments, "tag")?.map(str::to_string),
+            all_tags: string_array_argument(arguments, "all_tags")?.unwrap_or_default(),
+            created_after: timestamp_argument(arguments, "created_after")?,
+            created_before: timestamp_argument(arguments, "created_before")?,
+            updated_after: timestamp_argument(arguments, "updated_after")?,
+            updated_before: timestamp_argument(arguments, "updated_before")?,
+            fuzzy: bool_argument(arguments, "fuzzy")?,
+            unassigned,
+            has_due_date: optional_bool_argument(arguments, "has_due_date")?,
+            match_mode: string_argument(arguments, "match")?
+                .map(str::parse::<MatchMode>)
+                .transpose()?
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Check whether a task satisfies the filter: every set criterion with
+    /// [`MatchMode::All`], or at least one of them with [`MatchMode::Any`]
+    /// A filter without criteria matches every task in either mode
+    pub fn matches(&self, task: &Task) -> bool {
+        let has_created_range = self.created_after.is_some() || self.created_before.is_some();
+        let has_updated_range = self.updated_after.is_some() || self.updated_before.is_some();
+        let criteria = [
+            self.status.as_ref().map(|status| task.status == *status),
+            self.priority
+                .as_ref()
+                .map(|priority| task.priority == *priority),
+            self.assignee
+                .as_ref()
+                .map(|assignee| self.assignee_matches(assignee, task)),
+            self.unassigned.then(|| task.assignee.is_none()),
+            self.has_due_date
+                .map(|wanted| task.due_date.is_some() == wanted),
+            self.tag.as_ref().map(|tag| self.tag_matches(tag, task)),
+            (!self.all_tags.is_empty())
+                .then(|| self.all_tags.iter().all(|tag| self.tag_matches(tag, task))),
+            has_created_range.then(|| {
+                within_range(
+                    task.created_datetime(),
+                    self.created_after,
+                    self.created_before,
+                )
+            }),
+            has_updated_range.then(|| {
+                within_range(
+                    task.updated_datetime(),
+                    self.updated_after,
+                    self.updated_before,
+                )
+            }),
+        ];
+
+        let mut set = criteria.into_iter().flatten().peekable();
+        match self.match_mode {
+            MatchMode::All => set.all(|matched| matched),
+            MatchMode::Any => set.peek().is_none() || set.any(|matched| matched),
+        }
+    }
+
+    /// Whether the task's assignee matches, honouring `fuzzy`
+    fn assignee_matches(&self, assignee: &str, task: &Task) -> bool {
+        match &task.assignee {
+            None => false,
+            Some(actual) if self.fuzzy => {
+                actual.trim().to_lowercase() == assignee.trim().to_lowercase()
+            }
+            Some(actual) => actual == assignee,
+        }
+    }
+
+    /// Whether any of the task's tags matches, honouring `fuzzy`
+    fn tag_matches(&self, tag: &str, task: &Task) -> bool {
+        if !self.fuzzy {
+            return task.tags.iter().any(|t| t == tag);
+        }
+        let needle = tag.trim().to_lowercase();
+        task.tags.iter().any(|t| t.to_lowercase().contains(&needle))
+    }
+}
+
+/// Fields of a task to create; unset priority and status fall back to the service defaults
+#[derive(Debug, Clone, Default)]
+pub struct NewTask {
+    pub title: String,
+    pub description: String,
+    pub priority: Option<Priority>,
+    pub status: Option<TaskStatus>,
+}
+
+impl NewTask {
+    /// Build a new task from raw MCP tool arguments; `title` is required and must not be blank
+    pub fn from_arguments(
+        arguments: &serde_json::Map<String, serde_json::Value>,
+    ) -> Result<Self, TaskError> {
+        let title = string_argument(arguments, "title")?
+            .filter(|title| !title.trim().is_empty())
+            .ok_or_else(|| TaskError::Validation("title is required".to_string()))?;
+        let status = string_argument(arguments, "status")?
+            .map(str::parse::<TaskStatus>)
+            .transpose()?;
+        let priority = string_argument(arguments, "priority")?
+            .map(str::parse::<Priority>)
+            .transpose()?;
+
+        Ok(Self {
+            title: title.to_string(),
+            description: string_argument(arguments, "description")?
+                .unwrap_or_default()
+                .to_string(),
+            priority,
+            status,
+        })
+    }
+}
+
+/// Partial update to a task; only the fields that are set get changed
+#[derive(Debug, Clone, Default)]
+pub struct TaskUpdate {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub status: Option<TaskStatus>,
+    pub priority: Option<Priority>,
+    pub assignee: Option<String>,
+    /// `Some(None)` removes the due date
+    pub due_date: Option<Option<String>>,
+    pub tags: Option<Vec<String>>,
+    /// Metadata keys to set; other keys on the task are kept, and `null` removes a key
+    pub metadata: HashMap<String, serde_json::Value>,
+    /// Bypass the status transition rules; not a change in itself
+    pub force: bool,
+}
+
+impl TaskUpdate {
+    /// Build an update from raw MCP tool arguments
+    /// The `id` key names the task and is not part of the update
+    pub fn from_arguments(
+        arguments: &serde_json::Map<String, serde_json::Value>,
+    ) -> Result<Self, TaskError> {
+        let status = string_argument(arguments, "status")?
+            .map(str::parse::<TaskStatus>)
+            .transpose()?;
+        let priority = string_argument(arguments, "priority")?
+            .map(str::parse::<Priority>)
+            .transpose()?;
+
+        Ok(Self {
+            title: string_argument(arguments, "title")?.map(str::to_string),
+            description: string_argument(arguments, "description")?.map(str::to_string),
+            status,
+            priority,
+            assignee: string_argument(arguments, "assignee")?.map(str::to_string),
+            due_date: clearable_string_argument(arguments, "due_date")?,
+            tags: string_array_argument(arguments, "tags")?,
+            metadata: object_argument(arguments, "metadata")?,
+            force: bool_argument(arguments, "force")?,
+        })
+    }
+
+    /// Whether the update would change nothing
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.description.is_none()
+            && self.status.is_none()
+            && self.priority.is_none()
+            && self.assignee.is_none()
+            && self.due_date.is_none()
+            && self.tags.is_none()
+            && self.metadata.is_empty()
+    }
+
+    /// Reject empty updates, blank titles and unparseable due dates
+    fn validate(&self) -> Result<(), TaskError> {
+        if self.is_empty() {
+            return Err(TaskError::Validation("No fields to update".to_string()));
+        }
+        if self.title.as_deref().is_some_and(|t| t.trim().is_empty()) {
+            return Err(TaskError::Validation("title must not be empty".to_string()));
+        }
+        if let Some(Some(due_date)) = &self.due_date
+            && parse_timestamp(due_date).is_none()
+        {
+            return Err(TaskError::Validation(format!(
+                "Invalid due_date: {} (expected an RFC 3339 timestamp)",
+                due_date
+            )));
+        }
+        Ok(())
+    }
+
+    fn apply(&self, task: &mut Task) {
+        if let Some(title) = &self.title {
+            task.title = title.clone();
+        }
+        if let Some(description) = &self.description {
+            task.description = description.clone();
+        }
+        if let Some(status) = &self.status {
+            task.status = status.clone();
+        }
+        if let Some(priority) = &self.priority {
+            task.priority = priority.clone();
+        }
+        if let Some(assignee) = &self.assignee {
+            task.assignee = Some(assignee.clone());
+        }
+        if let Some(due_date) = &self.due_date {
+            task.due_date = due_date.clone();
+        }
+        if let Some(tags) = &self.tags {
+            task.tags = tags.clone();
+        }
+        for (key, value) in &self.metadata {
+            task.set_metadata(key, value.clone());
+        }
+    }
+}
+
+/// How imported tasks combine with the existing collection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Upsert by ID, keeping tasks that are not in the import
+    Merge,
+    /// Overwrite the whole collection with the imported tasks
+    Replace,
+}
+
+impl FromStr for ImportMode {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "merge" => Ok(ImportMode::Merge),
+            "replace" => Ok(ImportMode::Replace),
+            other => Err(TaskError::Validation(format!(
+                "Invalid mode: {} (expected merge or replace)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Outcome of an import
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ImportSummary {
+    pub created: usize,
+    pub updated: usize,
+    /// Number of tasks in the collection after the import
+    pub total: usize,
+}
+
+/// Columns every imported CSV must have
+const CSV_REQUIRED_COLUMNS: [&str; 4] = ["id", "title", "status", "priority"];
+
+/// First occurrence of `rule` after `due` that isn't before `now`
+fn next_occurrence(
+    rule: Recurrence,
+    due: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let mut next = rule.next_after(due)?;
+    while next < now {
+        next = rule.next_after(next)?;
+    }
+    Some(next)
+}
+
+/// Parse CSV rows into tasks, collecting every row problem instead of stopping at the first
+fn parse_csv_tasks(data: &str, now: &str, limits: &TaskLimits) -> Result<Vec<Task>, TaskError> {
+    let invalid_csv = |e: csv::Error| TaskError::Validation(format!("Invalid CSV: {}", e));
+
+    let mut reader = csv::Reader::from_reader(data.as_bytes());
+    let headers = reader.headers().map_err(invalid_csv)?.clone();
+    let missing_columns: Vec<&str> = CSV_REQUIRED_COLUMNS
+        .into_iter()
+        .filter(|column| !headers.iter().any(|h| h == *column))
+        .collect();
+    if !missing_columns.is_empty() {
+        return Err(TaskError::Validation(format!(
+            "CSV is missing required columns: {}",
+            missing_columns.join(", ")
+        )));
+    }
+
+    let mut tasks: Vec<Task> = Vec::new();
+    let mut problems = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(invalid_csv)?;
+        let line = record.position().map_or(0, |p| p.line());
+        let field = |name: &str| {
+            headers
+                .iter()
+                .position(|h| h == name)
+                .and_then(|i| record.get(i))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+
+        let mut row_problems = Vec::new();
+        for column in CSV_REQUIRED_COLUMNS {
+            if field(column).is_none() {
+                row_problems.push(format!("missing {}", column));
+            }
+        }
+        let status = field("status").map(parse_csv_label::<TaskStatus>);
+        let priority = field("priority").map(parse_csv_label::<Priority>);
+        if let Some(Err(e)) = &status {
+            row_problems.push(e.to_string());
+        }
+        if let Some(Err(e)) = &priority {
+            row_problems.push(e.to_string());
+        }
+
+        let (Some(id), Some(title), Some(Ok(status)), Some(Ok(priority))) =
+            (field("id"), field("title"), status, priority)
+        else {
+            problems.extend(
+                row_problems
+                    .into_iter()
+                    .map(|p| format!("Row {}: {}", line, p)),
+            );
+            continue;
+        };
+
+        let task = Task {
+            id: id.to_string(),
+            title: title.to_string(),
+            description: field("description").unwrap_or_default().to_string(),
+            status,
+            priority,
+            created_at: field("created_at").unwrap_or(now).to_string(),
+            updated_at: field("updated_at").unwrap_or(now).to_string(),
+            tags: split_csv_list(field("tags")),
+            assignee: field("assignee").map(str::to_string),
+            due_date: field("due_date").map(str::to_string),
+            parent_id: field("parent_id").map(str::to_string),
+            depends_on: split_csv_list(field("depends_on")),
+            recurrence: None,
+            comments: vec![],
+            metadata: HashMap::new(),
+            history: vec![],
+            order: None,
+        };
+        if tasks.iter().any(|t| t.id == task.id) {
+            problems.push(format!("Row {}: duplicate id {}", line, task.id));
+        }
+        if let Err(e) = task.validate_limits(limits) {
+            problems.push(format!("Row {}: {}", line, e));
+        }
+        if let Err(task_problems) = task.validate() {
+            problems.extend(
+                task_problems
+                    .into_iter()
+                    .map(|p| format!("Row {}: {}", line, p)),
+            );
+        }
+        tasks.push(task);
+    }
+
+    if problems.is_empty() {
+        Ok(tasks)
+    } else {
+        Err(TaskError::Validation(format!(
+            "Invalid CSV rows: {}",
+            problems.join("; ")
+        )))
+    }
+}
+
+/// Parse a status or priority given either as its wire name (`in_progress`)
+/// or as the label the CSV export writes (`In Progress`)
+fn parse_csv_label<T: FromStr>(value: &str) -> Result<T, T::Err> {
+    value.to_lowercase().replace(' ', "_").parse()
+}
+
+/// Split a `;`-joined CSV cell into its entries
+fn split_csv_list(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Overwrite the fields a CSV row carries, keeping comments, metadata and recurrence
+fn merge_csv_row(existing: &mut Task, row: Task) {
+    existing.title = row.title;
+    existing.description = row.description;
+    existing.status = row.status;
+    existing.priority = row.priority;
+    existing.updated_at = row.updated_at;
+    existing.tags = row.tags;
+    existing.assignee = row.assignee;
+    existing.due_date = row.due_date;
+    existing.parent_id = row.parent_id;
+    existing.depends_on = row.depends_on;
+}
+
+/// UTC date-time format used for iCalendar DUE and DTSTAMP values
+const ICAL_DATETIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Escape text for an iCalendar property value (RFC 5545 section 3.3.11)
+fn ical_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Map a priority onto the iCalendar 1 (highest) to 9 (lowest) scale
+fn ical_priority(priority: &Priority) -> u8 {
+    match priority {
+        Priority::Critical => 1,
+        Priority::High => 3,
+        Priority::Medium => 5,
+        Priority::Low => 9,
+    }
+}
+
+/// Map a status onto the VTODO STATUS values
+fn ical_status(status: &TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::Pending => "NEEDS-ACTION",
+        TaskStatus::InProgress => "IN-PROCESS",
+        TaskStatus::Completed => "COMPLETED",
+        TaskStatus::Cancelled => "CANCELLED",
+    }
+}
+
+/// Fold a content line so no physical line exceeds 75 octets
+fn fold_ical_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded
+}
+
+/// Field used to bucket tasks into groups
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Status,
+    Priority,
+    Assignee,
+    Tag,
+}
+
+impl FromStr for GroupBy {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "status" => Ok(GroupBy::Status),
+            "priority" => Ok(GroupBy::Priority),
+            "assignee" => Ok(GroupBy::Assignee),
+            "tag" => Ok(GroupBy::Tag),
+            other => Err(TaskError::Validation(format!(
+                "Invalid group_by: {} (expected status, priority, assignee or tag)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Field used to sort a task list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    /// Manual position set with `reorder_task`
+    Order,
+}
+
+impl FromStr for SortBy {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "order" => Ok(SortBy::Order),
+            other => Err(TaskError::Validation(format!(
+                "Invalid sort_by: {} (expected order)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Bucket tasks by key, keeping each bucket in file order
+fn group_tasks<K: Ord>(tasks: Vec<Task>, key: impl Fn(&Task) -> K) -> BTreeMap<K, Vec<Task>> {
+    let mut groups: BTreeMap<K, Vec<Task>> = BTreeMap::new();
+    for task in tasks {
+        groups.entry(key(&task)).or_default().push(task);
+    }
+    groups
+}
+
+/// Bucket tasks under each of their tags, with untagged tasks under [`UNTAGGED_BUCKET`]
+fn group_tasks_by_tag(tasks: Vec<Task>) -> BTreeMap<String, Vec<Task>> {
+    let mut groups: BTreeMap<String, Vec<Task>> = BTreeMap::new();
+    for task in tasks {
+        let tags: BTreeSet<String> = if task.tags.is_empty() {
+            BTreeSet::from([UNTAGGED_BUCKET.to_string()])
+        } else {
+            task.tags.iter().cloned().collect()
+        };
+        for tag in tags {
+            groups.entry(tag).or_default().push(task.clone());
+        }
+    }
+    groups
+}
+
+/// Render tasks as a Markdown table, one row per task
+fn markdown_table(tasks: &[Task]) -> String {
+    let mut table = String::from(
+        "| ID | Title | Status | Priority | Assignee | Due Date | Tags |\n\
+         |----|-------|--------|----------|----------|----------|------|",
+    );
+    for task in tasks {
+        table.push_str(&format!(
+            "\n| {} | {} | {} | {} | {} | {} | {} |",
+            markdown_cell(&task.id),
+            markdown_cell(&task.title),
+            task.status,
+            task.priority,
+            markdown_cell(task.assignee.as_deref().unwrap_or_default()),
+            markdown_cell(task.due_date.as_deref().unwrap_or_default()),
+            markdown_cell(&task.tags.join(", ")),
+        ));
+    }
+    table
+}
+
+/// Escape a value so it stays inside a single table cell
+fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+/// Check a timestamp against inclusive bounds
+/// With any bound set, a timestamp that failed to parse never matches
+fn within_range(
+    value: Option<DateTime<Utc>>,
+    after: Option<DateTime<Utc>>,
+    before: Option<DateTime<Utc>>,
+) -> bool {
+    if after.is_none() && before.is_none() {
+        return true;
+    }
+    value.is_some_and(|value| {
+        after.is_none_or(|after| value >= after) && before.is_none_or(|before| value <= before)
+    })
+}
+
+/// Read an optional RFC 3339 timestamp argument
+fn timestamp_argument(
+    arguments: &serde_json::Map<String, serde_json::Value>,
+    key: &str,
+) -> Result<Option<DateTime<Utc>>, TaskError> {
+    string_argument(arguments, key)?
+        .map(|value| {
+            parse_timestamp(value).ok_or_else(|| {
+                TaskError::Validation(format!(
+                    "Invalid {}: {} (expected an RFC 3339 timestamp)",
+                    key, value
+                ))
+            })
+        })
+        .transpose()
+}
+
+/// Trim a tag, rejecting blank ones
+fn normalize_tag(tag: &str) -> Result<String, TaskError> {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        return Err(TaskError::Validation("tag must not be empty".to_string()));
+    }
+    Ok(tag.to_string())
+}
+
+/// Append `tag` unless the task already has it, reporting whether the tags changed
+fn add_tag_to(tags: &mut Vec<String>, tag: &str) -> bool {
+    if tags.iter().any(|t| t == tag) {
+        false
+    } else {
+        tags.push(tag.to_string());
+        true
+    }
+}
+
+/// Remove every copy of `tag`, reporting whether the tags changed
+fn remove_tag_from(tags: &mut Vec<String>, tag: &str) -> bool {
+    let original_len = tags.len();
+    tags.retain(|t| t != tag);
+    tags.len() != original_len
+}
+
+/// Read an optional string argument, rejecting values of the wrong type
+fn string_argument<'a>(
+    arguments: &'a serde_json::Map<String, serde_json::Value>,
+    key: &str,
+) -> Result<Option<&'a str>, TaskError> {
+    match arguments.get(key) {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(serde_json::Value::String(value)) => Ok(Some(value)),
+        Some(_) => Err(TaskError::Validation(format!("{} must be a string", key))),
+    }
+}
+
+/// Read an optional string argument that can also be cleared: a missing key gives `None`,
+/// while `null` or a blank string gives `Some(None)`
+fn clearable_string_argument(
+    arguments: &serde_json::Map<String, serde_json::Value>,
+    key: &str,
+) -> Result<Option<Option<String>>, TaskError> {
+    match arguments.get(key) {
+        None => Ok(None),
+        Some(serde_json::Value::Null) => Ok(Some(None)),
+        Some(serde_json::Value::String(value)) if value.trim().is_empty() => Ok(Some(None)),
+        Some(serde_json::Value::String(value)) => Ok(Some(Some(value.clone()))),
+        Some(_) => Err(TaskError::Validation(format!(
+            "{} must be a string or null",
+            key
+        ))),
+    }
+}
+
+/// Read an optional boolean argument, defaulting to `false` and rejecting values of the wrong type
+fn bool_argument(
+    arguments: &serde_json::Map<String, serde_json::Value>,
+    key: &str,
+) -> Result<bool, TaskError> {
+    match arguments.get(key) {
+        None | Some(serde_json::Value::Null) => Ok(false),
+        Some(serde_json::Value::Bool(value)) => Ok(*value),
+        Some(_) => Err(TaskError::Validation(format!("{} must be a boolean", key))),
+    }
+}
+
+/// Read a boolean argument where leaving it out (or passing `null`) means "don't care"
+fn optional_bool_argument(
+    arguments: &serde_json::Map<String, serde_json::Value>,
+    key: &str,
+) -> Result<Option<bool>, TaskError> {
+    match arguments.get(key) {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(serde_json::Value::Bool(value)) => Ok(Some(*value)),
+        Some(_) => Err(TaskError::Validation(format!("{} must be a boolean", key))),
+    }
+}
+
+/// Read an optional array-of-strings argument, rejecting values of the wrong type
+fn string_array_argument(
+    arguments: &serde_json::Map<String, serde_json::Value>,
+    key: &str,
+) -> Result<Option<Vec<String>>, TaskError> {
+    let values = match arguments.get(key) {
+        None | Some(serde_json::Value::Null) => return Ok(None),
+        Some(serde_json::Value::Array(values)) => values,
+        Some(_) => {
+            return Err(TaskError::Validation(format!(
+                "{} must be an array of strings",
+                key
+            )));
+        }
+    };
+
+    values
+        .iter()
+        .map(|value| {
+            value.as_str().map(str::to_string).ok_or_else(|| {
+                TaskError::Validation(format!("{} must be an array of strings", key))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+/// Read an optional object argument as a map, rejecting values of the wrong type
+fn object_argument(
+    arguments: &serde_json::Map<String, serde_json::Value>,
+    key: &str,
+) -> Result<HashMap<String, serde_json::Value>, TaskError> {
+    match arguments.get(key) {
+        None | Some(serde_json::Value::Null) => Ok(HashMap::new()),
+        Some(serde_json::Value::Object(map)) => {
+            Ok(map.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+        }
+        Some(_) => Err(TaskError::Validation(format!("{} must be an object", key))),
+    }
+}
+
+/// A page of tasks taken from a larger result set
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskPage {
+    pub tasks: Vec<Task>,
+    pub total: usize,
+    pub offset: usize,
+}
+
+impl TaskPage {
+    /// Describe which slice of the result set this page covers, e.g. "Showing 11-20 of 57 tasks"
+    pub fn summary(&self) -> String {
+        if self.tasks.is_empty() {
+            format!("Showing 0 of {} tasks", self.total)
+        } else {
+            format!(
+                "Showing {}-{} of {} tasks",
+                self.offset + 1,
+                self.offset + self.tasks.len(),
+                self.total
+            )
+        }
+    }
+}
+
+/// Column order of the CSV export
+const CSV_HEADER: [&str; 12] = [
+    "id",
+    "title",
+    "description",
+    "status",
+    "priority",
+    "assignee",
+    "due_date",
+    "tags",
+    "parent_id",
+    "depends_on",
+    "created_at",
+    "updated_at",
+];
+
+/// Open task count and priority-weighted load of one assignee
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AssigneeWorkload {
+    pub assignee: String,
+    pub open_tasks: usize,
+    /// Sum of the open tasks' priority weights
+    pub weighted_load: u32,
+}
+
+/// A distinct field value and how many tasks carry it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: usize,
+}
+
+/// Assignee and tag vocabulary returned by [`TaskService::facets`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Facets {
+    pub assignees: Vec<FacetCount>,
+    pub tags: Vec<FacetCount>,
+}
+
+/// Standup summary returned by [`TaskService::daily_digest`]
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyDigest {
+    /// The UTC day the digest covers, as `YYYY-MM-DD`
+    pub date: String,
+    pub overdue: Vec<Task>,
+    /// Open tasks due later on the same day
+    pub due_today: Vec<Task>,
+    pub in_progress: Vec<Task>,
+    /// Pending and in-progress task counts per priority, every priority included
+    pub open_by_priority: BTreeMap<Priority, usize>,
+}
+
+/// A task matched by [`TaskService::search`] with its relevance score
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub task: Task,
+    pub score: u32,
+}
+
+/// Score for a query word that is a whole word of the title
+pub const TITLE_WORD_SCORE: u32 = 10;
+/// Score for a query word found only inside a longer word of the title
+pub const TITLE_PARTIAL_SCORE: u32 = 4;
+/// Score for a query word that is a whole word of the description
+pub const DESCRIPTION_WORD_SCORE: u32 = 3;
+/// Score for a query word found only inside a longer word of the description
+pub const DESCRIPTION_PARTIAL_SCORE: u32 = 1;
+
+/// Lowercased alphanumeric words of a text
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Wrap every case-insensitive occurrence of a term in `text` with `**` emphasis
+/// Terms are matched as literal text, and overlapping or adjacent matches share one span
+fn highlight(text: &str, terms: &[String]) -> String {
+    // Lowercase `text`, remembering which original character each lowercase byte came from
+    let mut lowered = String::new();
+    let mut origins = Vec::new();
+    for (start, ch) in text.char_indices() {
+        for lower in ch.to_lowercase() {
+            lowered.push(lower);
+            origins.extend(std::iter::repeat_n(
+                (start, start + ch.len_utf8()),
+                lower.len_utf8(),
+            ));
+        }
+    }
+
+    let mut spans: Vec<(usize, usize)> = terms
+        .iter()
+        .filter(|term| !term.is_empty())
+        .flat_map(|term| {
+            lowered
+                .match_indices(term.as_str())
+                .map(|(at, matched)| (origins[at].0, origins[at + matched.len() - 1].1))
+        })
+        .collect();
+    spans.sort_unstable();
+
+    let mut highlighted = String::with_capacity(text.len());
+    let mut written = 0;
+    let mut spans = spans.into_iter().peekable();
+    while let Some((start, mut end)) = spans.next() {
+        while let Some(&(next_start, next_end)) = spans.peek() {
+            if next_start > end {
+                break;
+            }
+            end = end.max(next_end);
+            spans.next();
+        }
+        highlighted.push_str(&text[written..start]);
+        highlighted.push_str("**");
+        highlighted.push_str(&text[start..end]);
+        highlighted.push_str("**");
+        written = end;
+    }
+    highlighted.push_str(&text[written..]);
+    highlighted
+}
+
+/// Sum of the title and description scores of every query term
+fn relevance(task: &Task, terms: &[String]) -> u32 {
+    let field_score = |text: &str, word_score: u32, partial_score: u32| {
+        let text_words = words(text);
+        terms
+            .iter()
+            .map(|term| {
+                if text_words.iter().any(|word| word == term) {
+                    word_score
+                } else if text_words.iter().any(|word| word.contains(term.as_str())) {
+                    partial_score
+                } else {
+                    0
+                }
+            })
+            .sum::<u32>()
+    };
+    field_score(&task.title, TITLE_WORD_SCORE, TITLE_PARTIAL_SCORE)
+        + field_score(
+            &task.description,
+            DESCRIPTION_WORD_SCORE,
+            DESCRIPTION_PARTIAL_SCORE,
+        )
+}
+
+/// IDs used by more than one task, sorted
+fn duplicate_ids(tasks: &[Task]) -> Vec<String> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for task in tasks {
+        *counts.entry(task.id.as_str()).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|(id, _)| id.to_string())
+        .collect()
+}
+
+/// Diagnostic snapshot of the task file the service is using
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthReport {
+    pub file_path: PathBuf,
+    pub file_exists: bool,
+    pub task_count: usize,
+    /// Last modification time of the task file; `None` when it doesn't exist yet
+    pub last_modified: Option<DateTime<Utc>>,
+    pub schema_version: String,
+}
+
+/// Statistics about tasks
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskStatistics {
+    pub total_tasks: usize,
+    pub status_counts: HashMap<String, usize>,
+    pub priority_counts: HashMap<String, usize>,
+    /// Task counts per `(status, priority)` pair; pairs without tasks are absent
+    /// Serialized as an object keyed by status, then priority
+    #[serde(serialize_with = "serialize_matrix")]
+    pub matrix: HashMap<(String, String), usize>,
+    /// Fraction of tasks that are completed (0.0 when there are no tasks)
+    pub completion_rate: f64,
+    pub overdue_count: usize,
+    pub unassigned_count: usize,
+    /// Task counts per assignee, with unassigned tasks under [`UNASSIGNED_BUCKET`]
+    pub assignee_counts: HashMap<String, usize>,
+    /// Number of tasks carrying each tag; a tag listed twice on one task counts once
+    pub tag_counts: HashMap<String, usize>,
+}
+
+/// Bucket name used for tasks without an assignee in grouped statistics
+pub const UNASSIGNED_BUCKET: &str = "(unassigned)";
+
+/// Bucket name used for tasks without tags when grouping by tag
+pub const UNTAGGED_BUCKET: &str = "(untagged)";
+
+impl TaskStatistics {
+    /// Create task statistics from a collection of tasks, measuring overdue tasks against the current time
+    pub fn from_tasks(tasks: &[Task]) -> Self {
+        Self::from_tasks_at(tasks, Utc::now())
+    }
+
+    /// Create task statistics from a collection of tasks, measuring overdue tasks against `now`
+    pub fn from_tasks_at(tasks: &[Task], now: DateTime<Utc>) -> Self {
+        let total_tasks = tasks.len();
+        let mut status_counts = HashMap::new();
+        let mut priority_counts = HashMap::new();
+        let mut matrix = HashMap::new();
+        let mut completed_count = 0;
+        let mut overdue_count = 0;
+        let mut unassigned_count = 0;
+        let mut assignee_counts = HashMap::new();
+        let mut tag_counts = HashMap::new();
+
+        for task in tasks {
+            *status_counts.entry(task.status.to_string()).or_insert(0) += 1;
+            *priority_counts
+                .entry(task.priority.to_string())
+                .or_insert(0) += 1;
+            *matrix
+                .entry((task.status.to_string(), task.priority.to_string()))
+                .or_insert(0) += 1;
+
+            if task.status == TaskStatus::Completed {
+                completed_count += 1;
+            }
+            if task.is_overdue(now) {
+                overdue_count += 1;
+            }
+            if task.assignee.is_none() {
+                unassigned_count += 1;
+            }
+            let assignee = task.assignee.as_deref().unwrap_or(UNASSIGNED_BUCKET);
+            *assignee_counts.entry(assignee.to_string()).or_insert(0) += 1;
+
+            let unique_tags: HashSet<&String> = task.tags.iter().collect();
+            for tag in unique_tags {
+                *tag_counts.entry(tag.clone()).or_insert(0) += 1;
+            }
+        }
+
+        let completion_rate = if total_tasks == 0 {
+            0.0
+        } else {
+            completed_count as f64 / total_tasks as f64
+        };
+
+        Self {
+            total_tasks,
+            status_counts,
+            priority_counts,
+            matrix,
+            completion_rate,
+            overdue_count,
+            unassigned_count,
+            assignee_counts,
+            tag_counts,
+        }
+    }
+
+    /// Format statistics as a human-readable string
+    /// Statuses are listed in workflow order, priorities from Low to Critical, and
+    /// assignees and tags alphabetically, so the output is the same on every run
+    pub fn format_stats(&self) -> String {
+        format!(
+            "## Task Statistics\n\n**Total Tasks:** {}\n**Completion Rate:** {:.1}%\n**Overdue:** {}\n**Unassigned:** {}\n\n### By Status:\n{}\n\n### By Priority:\n{}\n\n### By Status and Priority:\n{}\n\n### By Assignee:\n{}\n\n### By Tag:\n{}",
+            self.total_tasks,
+            self.completion_rate * 100.0,
+            self.overdue_count,
+            self.unassigned_count,
+            format_counts_in_order(
+                &self.status_counts,
+                TaskStatus::ALL.iter().map(ToString::to_string)
+            ),
+            format_counts_in_order(
+                &self.priority_counts,
+                Priority::ALL.iter().map(ToString::to_string)
+            ),
+            self.format_matrix(),
+            format_counts(&self.assignee_counts),
+            format_counts(&self.tag_counts)
+        )
+    }
+
+    /// Render `matrix` as a markdown table with a row per status in workflow order and a
+    /// column per priority from Low to Critical; empty cells show 0
+    fn format_matrix(&self) -> String {
+        let priorities: Vec<String> = Priority::ALL.iter().map(ToString::to_string).collect();
+        let mut lines = vec![
+            format!("| Status | {} |", priorities.join(" | ")),
+            format!("|--------|{}", "---:|".repeat(priorities.len())),
+        ];
+        for status in TaskStatus::ALL.iter().map(ToString::to_string) {
+            let cells: Vec<String> = priorities
+                .iter()
+                .map(|priority| {
+                    let key = (status.clone(), priority.clone());
+                    self.matrix.get(&key).copied().unwrap_or(0).to_string()
+                })
+                .collect();
+            lines.push(format!("| {} | {} |", status, cells.join(" | ")));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Serialize the status-by-priority matrix as `{ status: { priority: count } }`, since JSON
+/// object keys can't be pairs; both levels come out sorted by name
+fn serialize_matrix<S: serde::Serializer>(
+    matrix: &HashMap<(String, String), usize>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut nested: BTreeMap<&str, BTreeMap<&str, usize>> = BTreeMap::new();
+    for ((status, priority), count) in matrix {
+        nested
+            .entry(status.as_str())
+            .or_default()
+            .insert(priority.as_str(), *count);
+    }
+    serializer.collect_map(nested)
+}
+
+/// Render a map of counts as a markdown bullet list, sorted by name
+fn format_counts(counts: &HashMap<String, usize>) -> String {
+    let mut names: Vec<&String> = counts.keys().collect();
+    names.sort();
+    format_counts_in_order(counts, names.into_iter().cloned())
+}
+
+/// Render a map of counts as a markdown bullet list in the given name order
+/// Names without a count are skipped
+fn format_counts_in_order(
+    counts: &HashMap<String, usize>,
+    names: impl IntoIterator<Item = String>,
+) -> String {
+    names
+        .into_iter()
+        .filter_map(|name| {
+            counts
+                .get(&name)
+                .map(|count| format!("- {}: {}", name, count))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+    use crate::error::StorageError;
+    use crate::models::TaskChange;
+    use crate::storage::MemoryStorage;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    fn sample_task(id: &str) -> Task {
+        Task {
+            id: id.to_string(),
+            title: format!("Task {}", id),
+            description: "Sample task".to_string(),
+            status: TaskStatus::Pending,
+            priority: Priority::Medium,
+            created_at: "2024-01-15T09:00:00Z".to_string(),
+            updated_at: "2024-01-15T09:00:00Z".to_string(),
+            tags: vec![],
+            assignee: None,
+            due_date: None,
+            parent_id: None,
+            depends_on: vec![],
+            recurrence: None,
+            comments: vec![],
+            metadata: Default::default(),
+            history: vec![],
+            order: None,
+        }
+    }
+
+    fn sample_tasks(count: usize) -> Vec<Task> {
+        (1..=count)
+            .map(|i| sample_task(&format!("task-{}", i)))
+            .collect()
+    }
+
+    fn service() -> TaskService {
+        TaskService::new(TaskStorage::new(PathBuf::from("unused.json")))
+    }
+
+    async fn service_with_tasks(tasks: Vec<Task>) -> (TempDir, TaskService) {
+        let dir = TempDir::new().expect("Failed to create temp dir");
+        let service = TaskService::new(TaskStorage::new(dir.path().join("tasks.json")));
+        let mut collection = TaskCollection::new();
+        collection.tasks = tasks;
+        service
+            .save_tasks(&collection)
+            .await
+            .expect("Failed to save tasks");
+        (dir, service)
+    }
+
+    #[test]
+    fn test_paginate_middle_page() {
+        let page = service().paginate(sample_tasks(57), 10, Some(10));
+        assert_eq!(page.tasks.len(), 10);
+        assert_eq!(page.tasks[0].id, "task-11");
+        assert_eq!(page.summary(), "Showing 11-20 of 57 tasks");
+    }
+
+    #[test]
+    fn test_paginate_boundary_offsets() {
+        let page = service().paginate(sample_tasks(5), 4, Some(10));
+        assert_eq!(page.tasks.len(), 1);
+        assert_eq!(page.summary(), "Showing 5-5 of 5 tasks");
+
+        let page = service().paginate(sample_tasks(5), 5, Some(10));
+        assert!(page.tasks.is_empty());
+        assert_eq!(page.summary(), "Showing 0 of 5 tasks");
+
+        let page = service().paginate(sample_tasks(5), 100, None);
+        assert!(page.tasks.is_empty());
+    }
+
+    #[test]
+    fn test_paginate_limit_larger_than_results() {
+        let page = service().paginate(sample_tasks(3), 0, Some(50));
+        assert_eq!(page.tasks.len(), 3);
+        assert_eq!(page.total, 3);
+        assert_eq!(page.summary(), "Showing 1-3 of 3 tasks");
+    }
+
+    fn args(value: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+        match value {
+            serde_json::Value::Object(map) => map,
+            _ => panic!("Arguments must be an object"),
+        }
+    }
+
+    #[test]
+    fn test_filter_from_arguments() {
+        let filter = TaskFilter::from_arguments(&args(serde_json::json!({
+            "status": "in_progress",
+            "priority": "high",
+            "assignee": "alice.smith",
+            "limit": 10
+        })))
+        .expect("Valid filter should parse");
+
+        assert_eq!(filter.status, Some(TaskStatus::InProgress));
+        assert_eq!(filter.priority, Some(Priority::High));
+        assert_eq!(filter.assignee.as_deref(), Some("alice.smith"));
+        assert_eq!(filter.tag, None);
+    }
+
+    #[test]
+    fn test_filter_enums_ignore_case() {
+        let filter = TaskFilter::from_arguments(&args(serde_json::json!({
+            "status": "Pending",
+            "priority": " HIGH "
+        })))
+        .expect("Mixed-case filter should parse");
+
+        assert_eq!(filter.status, Some(TaskStatus::Pending));
+        assert_eq!(filter.priority, Some(Priority::High));
+        // The file format keeps its lowercase names
+        assert_eq!(serde_json::to_value(Priority::High).unwrap(), "high");
+    }
+
+    #[test]
+    fn test_filter_rejects_bad_status() {
+        let err = TaskFilter::from_arguments(&args(serde_json::json!({ "status": "done" })))
+            .expect_err("Unknown status should be rejected");
+        assert!(err.to_string().contains("Invalid status: done"));
+    }
+
+    #[test]
+    fn test_filter_tasks_by_tag_and_priority() {
+        let mut tagged = sample_task("task-1");
+        tagged.tags = vec!["backend".to_string()];
+        tagged.priority = Priority::High;
+        let mut other = sample_task("task-2");
+        other.tags = vec!["backend".to_string()];
+        let tasks = vec![tagged, other, sample_task("task-3")];
+
+        let filter = TaskFilter {
+            priority: Some(Priority::High),
+            tag: Some("backend".to_string()),
+            ..Default::default()
+        };
+        let filtered = service().filter_tasks(&tasks, &filter);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].id, "task-1");
+    }
+
+    #[test]
+    fn test_fuzzy_filter() {
+        let mut alice = sample_task("task-1");
+        alice.assignee = Some(" alice ".to_string());
+        alice.tags = vec!["Backend-API".to_string()];
+        let tasks = vec![alice, sample_task("task-2")];
+
+        let exact =
+            TaskFilter::from_arguments(&args(serde_json::json!({ "assignee": "Alice" }))).unwrap();
+        assert!(service().filter_tasks(&tasks, &exact).is_empty());
+
+        let fuzzy = TaskFilter::from_arguments(&args(
+            serde_json::json!({ "assignee": "Alice", "fuzzy": true }),
+        ))
+        .unwrap();
+        let filtered = service().filter_tasks(&tasks, &fuzzy);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].id, "task-1");
+
+        let tag = TaskFilter {
+            tag: Some("backend".to_string()),
+            ..Default::default()
+        };
+        assert!(service().filter_tasks(&tasks, &tag).is_empty());
+        let tag = TaskFilter { fuzzy: true, ..tag };
+        assert_eq!(service().filter_tasks(&tasks, &tag).len(), 1);
+
+        assert!(TaskFilter::from_arguments(&args(serde_json::json!({ "fuzzy": "yes" }))).is_err());
+    }
+
+    #[test]
+    fn test_unassigned_filter() {
+        let mut assigned = sample_task("task-1");
+        assigned.assignee = Some("alice".to_string());
+        let mut pending_unassigned = sample_task("task-2");
+        pending_unassigned.priority = Priority::High;
+        let tasks = vec![assigned, pending_unassigned, sample_task("task-3")];
+
+        let filter =
+            TaskFilter::from_arguments(&args(serde_json::json!({ "unassigned": true }))).unwrap();
+        let ids: Vec<String> = service()
+            .filter_tasks(&tasks, &filter)
+            .into_iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(ids, vec!["task-2", "task-3"]);
+
+        let filter = TaskFilter::from_arguments(&args(
+            serde_json::json!({ "unassigned": true, "priority": "high" }),
+        ))
+        .unwrap();
+        assert_eq!(service().filter_tasks(&tasks, &filter).len(), 1);
+
+        assert!(
+            TaskFilter::from_arguments(&args(
+                serde_json::json!({ "unassigned": true, "assignee": "alice" })
+            ))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_has_due_date_filter() {
+        let mut scheduled = sample_task("task-1");
+        scheduled.due_date = Some("2024-03-01T17:00:00Z".to_string());
+        let tasks = vec![scheduled, sample_task("task-2"), sample_task("task-3")];
+        let ids = |arguments: serde_json::Value| -> Vec<String> {
+            let filter = TaskFilter::from_arguments(&args(arguments)).unwrap();
+            service()
+                .filter_tasks(&tasks, &filter)
+                .into_iter()
+                .map(|t| t.id)
+                .collect()
+        };
+
+        assert_eq!(
+            ids(serde_json::json!({ "has_due_date": true })),
+            vec!["task-1"]
+        );
+        assert_eq!(
+            ids(serde_json::json!({ "has_due_date": false })),
+            vec!["task-2", "task-3"]
+        );
+        assert_eq!(ids(serde_json::json!({ "has_due_date": null })).len(), 3);
+        assert!(
+            TaskFilter::from_arguments(&args(serde_json::json!({ "has_due_date": "yes" })))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_all_tags_filter_requires_every_tag() {
+        let tagged = |id: &str, tags: &[&str]| {
+            let mut task = sample_task(id);
+            task.tags = tags.iter().map(|t| t.to_string()).collect();
+            task
+        };
+        let tasks = vec![
+            tagged("task-1", &["backend", "urgent", "api"]),
+            tagged("task-2", &["backend"]),
+            tagged("task-3", &["urgent"]),
+        ];
+        let ids = |arguments: serde_json::Value| -> Vec<String> {
+            let filter = TaskFilter::from_arguments(&args(arguments)).unwrap();
+            service()
+                .filter_tasks(&tasks, &filter)
+                .into_iter()
+                .map(|t| t.id)
+                .collect()
+        };
+
+        assert_eq!(
+            ids(serde_json::json!({ "all_tags": ["backend", "urgent"] })),
+            vec!["task-1"]
+        );
+        assert_eq!(
+            ids(serde_json::json!({ "all_tags": ["backend"], "tag": "api" })),
+            vec!["task-1"]
+        );
+        assert_eq!(ids(serde_json::json!({ "all_tags": [] })).len(), 3);
+        assert!(
+            TaskFilter::from_arguments(&args(serde_json::json!({ "all_tags": "backend" })))
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reorder_task_moves_to_top() {
+        let mut tasks = sample_tasks(4);
+        for (task, order) in tasks.iter_mut().zip(1..=3) {
+            task.order = Some(order);
+        }
+        let (_dir, service) = service_with_tasks(tasks).await;
+
+        let moved = service.reorder_task("task-3", 1).await.unwrap();
+        assert_eq!(moved.order, Some(1));
+
+        let mut tasks = service.load_tasks().await.unwrap().tasks;
+        service.sort_tasks(&mut tasks, SortBy::Order);
+        let sorted: Vec<(&str, Option<i64>)> =
+            tasks.iter().map(|t| (t.id.as_str(), t.order)).collect();
+        assert_eq!(
+            sorted,
+            vec![
+                ("task-3", Some(1)),
+                ("task-1", Some(2)),
+                ("task-2", Some(3)),
+                ("task-4", None)
+            ]
+        );
+
+        assert!(matches!(
+            service.reorder_task("missing", 1).await,
+            Err(TaskError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_match_any_versus_all() {
+        let task = |id: &str, priority: Priority, assignee: Option<&str>| {
+            let mut task = sample_task(id);
+            task.priority = priority;
+            task.assignee = assignee.map(str::to_string);
+            task
+        };
+        let tasks = vec![
+            task("task-1", Priority::High, Some("alice")),
+            task("task-2", Priority::High, Some("bob")),
+            task("task-3", Priority::Low, Some("alice")),
+            task("task-4", Priority::Low, None),
+        ];
+        let ids = |arguments: serde_json::Value| -> Vec<String> {
+            let filter = TaskFilter::from_arguments(&args(arguments)).unwrap();
+            service()
+                .filter_tasks(&tasks, &filter)
+                .into_iter()
+                .map(|t| t.id)
+                .collect()
+        };
+
+        assert_eq!(
+            ids(serde_json::json!({ "priority": "high", "assignee": "alice" })),
+            vec!["task-1"]
+        );
+        assert_eq!(
+            ids(serde_json::json!({ "priority": "high", "assignee": "alice", "match": "all" })),
+            vec!["task-1"]
+        );
+        assert_eq!(
+            ids(serde_json::json!({ "priority": "high", "assignee": "alice", "match": "any" })),
+            vec!["task-1", "task-2", "task-3"]
+        );
+        assert_eq!(ids(serde_json::json!({ "match": "any" })).len(), 4);
+        assert!(
+            TaskFilter::from_arguments(&args(serde_json::json!({ "match": "either" }))).is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_ranks_title_above_description() {
+        let mut described = sample_task("task-1");
+        described.title = "Quarterly planning".to_string();
+        described.description = "Prepare the deploy checklist".to_string();
+        described.priority = Priority::Critical;
+        let mut titled = sample_task("task-2");
+        titled.title = "Deploy the API".to_string();
+        titled.description = "Ship it".to_string();
+        titled.priority = Priority::Low;
+        let mut partial = sample_task("task-3");
+        partial.title = "Redeployment dry run".to_string();
+        partial.description = "No match here".to_string();
+        let (_dir, service) =
+            service_with_tasks(vec![described, titled, partial, sample_task("task-4")]).await;
+
+        let hits = service.search("DEPLOY").await.unwrap();
+        let ids: Vec<&str> = hits.iter().map(|hit| hit.task.id.as_str()).collect();
+        assert_eq!(ids, vec!["task-2", "task-3", "task-1"]);
+        assert_eq!(hits[0].score, TITLE_WORD_SCORE);
+        assert_eq!(hits[1].score, TITLE_PARTIAL_SCORE);
+        assert_eq!(hits[2].score, DESCRIPTION_WORD_SCORE);
+    }
+
+    #[tokio::test]
+    async fn test_search_ties_break_on_priority() {
+        let mut low = sample_task("task-1");
+        low.title = "Fix login".to_string();
+        low.priority = Priority::Low;
+        let mut high = sample_task("task-2");
+        high.title = "Fix logout".to_string();
+        high.priority = Priority::High;
+        let (_dir, service) = service_with_tasks(vec![low, high]).await;
+
+        let hits = service.search("fix").await.unwrap();
+        assert_eq!(hits[0].task.id, "task-2");
+        assert_eq!(hits[0].score, hits[1].score);
+
+        assert!(service.search("  --  ").await.is_err());
+    }
+
+    #[test]
+    fn test_highlight_is_literal_and_ignores_case() {
+        let terms = vec!["deploy".to_string(), "ploy".to_string()];
+        assert_eq!(
+            highlight("Deploy the API, then redeploy", &terms),
+            "**Deploy** the API, then re**deploy**"
+        );
+        // Terms are plain text, never patterns
+        assert_eq!(
+            highlight("axb and a.b", &["a.b".to_string()]),
+            "axb and **a.b**"
+        );
+        assert_eq!(highlight("nothing here", &terms), "nothing here");
+    }
+
+    #[tokio::test]
+    async fn test_related_tags_counts_co_occurrences() {
+        let tagged = |id: &str, tags: &[&str]| {
+            let mut task = sample_task(id);
+            task.tags = tags.iter().map(|t| t.to_string()).collect();
+            task
+        };
+        let tasks = vec![
+            tagged("task-1", &["backend", "api", "urgent"]),
+            tagged("task-2", &["backend", "api", "api"]),
+            tagged("task-3", &["backend", "db"]),
+            tagged("task-4", &["frontend", "api"]),
+        ];
+        let (_dir, service) = service_with_tasks(tasks).await;
+
+        let related = service.related_tags("backend").await.unwrap();
+        assert_eq!(
+            related,
+            vec![
+                ("api".to_string(), 2),
+                ("db".to_string(), 1),
+                ("urgent".to_string(), 1)
+            ]
+        );
+        assert!(service.related_tags("missing").await.unwrap().is_empty());
+        assert!(service.related_tags("  ").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_facets_are_distinct_and_sorted() {
+        let task = |id: &str, assignee: Option<&str>, tags: &[&str]| {
+            let mut task = sample_task(id);
+            task.assignee = assignee.map(str::to_string);
+            task.tags = tags.iter().map(|t| t.to_string()).collect();
+            task
+        };
+        let tasks = vec![
+            task("task-1", Some("bob"), &["backend", "api"]),
+            task("task-2", Some("alice"), &["api", "api", "urgent"]),
+            task("task-3", Some("bob"), &["backend"]),
+            task("task-4", None, &["api", "docs"]),
+        ];
+        let (_dir, service) = service_with_tasks(tasks).await;
+
+        let facet = |value: &str, count: usize| FacetCount {
+            value: value.to_string(),
+            count,
+        };
+        let facets = service.facets().await.unwrap();
+        assert_eq!(facets.assignees, vec![facet("alice", 1), facet("bob", 2)]);
+        assert_eq!(
+            facets.tags,
+            vec![
+                facet("api", 3),
+                facet("backend", 2),
+                facet("docs", 1),
+                facet("urgent", 1)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_workload_orders_by_weighted_load() {
+        let task = |id: &str, assignee: Option<&str>, priority: Priority, status: TaskStatus| {
+            let mut task = sample_task(id);
+            task.assignee = assignee.map(str::to_string);
+            task.priority = priority;
+            task.status = status;
+            task
+        };
+        let tasks = vec![
+            // alice: three low-priority tasks, weight 3
+            task("task-1", Some("alice"), Priority::Low, TaskStatus::Pending),
+            task(
+                "task-2",
+                Some("alice"),
+                Priority::Low,
+                TaskStatus::InProgress,
+            ),
+            task("task-3", Some("alice"), Priority::Low, TaskStatus::Pending),
+            // bob: one critical task, weight 8; his completed task doesn't count
+            task(
+                "task-4",
+                Some("bob"),
+                Priority::Critical,
+                TaskStatus::Pending,
+            ),
+            task("task-5", Some("bob"), Priority::High, TaskStatus::Completed),
+            // unassigned: one medium task, weight 2
+            task("task-6", None, Priority::Medium, TaskStatus::Pending),
+            task(
+                "task-7",
+                Some("carol"),
+                Priority::High,
+                TaskStatus::Cancelled,
+            ),
+        ];
+        let (_dir, service) = service_with_tasks(tasks).await;
+
+        let workload = service.workload().await.unwrap();
+        assert_eq!(
+            workload,
+            vec![
+                AssigneeWorkload {
+                    assignee: "bob".to_string(),
+                    open_tasks: 1,
+                    weighted_load: 8,
+                },
+                AssigneeWorkload {
+                    assignee: "alice".to_string(),
+                    open_tasks: 3,
+                    weighted_load: 3,
+                },
+                AssigneeWorkload {
+                    assignee: UNASSIGNED_BUCKET.to_string(),
+                    open_tasks: 1,
+                    weighted_load: 2,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_custom_priority_weights_change_workload_order() {
+        let task = |id: &str, assignee: &str, priority: Priority| {
+            let mut task = sample_task(id);
+            task.assignee = Some(assignee.to_string());
+            task.priority = priority;
+            task
+        };
+        let tasks = vec![
+            task("task-1", "alice", Priority::Low),
+            task("task-2", "alice", Priority::Low),
+            task("task-3", "alice", Priority::Low),
+            task("task-4", "bob", Priority::High),
+        ];
+        let (_dir, service) = service_with_tasks(tasks).await;
+
+        let order = |workload: Vec<AssigneeWorkload>| -> Vec<String> {
+            workload.into_iter().map(|w| w.assignee).collect()
+        };
+        assert_eq!(
+            order(service.workload().await.unwrap()),
+            vec!["bob", "alice"]
+        );
+
+        let service =
+            service.with_priority_weights(HashMap::from([(Priority::Low, 5), (Priority::High, 6)]));
+        let workload = service.workload().await.unwrap();
+        assert_eq!(workload[0].weighted_load, 15);
+        assert_eq!(order(workload), vec!["alice", "bob"]);
+    }
+
+    #[tokio::test]
+    async fn test_overdue_tasks() {
+        let now = Utc.with_ymd_and_hms(2024, 2, 1, 12, 0, 0).unwrap();
+
+        let mut future = sample_task("future");
+        future.due_date = Some("2024-02-10T17:00:00Z".to_string());
+        let mut past = sample_task("past");
+        past.due_date = Some("2024-01-20T17:00:00Z".to_string());
+        let mut past_completed = sample_task("past-completed");
+        past_completed.due_date = Some("2024-01-20T17:00:00Z".to_string());
+        past_completed.status = TaskStatus::Completed;
+        let no_due_date = sample_task("no-due-date");
+
+        let (_dir, service) =
+            service_with_tasks(vec![future, past, past_completed, no_due_date]).await;
+
+        let overdue = service.overdue_tasks(now).await.unwrap();
+        assert_eq!(overdue.len(), 1);
+        assert_eq!(overdue[0].id, "past");
+    }
+
+    #[test]
+    fn test_format_stats_order_is_stable() {
+        let statuses = [
+            TaskStatus::Cancelled,
+            TaskStatus::Completed,
+            TaskStatus::InProgress,
+            TaskStatus::Pending,
+        ];
+        let priorities = [
+            Priority::Critical,
+            Priority::Low,
+            Priority::High,
+            Priority::Medium,
+        ];
+        let tasks: Vec<Task> = statuses
+            .iter()
+            .zip(priorities.iter())
+            .enumerate()
+            .map(|(i, (status, priority))| {
+                let mut task = sample_task(&format!("task-{}", i));
+                task.status = status.clone();
+                task.priority = priority.clone();
+                task.assignee = Some(["zoe", "adam", "mia", "adam"][i].to_string());
+                task
+            })
+            .collect();
+
+        let rendered = TaskStatistics::from_tasks(&tasks).format_stats();
+        for _ in 0..10 {
+            assert_eq!(TaskStatistics::from_tasks(&tasks).format_stats(), rendered);
+        }
+
+        let section = |heading: &str| -> Vec<String> {
+            rendered
+                .split(heading)
+                .nth(1)
+                .unwrap()
+                .lines()
+                .skip(1)
+                .take_while(|line| line.starts_with("- "))
+                .map(str::to_string)
+                .collect()
+        };
+        assert_eq!(
+            section("### By Status:"),
+            vec![
+                "- Pending: 1",
+                "- In Progress: 1",
+                "- Completed: 1",
+                "- Cancelled: 1"
+            ]
+        );
+        assert_eq!(
+            section("### By Priority:"),
+            vec!["- Low: 1", "- Medium: 1", "- High: 1", "- Critical: 1"]
+        );
+        assert_eq!(
+            section("### By Assignee:"),
+            vec!["- adam: 2", "- mia: 1", "- zoe: 1"]
+        );
+    }
+
+    #[test]
+    fn test_unknown_filter_key_strict_and_lenient() {
+        let arguments = args(serde_json::json!({ "statuss": "pending", "limit": 5 }));
+
+        let filter = service()
+            .filter_from_arguments(&arguments, &["limit"])
+            .expect("Lenient mode should ignore unknown keys");
+        assert!(filter.status.is_none());
+
+        let err = service()
+            .with_strict_filters(true)
+            .filter_from_arguments(&arguments, &["limit"])
+            .unwrap_err();
+        assert!(err.to_string().contains("Unknown filter keys: statuss"));
+    }
+
+    #[test]
+    fn test_filter_created_range_is_inclusive() {
+        let mut early = sample_task("early");
+        early.created_at = "2024-01-01T00:00:00Z".to_string();
+        let mut on_bound = sample_task("on-bound");
+        on_bound.created_at = "2024-01-10T00:00:00Z".to_string();
+        let mut inside = sample_task("inside");
+        inside.created_at = "2024-01-15T12:00:00Z".to_string();
+        let mut garbage = sample_task("garbage");
+        garbage.created_at = "yesterday".to_string();
+
+        let filter = TaskFilter::from_arguments(&args(serde_json::json!({
+            "created_after": "2024-01-10T00:00:00Z",
+            "created_before": "2024-01-20T00:00:00Z"
+        })))
+        .unwrap();
+        let ids: Vec<String> = service()
+            .filter_tasks(&[early, on_bound, inside, garbage], &filter)
+            .into_iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(ids, vec!["on-bound", "inside"]);
+    }
+
+    #[test]
+    fn test_filter_updated_range_excludes_out_of_range() {
+        let mut stale = sample_task("stale");
+        stale.updated_at = "2023-12-31T23:59:59Z".to_string();
+        let fresh = sample_task("fresh");
+
+        let filter = TaskFilter::from_arguments(&args(serde_json::json!({
+            "updated_after": "2024-01-01T00:00:00Z"
+        })))
+        .unwrap();
+        let matched = service().filter_tasks(&[stale, fresh], &filter);
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].id, "fresh");
+
+        let err = TaskFilter::from_arguments(&args(serde_json::json!({
+            "updated_before": "last week"
+        })))
+        .unwrap_err();
+        assert!(err.to_string().contains("Invalid updated_before"));
+    }
+
+    #[tokio::test]
+    async fn test_set_assignee_transitions() {
+        let (_dir, service) = service_with_tasks(vec![sample_task("task-1")]).await;
+
+        let previous = service
+            .set_assignee("task-1", Some("alice".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(previous, None);
+
+        let previous = service
+            .set_assignee("task-1", Some("bob".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(previous.as_deref(), Some("alice"));
+        let task = service.find_task_by_id("task-1").await.unwrap();
+        assert_eq!(task.assignee.as_deref(), Some("bob"));
+
+        let previous = service.set_assignee("task-1", None).await.unwrap();
+        assert_eq!(previous.as_deref(), Some("bob"));
+        let task = service.find_task_by_id("task-1").await.unwrap();
+        assert_eq!(task.assignee, None);
+    }
+
+    #[tokio::test]
+    async fn test_set_due_date_relative() {
+        let now = Utc.with_ymd_and_hms(2024, 2, 1, 12, 0, 0).unwrap();
+        let (_dir, service) = service_with_tasks(vec![sample_task("task-1")]).await;
+
+        let task = service
+            .set_due_date("task-1", "tomorrow", now)
+            .await
+            .unwrap();
+        assert_eq!(task.due_date.as_deref(), Some("2024-02-02T23:59:59Z"));
+        let task = service
+            .set_due_date("task-1", "in 3 days", now)
+            .await
+            .unwrap();
+        assert_eq!(task.due_date.as_deref(), Some("2024-02-04T23:59:59Z"));
+        assert_eq!(
+            service.find_task_by_id("task-1").await.unwrap().due_date,
+            task.due_date
+        );
+
+        assert!(matches!(
+            service.set_due_date("task-1", "whenever", now).await,
+            Err(TaskError::Validation(_))
+        ));
+        assert!(matches!(
+            service.set_due_date("missing", "tomorrow", now).await,
+            Err(TaskError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_is_shared_until_a_change() {
+        let (_dir, service) = service_with_tasks(vec![sample_task("task-1")]).await;
+
+        let first = service.snapshot().await.unwrap();
+        let second = service.snapshot().await.unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        service.delete_task("task-1", false).await.unwrap();
+        let after = service.snapshot().await.unwrap();
+        assert!(!Arc::ptr_eq(&first, &after));
+        assert!(after.tasks.is_empty());
+        assert_eq!(first.tasks.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_event_log_records_create_and_delete() {
+        let dir = TempDir::new().unwrap();
+        let log_path = dir.path().join("events.jsonl");
+        let service = TaskService::new(TaskStorage::in_memory(MemoryStorage::new()))
+            .with_clock(FixedClock(
+                Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap(),
+            ))
+            .with_event_log(EventLog::new(&log_path));
+
+        let new_task = NewTask {
+            title: "Write release notes".to_string(),
+            ..NewTask::default()
+        };
+        let task = service.create_task(&new_task).await.unwrap();
+        assert!(service.delete_task(&task.id, true).await.unwrap());
+        assert!(service.delete_task(&task.id, false).await.unwrap());
+
+        let content = std::fs::read_to_string(&log_path).unwrap();
+        let events: Vec<serde_json::Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2, "unexpected log: {}", content);
+
+        assert_eq!(events[0]["operation"], "create");
+        assert_eq!(events[0]["task_id"], task.id.as_str());
+        assert_eq!(events[0]["at"], "2024-03-01T12:00:00Z");
+        assert_eq!(
+            events[0]["diff"]["title"],
+            serde_json::json!({ "old": null, "new": "Write release notes" })
+        );
+
+        assert_eq!(events[1]["operation"], "delete");
+        assert_eq!(events[1]["task_id"], task.id.as_str());
+        assert_eq!(
+            events[1]["diff"]["status"],
+            serde_json::json!({ "old": "pending", "new": null })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_patch_appends_tag() {
+        let mut task = sample_task("task-1");
+        task.tags = vec!["backend".to_string()];
+        let (_dir, service) = service_with_tasks(vec![task, sample_task("task-2")]).await;
+
+        let patch: json_patch::Patch = serde_json::from_value(serde_json::json!([
+            { "op": "add", "path": "/tags/-", "value": "urgent" },
+            { "op": "replace", "path": "/priority", "value": "high" }
+        ]))
+        .unwrap();
+        let patched = service.apply_patch("task-1", &patch, false).await.unwrap();
+        assert_eq!(patched.tags, vec!["backend", "urgent"]);
+        assert_eq!(patched.priority, Priority::High);
+        assert_eq!(
+            patched
+                .history
+                .iter()
+                .map(|change| change.field.as_str())
+                .collect::<Vec<_>>(),
+            vec!["priority", "tags"]
+        );
+        assert_eq!(
+            service.find_task_by_id("task-1").await.unwrap().priority,
+            Priority::High
+        );
+
+        let reject = |ops: serde_json::Value| {
+            let patch: json_patch::Patch = serde_json::from_value(ops).unwrap();
+            let service = service.clone();
+            async move { service.apply_patch("task-1", &patch, false).await }
+        };
+        let err = reject(serde_json::json!([
+            { "op": "replace", "path": "/priority", "value": "urgent" }
+        ]))
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("Patched task is not valid"));
+        let err = reject(serde_json::json!([
+            { "op": "replace", "path": "/id", "value": "task-9" }
+        ]))
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("cannot change the task ID"));
+        let err = reject(serde_json::json!([
+            { "op": "remove", "path": "/tags/5" }
+        ]))
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("Invalid patch"));
+        let early_due: json_patch::Patch = serde_json::from_value(serde_json::json!([
+            { "op": "add", "path": "/due_date", "value": "2024-01-14T09:00:00Z" }
+        ]))
+        .unwrap();
+        let err = service
+            .clone()
+            .with_strict_validation(true)
+            .apply_patch("task-1", &early_due, false)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("is before created_at"));
+        assert!(matches!(
+            service.apply_patch("missing", &patch, false).await,
+            Err(TaskError::NotFound(_))
+        ));
+        assert_eq!(
+            service.find_task_by_id("task-1").await.unwrap().tags,
+            vec!["backend", "urgent"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_task_with_slug_ids() {
+        let service = TaskService::new(TaskStorage::in_memory(MemoryStorage::new()))
+            .with_id_strategy(IdStrategy::Slug);
+        let new_task = NewTask {
+            title: "Write release notes".to_string(),
+            ..NewTask::default()
+        };
+
+        let first = service.create_task(&new_task).await.unwrap();
+        let second = service.create_task(&new_task).await.unwrap();
+        assert_eq!(first.id, "write-release-notes");
+        assert_eq!(second.id, "write-release-notes-2");
+        assert_eq!(
+            service.find_task_by_id(&second.id).await.unwrap().title,
+            "Write release notes"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recurring_copy_gets_slug_id() {
+        let mut task = sample_task("water-plants");
+        task.title = "Water plants".to_string();
+        task.recurrence = Some("daily".to_string());
+        let mut collection = TaskCollection::new();
+        collection.tasks = vec![task];
+        let service = TaskService::new(TaskStorage::in_memory(MemoryStorage::with_tasks(
+            collection,
+        )))
+        .with_id_strategy(IdStrategy::Slug);
+
+        let (_, next_task) = service
+            .complete_and_reschedule("water-plants", false)
+            .await
+            .unwrap();
+        assert_eq!(next_task.unwrap().id, "water-plants-2");
+    }
+
+    #[tokio::test]
+    async fn test_create_task_applies_configured_defaults() {
+        let service = TaskService::new(TaskStorage::in_memory(MemoryStorage::new()))
+            .with_task_defaults(Priority::High, TaskStatus::InProgress);
+
+        let arguments = serde_json::json!({ "title": "  Write release notes " });
+        let new_task = NewTask::from_arguments(arguments.as_object().unwrap()).unwrap();
+        let task = service.create_task(&new_task).await.unwrap();
+        assert_eq!(task.title, "Write release notes");
+        assert_eq!(task.priority, Priority::High);
+        assert_eq!(task.status, TaskStatus::InProgress);
+        assert_eq!(
+            service.find_task_by_id(&task.id).await.unwrap().priority,
+            Priority::High
+        );
+
+        let explicit = NewTask {
+            title: "Ship it".to_string(),
+            priority: Some(Priority::Low),
+            ..NewTask::default()
+        };
+        assert_eq!(
+            service.create_task(&explicit).await.unwrap().priority,
+            Priority::Low
+        );
+
+        let blank = serde_json::json!({ "title": "  " });
+        assert!(NewTask::from_arguments(blank.as_object().unwrap()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cleared_due_date_is_no_longer_overdue() {
+        let now = Utc.with_ymd_and_hms(2024, 2, 1, 12, 0, 0).unwrap();
+        let (_dir, service) = service_with_tasks(vec![sample_task("task-1")]).await;
+
+        let update = TaskUpdate::from_arguments(&args(
+            serde_json::json!({ "due_date": "2024-01-20T09:00:00Z" }),
+        ))
+        .unwrap();
+        service.update_task("task-1", &update, false).await.unwrap();
+        assert_eq!(service.overdue_tasks(now).await.unwrap().len(), 1);
+
+        let clear =
+            TaskUpdate::from_arguments(&args(serde_json::json!({ "due_date": null }))).unwrap();
+        assert_eq!(clear.due_date, Some(None));
+        service.update_task("task-1", &clear, false).await.unwrap();
+        assert_eq!(
+            service.find_task_by_id("task-1").await.unwrap().due_date,
+            None
+        );
+        assert!(service.overdue_tasks(now).await.unwrap().is_empty());
+
+        // set_due_date clears with a blank expression, and the task leaves due-soon too
+        service
+            .set_due_date("task-1", "tomorrow", now)
+            .await
+            .unwrap();
+        assert_eq!(service.due_within(now, 7).await.unwrap().len(), 1);
+        let task = service.set_due_date("task-1", " ", now).await.unwrap();
+        assert_eq!(task.due_date, None);
+        assert!(service.due_within(now, 7).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_set_assignee_blank_unassigns() {
+        let mut task = sample_task("task-1");
+        task.assignee = Some("alice".to_string());
+        let (_dir, service) = service_with_tasks(vec![task]).await;
+
+        service
+            .set_assignee("task-1", Some("  ".to_string()))
+            .await
+            .unwrap();
+        let task = service.find_task_by_id("task-1").await.unwrap();
+        assert_eq!(task.assignee, None);
+    }
+
+    #[tokio::test]
+    async fn test_add_tag_deduplicates() {
+        let mut task = sample_task("task-1");
+        task.tags = vec!["backend".to_string()];
+        let (_dir, service) = service_with_tasks(vec![task]).await;
+
+        let tags = service.add_tag("task-1", "api").await.unwrap();
+        assert_eq!(tags, vec!["backend", "api"]);
+        let tags = service.add_tag("task-1", "backend").await.unwrap();
+        assert_eq!(tags, vec!["backend", "api"]);
+
+        let task = service.find_task_by_id("task-1").await.unwrap();
+        assert_eq!(task.tags, vec!["backend", "api"]);
+    }
+
+    #[tokio::test]
+    async fn test_remove_absent_tag_is_noop() {
+        let mut task = sample_task("task-1");
+        task.tags = vec!["backend".to_string(), "api".to_string()];
+        let (_dir, service) = service_with_tasks(vec![task]).await;
+
+        let tags = service.remove_tag("task-1", "frontend").await.unwrap();
+        assert_eq!(tags, vec!["backend", "api"]);
+        let task = service.find_task_by_id("task-1").await.unwrap();
+        assert_eq!(task.updated_at, "2024-01-15T09:00:00Z");
+
+        let tags = service.remove_tag("task-1", "backend").await.unwrap();
+        assert_eq!(tags, vec!["api"]);
+    }
+
+    #[tokio::test]
+    async fn test_rename_tag_merges_without_duplicates() {
+        let mut both = sample_task("task-1");
+        both.tags = vec!["bugs".to_string(), "api".to_string(), "bug".to_string()];
+        let mut typo = sample_task("task-2");
+        typo.tags = vec!["bugs".to_string()];
+        let mut untouched = sample_task("task-3");
+        untouched.tags = vec!["api".to_string()];
+        let (_dir, service) = service_with_tasks(vec![both, typo, untouched]).await;
+
+        assert_eq!(service.rename_tag("bugs", "bug").await.unwrap(), 2);
+
+        let task = service.find_task_by_id("task-1").await.unwrap();
+        assert_eq!(task.tags, vec!["bug", "api"]);
+        let task = service.find_task_by_id("task-2").await.unwrap();
+        assert_eq!(task.tags, vec!["bug"]);
+        let task = service.find_task_by_id("task-3").await.unwrap();
+        assert_eq!(task.tags, vec!["api"]);
+        assert_eq!(task.updated_at, "2024-01-15T09:00:00Z");
+
+        assert_eq!(service.rename_tag("bugs", "bug").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_tag_high_priority_tasks() {
+        let mut tagged = sample_task("task-1");
+        tagged.priority = Priority::High;
+        tagged.tags = vec!["urgent".to_string(), "triage".to_string()];
+        let mut untagged = sample_task("task-2");
+        untagged.priority = Priority::High;
+        let (_dir, service) =
+            service_with_tasks(vec![tagged, untagged, sample_task("task-3")]).await;
+
+        let filter = TaskFilter {
+            priority: Some(Priority::High),
+            ..TaskFilter::default()
+        };
+        let updated = service
+            .bulk_tag(&filter, &[" urgent ".to_string()], &[])
+            .await
+            .unwrap();
+        assert_eq!(updated, 1);
+        let task = service.find_task_by_id("task-1").await.unwrap();
+        assert_eq!(task.tags, vec!["urgent", "triage"]);
+        let task = service.find_task_by_id("task-2").await.unwrap();
+        assert_eq!(task.tags, vec!["urgent"]);
+        let task = service.find_task_by_id("task-3").await.unwrap();
+        assert!(task.tags.is_empty());
+
+        let updated = service
+            .bulk_tag(&filter, &["q3".to_string()], &["triage".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(updated, 2);
+        let task = service.find_task_by_id("task-1").await.unwrap();
+        assert_eq!(task.tags, vec!["urgent", "q3"]);
+
+        assert!(service.bulk_tag(&filter, &[], &[]).await.is_err());
+        assert!(
+            service
+                .bulk_tag(&filter, &["q3".to_string()], &["q3".to_string()])
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_undo_delete_restores_task() {
+        let tasks = vec![sample_task("task-1"), sample_task("task-2")];
+        let (_dir, service) = service_with_tasks(tasks).await;
+
+        assert!(service.delete_task("task-1", false).await.unwrap());
+        // A dry run changes nothing, so it leaves the delete as the step to undo
+        assert!(service.delete_task("task-2", true).await.unwrap());
+        assert!(service.find_task_by_id("task-1").await.is_err());
+
+        let restored = service.undo().await.unwrap();
+        assert_eq!(restored.tasks.len(), 2);
+        let task = service.find_task_by_id("task-1").await.unwrap();
+        assert_eq!(task.title, sample_task("task-1").title);
+
+        assert!(matches!(
+            service.undo().await,
+            Err(TaskError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_group_by_status() {
+        let mut completed = sample_task("task-2");
+        completed.status = TaskStatus::Completed;
+        let tasks = vec![sample_task("task-1"), completed, sample_task("task-3")];
+        let (_dir, service) = service_with_tasks(tasks).await;
+
+        let groups = service.group_by(GroupBy::Status).await.unwrap();
+        assert_eq!(
+            groups.keys().collect::<Vec<_>>(),
+            vec!["Completed", "Pending"]
+        );
+        let pending: Vec<&str> = groups["Pending"].iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(pending, vec!["task-1", "task-3"]);
+        assert_eq!(groups["Completed"][0].id, "task-2");
+    }
+
+    #[tokio::test]
+    async fn test_group_by_tag_lists_task_under_each_tag() {
+        let mut both = sample_task("both");
+        both.tags = vec!["api".to_string(), "backend".to_string()];
+        let mut api = sample_task("api-only");
+        api.tags = vec!["api".to_string()];
+        let untagged = sample_task("untagged");
+        let (_dir, service) = service_with_tasks(vec![both, api, untagged]).await;
+
+        let groups = service.group_by(GroupBy::Tag).await.unwrap();
+        let ids = |key: &str| -> Vec<String> { groups[key].iter().map(|t| t.id.clone()).collect() };
+        assert_eq!(ids("api"), vec!["both", "api-only"]);
+        assert_eq!(ids("backend"), vec!["both"]);
+        assert_eq!(ids(UNTAGGED_BUCKET), vec!["untagged"]);
+    }
+
+    #[tokio::test]
+    async fn test_daily_digest_sections() {
+        let now = Utc.with_ymd_and_hms(2024, 2, 1, 12, 0, 0).unwrap();
+        let task = |id: &str, due: Option<&str>, status: TaskStatus, priority: Priority| {
+            let mut task = sample_task(id);
+            task.due_date = due.map(str::to_string);
+            task.status = status;
+            task.priority = priority;
+            task
+        };
+        let tasks = vec![
+            task(
+                "overdue",
+                Some("2024-01-31T09:00:00Z"),
+                TaskStatus::Pending,
+                Priority::High,
+            ),
+            task(
+                "due-tonight",
+                Some("2024-02-01T23:00:00Z"),
+                TaskStatus::InProgress,
+                Priority::Low,
+            ),
+            task(
+                "due-tomorrow",
+                Some("2024-02-02T00:00:00Z"),
+                TaskStatus::Pending,
+                Priority::Medium,
+            ),
+            task(
+                "done-late",
+                Some("2024-01-30T09:00:00Z"),
+                TaskStatus::Completed,
+                Priority::Critical,
+            ),
+            task("working", None, TaskStatus::InProgress, Priority::Critical),
+        ];
+        let (_dir, service) = service_with_tasks(tasks).await;
+
+        let digest = service.daily_digest(now).await.unwrap();
+        let ids = |tasks: &[Task]| tasks.iter().map(|t| t.id.clone()).collect::<Vec<_>>();
+        assert_eq!(digest.date, "2024-02-01");
+        assert_eq!(ids(&digest.overdue), vec!["overdue"]);
+        assert_eq!(ids(&digest.due_today), vec!["due-tonight"]);
+        assert_eq!(ids(&digest.in_progress), vec!["working", "due-tonight"]);
+        assert_eq!(digest.open_by_priority[&Priority::Critical], 1);
+        assert_eq!(digest.open_by_priority[&Priority::High], 1);
+        assert_eq!(digest.open_by_priority[&Priority::Medium], 1);
+        assert_eq!(digest.open_by_priority[&Priority::Low], 1);
+    }
+
+    #[tokio::test]
+    async fn test_stale_tasks_older_than_threshold() {
+        let now = Utc.with_ymd_and_hms(2024, 2, 20, 12, 0, 0).unwrap();
+        let updated = |id: &str, updated_at: &str, status: TaskStatus| {
+            let mut task = sample_task(id);
+            task.updated_at = updated_at.to_string();
+            task.status = status;
+            task
+        };
+        let tasks = vec![
+            updated("ten-days", "2024-02-10T12:00:00Z", TaskStatus::InProgress),
+            updated("twenty-days", "2024-01-31T12:00:00Z", TaskStatus::Pending),
+            updated("yesterday", "2024-02-19T12:00:00Z", TaskStatus::Pending),
+            updated("done", "2024-01-01T12:00:00Z", TaskStatus::Completed),
+            updated("garbled", "last week", TaskStatus::Pending),
+        ];
+        let (_dir, service) = service_with_tasks(tasks).await;
+
+        let ids = |tasks: Vec<Task>| tasks.into_iter().map(|t| t.id).collect::<Vec<_>>();
+        assert_eq!(
+            ids(service.stale(now, 7).await.unwrap()),
+            vec!["twenty-days", "ten-days"]
+        );
+        assert_eq!(
+            ids(service.stale(now, 14).await.unwrap()),
+            vec!["twenty-days"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_due_within_window() {
+        let now = Utc.with_ymd_and_hms(2024, 2, 1, 12, 0, 0).unwrap();
+
+        let mut tomorrow = sample_task("tomorrow");
+        tomorrow.due_date = Some("2024-02-02T12:00:00Z".to_string());
+        let mut next_month = sample_task("next-month");
+        next_month.due_date = Some("2024-03-02T12:00:00Z".to_string());
+        let mut tomorrow_completed = sample_task("tomorrow-completed");
+        tomorrow_completed.due_date = Some("2024-02-02T12:00:00Z".to_string());
+        tomorrow_completed.status = TaskStatus::Completed;
+        let no_due_date = sample_task("no-due-date");
+
+        let (_dir, service) =
+            service_with_tasks(vec![tomorrow, next_month, tomorrow_completed, no_due_date]).await;
+
+        let due = service.due_within(now, 7).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].id, "tomorrow");
+    }
+
+    #[tokio::test]
+    async fn test_due_on_matches_the_utc_day() {
+        let due = |id: &str, due_date: &str| {
+            let mut task = sample_task(id);
+            task.due_date = Some(due_date.to_string());
+            task
+        };
+        let mut completed = due("completed", "2024-03-01T09:00:00Z");
+        completed.status = TaskStatus::Completed;
+        let tasks = vec![
+            due("late-evening", "2024-03-01T23:00:00Z"),
+            due("date-only", "2024-03-01"),
+            due("next-day", "2024-03-02T00:30:00Z"),
+            // 23:30 at UTC-2 is already 01:30 the next day in UTC
+            due("offset", "2024-03-01T23:30:00-02:00"),
+            due("day-before", "2024-02-29T23:59:59Z"),
+            completed,
+            sample_task("unscheduled"),
+        ];
+        let (_dir, service) = service_with_tasks(tasks).await;
+
+        let ids: Vec<String> = service
+            .due_on(NaiveDate::from_ymd_opt(2024, 3, 1).unwrap())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(ids, vec!["date-only", "completed", "late-evening"]);
+
+        let next_day = service
+            .due_on(NaiveDate::from_ymd_opt(2024, 3, 2).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(next_day.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_export_csv_quotes_descriptions() {
+        let mut task = sample_task("task-1");
+        task.description = "Buy milk, eggs".to_string();
+        task.tags = vec!["home".to_string(), "errands".to_string()];
+        let (_dir, service) = service_with_tasks(vec![task]).await;
+
+        let csv = service.export_csv().await.unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some(
+                "id,title,description,status,priority,assignee,due_date,tags,parent_id,depends_on,created_at,updated_at"
+            )
+        );
+        assert_eq!(
+            lines.next(),
+            Some(
+                "task-1,Task task-1,\"Buy milk, eggs\",Pending,Medium,,,home;errands,,,2024-01-15T09:00:00Z,2024-01-15T09:00:00Z"
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_markdown_table() {
+        let mut task = sample_task("task-1");
+        task.title = "Fix a | b".to_string();
+        let (_dir, service) = service_with_tasks(vec![task]).await;
+
+        let markdown = service.export_markdown(None).await.unwrap();
+        let mut lines = markdown.lines();
+        assert_eq!(
+            lines.next(),
+            Some("| ID | Title | Status | Priority | Assignee | Due Date | Tags |")
+        );
+        assert_eq!(
+            lines.nth(1),
+            Some("| task-1 | Fix a \\| b | Pending | Medium |  |  |  |")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_markdown_grouped_by_status() {
+        let mut completed = sample_task("task-2");
+        completed.status = TaskStatus::Completed;
+        let tasks = vec![sample_task("task-1"), completed, sample_task("task-3")];
+        let (_dir, service) = service_with_tasks(tasks).await;
+
+        let markdown = service.export_markdown(Some("status")).await.unwrap();
+        let headings: Vec<&str> = markdown.lines().filter(|l| l.starts_with("## ")).collect();
+        assert_eq!(headings, vec!["## Status: Pending", "## Status: Completed"]);
+
+        let err = service.export_markdown(Some("due_date")).await.unwrap_err();
+        assert!(err.to_string().contains("Invalid group_by"));
+    }
+
+    #[tokio::test]
+    async fn test_import_csv_merge_updates_existing() {
+        let mut existing = sample_task("task-1");
+        existing.comments = vec![TaskComment {
+            author: None,
+            body: "Keep me".to_string(),
+            created_at: "2024-01-16T10:00:00Z".to_string(),
+        }];
+        let (_dir, service) = service_with_tasks(vec![existing, sample_task("task-2")]).await;
+
+        let csv = "id,title,status,priority,tags\n\
+                   task-1,Renamed,In Progress,High,a;b\n\
+                   task-3,New task,pending,low,\n";
+        let summary = service.import_csv(csv, ImportMode::Merge).await.unwrap();
+        assert_eq!(
+            summary,
+            ImportSummary {
+                created: 1,
+                updated: 1,
+                total: 3
+            }
+        );
+
+        let task = service.find_task_by_id("task-1").await.unwrap();
+        assert_eq!(task.title, "Renamed");
+        assert_eq!(task.status, TaskStatus::InProgress);
+        assert_eq!(task.tags, vec!["a", "b"]);
+        assert_eq!(task.comments.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fixed_clock_stamps_tasks() {
+        let instant = Utc.with_ymd_and_hms(2024, 3, 1, 12, 30, 0).unwrap();
+        let (_dir, service) = service_with_tasks(vec![sample_task("task-1")]).await;
+        let service = service.with_clock(FixedClock(instant));
+
+        let csv = "id,title,status,priority\ntask-2,Imported,pending,low\n";
+        service.import_csv(csv, ImportMode::Merge).await.unwrap();
+        let imported = service.find_task_by_id("task-2").await.unwrap();
+        assert_eq!(imported.created_at, "2024-03-01T12:30:00Z");
+        assert_eq!(imported.created_datetime(), Some(instant));
+
+        let update = TaskUpdate {
+            title: Some("Renamed".to_string()),
+            ..TaskUpdate::default()
+        };
+        let updated = service.update_task("task-1", &update, false).await.unwrap();
+        assert_eq!(updated.updated_at, "2024-03-01T12:30:00Z");
+
+        let service = service.with_timestamp_format(TimestampFormat::Rfc3339Millis);
+        assert_eq!(service.timestamp(), "2024-03-01T12:30:00.000Z");
+    }
+
+    #[tokio::test]
+    async fn test_import_csv_replace_wipes_prior_tasks() {
+        let (_dir, service) =
+            service_with_tasks(vec![sample_task("task-1"), sample_task("task-2")]).await;
+
+        let csv = "id,title,status,priority\ntask-9,Only task,pending,medium\n";
+        service.import_csv(csv, ImportMode::Replace).await.unwrap();
+
+        let tasks = service.load_tasks().await.unwrap().tasks;
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].id, "task-9");
+    }
+
+    #[tokio::test]
+    async fn test_import_csv_reports_missing_fields() {
+        let (_dir, service) = service_with_tasks(vec![sample_task("task-1")]).await;
+
+        let csv = "id,title,status,priority\ntask-2,,pending,medium\ntask-3,Ok,,medium\n";
+        let err = service
+            .import_csv(csv, ImportMode::Replace)
+            .await
+            .unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("Row 2: missing title"));
+        assert!(message.contains("Row 3: missing status"));
+
+        assert_eq!(service.load_tasks().await.unwrap().tasks.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_import_csv_rejects_dependency_cycle() {
+        let (_dir, service) = service_with_tasks(vec![sample_task("task-1")]).await;
+
+        let csv = "id,title,status,priority,depends_on\n\
+                   task-a,First,pending,medium,task-b\n\
+                   task-b,Second,pending,medium,task-a\n";
+        for mode in [ImportMode::Merge, ImportMode::Replace] {
+            let err = service.import_csv(csv, mode).await.unwrap_err();
+            assert!(matches!(err, TaskError::Validation(_)));
+            assert!(err.to_string().contains("Dependency cycle"));
+        }
+
+        let tasks = service.load_tasks().await.unwrap().tasks;
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].id, "task-1");
+    }
+
+    #[tokio::test]
+    async fn test_export_ndjson() {
+        let mut tagged = sample_task("task-2");
+        tagged.tags = vec!["multi\nline".to_string()];
+        let tasks = vec![sample_task("task-1"), tagged, sample_task("task-3")];
+        let (_dir, service) = service_with_tasks(tasks.clone()).await;
+
+        let mut output = Vec::new();
+        let written = service.export_ndjson(&mut output).await.unwrap();
+        assert_eq!(written, 3);
+
+        let text = String::from_utf8(output).unwrap();
+        let parsed: Vec<Task> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("Each line should be a task"))
+            .collect();
+        assert_eq!(
+            serde_json::to_value(&parsed).unwrap(),
+            serde_json::to_value(&tasks).unwrap()
+        );
+        assert!(text.ends_with('\n'));
+    }
+
+    #[tokio::test]
+    async fn test_export_ical() {
+        let mut due = sample_task("task-1");
+        due.title = "Ship release, finally".to_string();
+        due.due_date = Some("2024-01-20T17:00:00Z".to_string());
+        due.priority = Priority::High;
+        let (_dir, service) = service_with_tasks(vec![due, sample_task("task-2")]).await;
+
+        let ical = service.export_ical().await.unwrap();
+        assert!(ical.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert_eq!(ical.matches("BEGIN:VTODO").count(), 1);
+        assert!(ical.contains("\r\nUID:task-1\r\n"));
+        assert!(ical.contains("\r\nSUMMARY:Ship release\\, finally\r\n"));
+        assert!(ical.contains("\r\nDUE:20240120T170000Z\r\n"));
+        assert!(ical.contains("\r\nPRIORITY:3\r\n"));
+        assert!(ical.ends_with("END:VCALENDAR\r\n"));
+    }
+
+    #[test]
+    fn test_fold_ical_line() {
+        let line = format!("DESCRIPTION:{}", "x".repeat(100));
+        let folded = fold_ical_line(&line);
+        assert!(folded.split("\r\n").all(|part| part.len() <= 75));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+
+    #[test]
+    fn test_statistics_completion_rate() {
+        let now = Utc.with_ymd_and_hms(2024, 2, 1, 12, 0, 0).unwrap();
+        let mut completed = sample_task("task-1");
+        completed.status = TaskStatus::Completed;
+        completed.assignee = Some("alice.smith".to_string());
+        let mut overdue = sample_task("task-2");
+        overdue.due_date = Some("2024-01-20T17:00:00Z".to_string());
+
+        let stats = TaskStatistics::from_tasks_at(&[completed, overdue], now);
+        assert_eq!(stats.total_tasks, 2);
+        assert_eq!(stats.completion_rate, 0.5);
+        assert_eq!(stats.overdue_count, 1);
+        assert_eq!(stats.unassigned_count, 1);
+        assert!(stats.format_stats().contains("**Completion Rate:** 50.0%"));
+    }
+
+    #[test]
+    fn test_statistics_status_priority_matrix() {
+        let task = |id: &str, status: TaskStatus, priority: Priority| {
+            let mut task = sample_task(id);
+            task.status = status;
+            task.priority = priority;
+            task
+        };
+        let tasks = vec![
+            task("task-1", TaskStatus::InProgress, Priority::High),
+            task("task-2", TaskStatus::InProgress, Priority::High),
+            task("task-3", TaskStatus::InProgress, Priority::Low),
+            task("task-4", TaskStatus::Pending, Priority::High),
+            task("task-5", TaskStatus::Completed, Priority::Critical),
+        ];
+
+        let stats = TaskStatistics::from_tasks(&tasks);
+        let cell = |status: TaskStatus, priority: Priority| {
+            stats
+                .matrix
+                .get(&(status.to_string(), priority.to_string()))
+                .copied()
+        };
+        assert_eq!(cell(TaskStatus::InProgress, Priority::High), Some(2));
+        assert_eq!(cell(TaskStatus::Pending, Priority::High), Some(1));
+        assert_eq!(cell(TaskStatus::Pending, Priority::Low), None);
+
+        let rendered = stats.format_stats();
+        let table: Vec<&str> = rendered
+            .split("### By Status and Priority:\n")
+            .nth(1)
+            .unwrap()
+            .lines()
+            .take_while(|line| line.starts_with('|'))
+            .collect();
+        assert_eq!(
+            table,
+            vec![
+                "| Status | Low | Medium | High | Critical |",
+                "|--------|---:|---:|---:|---:|",
+                "| Pending | 0 | 0 | 1 | 0 |",
+                "| In Progress | 1 | 0 | 2 | 0 |",
+                "| Completed | 0 | 0 | 0 | 1 |",
+                "| Cancelled | 0 | 0 | 0 | 0 |",
+            ]
+        );
+
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["matrix"]["In Progress"]["High"], 2);
+        assert_eq!(json["matrix"]["Completed"]["Critical"], 1);
+    }
+
+    #[test]
+    fn test_statistics_without_tasks() {
+        let stats = TaskStatistics::from_tasks(&[]);
+        assert_eq!(stats.total_tasks, 0);
+        assert_eq!(stats.completion_rate, 0.0);
+    }
+
+    #[test]
+    fn test_statistics_assignee_counts() {
+        let mut first = sample_task("task-1");
+        first.assignee = Some("alice.smith".to_string());
+        let mut second = sample_task("task-2");
+        second.assignee = Some("alice.smith".to_string());
+        let mut third = sample_task("task-3");
+        third.assignee = Some("bob.johnson".to_string());
+        let unassigned = sample_task("task-4");
+
+        let stats = TaskStatistics::from_tasks(&[first, second, third, unassigned]);
+        assert_eq!(stats.assignee_counts.len(), 3);
+        assert_eq!(stats.assignee_counts["alice.smith"], 2);
+        assert_eq!(stats.assignee_counts["bob.johnson"], 1);
+        assert_eq!(stats.assignee_counts[UNASSIGNED_BUCKET], 1);
+        assert!(stats.format_stats().contains("### By Assignee:"));
+    }
+
+    #[test]
+    fn test_statistics_tag_counts() {
+        let mut first = sample_task("task-1");
+        first.tags = vec![
+            "backend".to_string(),
+            "security".to_string(),
+            "backend".to_string(),
+        ];
+        let mut second = sample_task("task-2");
+        second.tags = vec!["backend".to_string(), "frontend".to_string()];
+
+        let stats = TaskStatistics::from_tasks(&[first, second]);
+        assert_eq!(stats.tag_counts["backend"], 2);
+        assert_eq!(stats.tag_counts["security"], 1);
+        assert_eq!(stats.tag_counts["frontend"], 1);
+        assert!(stats.format_stats().contains("### By Tag:"));
+    }
+
+    #[tokio::test]
+    async fn test_strict_validation_toggle() {
+        let (_dir, service) =
+            service_with_tasks(vec![sample_task("task-1"), sample_task("task-1")]).await;
+
+        let lenient = service.clone().with_strict_validation(false);
+        assert_eq!(lenient.load_tasks().await.unwrap().tasks.len(), 2);
+
+        let strict = service.with_strict_validation(true);
+        let err = strict.load_tasks().await.unwrap_err();
+        assert!(err.to_string().contains("Task task-1: duplicate id"));
+    }
+
+    #[tokio::test]
+    async fn test_find_task_error_variants() {
+        let (dir, service) = service_with_tasks(vec![sample_task("task-1")]).await;
+
+        let err = service.find_task_by_id("missing").await.unwrap_err();
+        assert!(matches!(err, TaskError::NotFound(ref id) if id == "missing"));
+
+        std::fs::write(dir.path().join("tasks.json"), "not json").unwrap();
+        let err = service.find_task_by_id("task-1").await.unwrap_err();
+        assert!(matches!(
+            err,
+            TaskError::Storage(StorageError::InvalidJson { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_find_tasks_by_ids() {
+        let (_dir, service) =
+            service_with_tasks(vec![sample_task("task-1"), sample_task("task-2")]).await;
+
+        let ids = vec![
+            "task-2".to_string(),
+            "missing".to_string(),
+            "task-1".to_string(),
+        ];
+        let (found, missing) = service.find_tasks_by_ids(&ids).await.unwrap();
+
+        let found_ids: Vec<_> = found.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(found_ids, vec!["task-2", "task-1"]);
+        assert_eq!(missing, vec!["missing".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_children_of_nested_tasks() {
+        let root = sample_task("root");
+        let mut child = sample_task("child");
+        child.parent_id = Some("root".to_string());
+        let mut grandchild = sample_task("grandchild");
+        grandchild.parent_id = Some("child".to_string());
+        let mut own_parent = sample_task("loop");
+        own_parent.parent_id = Some("loop".to_string());
+
+        let (_dir, service) = service_with_tasks(vec![root, child, grandchild, own_parent]).await;
+
+        let children = service.children_of("root").await.unwrap();
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0].id, "child");
+
+        let grandchildren = service.children_of("child").await.unwrap();
+        assert_eq!(grandchildren.len(), 1);
+        assert_eq!(grandchildren[0].id, "grandchild");
+
+        assert!(service.children_of("loop").await.unwrap().is_empty());
+        assert!(matches!(
+            service.children_of("missing").await,
+            Err(TaskError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_blocked_by_unfinished_dependency() {
+        let mut a = sample_task("a");
+        a.depends_on = vec!["b".to_string()];
+        let b = sample_task("b");
+        let (_dir, service) = service_with_tasks(vec![a.clone(), b.clone()]).await;
+
+        assert!(service.is_blocked(&a, &[a.clone(), b.clone()]));
+        let blocked = service.blocked_tasks().await.unwrap();
+        assert_eq!(blocked.len(), 1);
+        assert_eq!(blocked[0].id, "a");
+
+        service
+            .set_status("b", TaskStatus::Completed, false)
+            .await
+            .unwrap();
+        assert!(service.blocked_tasks().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_next_task_breaks_priority_ties_by_due_date() {
+        let mut later = sample_task("later");
+        later.priority = Priority::High;
+        later.due_date = Some("2024-03-01T17:00:00Z".to_string());
+        let mut sooner = sample_task("sooner");
+        sooner.priority = Priority::High;
+        sooner.due_date = Some("2024-02-01T17:00:00Z".to_string());
+        let mut undated = sample_task("undated");
+        undated.priority = Priority::High;
+        let low = sample_task("low");
+        let (_dir, service) = service_with_tasks(vec![later, undated, low, sooner]).await;
+
+        let next = service.next_task().await.unwrap().unwrap();
+        assert_eq!(next.id, "sooner");
+    }
+
+    #[tokio::test]
+    async fn test_next_task_skips_completed_and_blocked() {
+        let mut done = sample_task("done");
+        done.priority = Priority::Critical;
+        done.status = TaskStatus::Completed;
+        let mut blocked = sample_task("blocked");
+        blocked.priority = Priority::High;
+        blocked.depends_on = vec!["open".to_string()];
+        let open = sample_task("open");
+        let (_dir, service) = service_with_tasks(vec![done, blocked, open]).await;
+
+        let next = service.next_task().await.unwrap().unwrap();
+        assert_eq!(next.id, "open");
+
+        service
+            .set_status("open", TaskStatus::Completed, false)
+            .await
+            .unwrap();
+        let next = service.next_task().await.unwrap().unwrap();
+        assert_eq!(next.id, "blocked");
+
+        service
+            .set_status("blocked", TaskStatus::Cancelled, false)
+            .await
+            .unwrap();
+        assert!(service.next_task().await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_validate_dependencies_flags_cycle() {
+        let mut a = sample_task("a");
+        a.depends_on = vec!["b".to_string()];
+        let mut b = sample_task("b");
+        b.depends_on = vec!["a".to_string()];
+
+        let err = service().validate_dependencies(&[a, b]).unwrap_err();
+        assert!(err.to_string().contains("Dependency cycle: a -> b -> a"));
+    }
+
+    #[tokio::test]
+    async fn test_complete_weekly_task_reschedules() {
+        let mut task = sample_task("task-001");
+        task.recurrence = Some("weekly".to_string());
+        task.due_date = Some("2024-01-20T17:00:00Z".to_string());
+        let (_dir, service) = service_with_tasks(vec![task]).await;
+        let service = service.with_clock(FixedClock(
+            Utc.with_ymd_and_hms(2024, 1, 19, 9, 0, 0).unwrap(),
+        ));
+
+        let (previous_status, next_task) = service
+            .complete_and_reschedule("task-001", false)
+            .await
+            .unwrap();
+        assert_eq!(previous_status, TaskStatus::Pending);
+        let next_task = next_task.expect("Weekly task should be rescheduled");
+        assert!(uuid::Uuid::parse_str(&next_task.id).is_ok());
+        assert_eq!(next_task.status, TaskStatus::Pending);
+        assert_eq!(next_task.due_date.as_deref(), Some("2024-01-27T17:00:00Z"));
+
+        let original = service.find_task_by_id("task-001").await.unwrap();
+        assert_eq!(original.status, TaskStatus::Completed);
+        assert_eq!(original.recurrence, None);
+
+        let (_, next_task) = service
+            .complete_and_reschedule("task-001", false)
+            .await
+            .unwrap();
+        assert!(next_task.is_none());
+        assert_eq!(service.load_tasks().await.unwrap().tasks.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_late_recurring_task_is_not_due_before_creation() {
+        let mut task = sample_task("task-001");
+        task.recurrence = Some("weekly".to_string());
+        task.due_date = Some("2024-01-20T17:00:00Z".to_string());
+        let (_dir, service) = service_with_tasks(vec![task]).await;
+        let service = service
+            .with_clock(FixedClock(
+                Utc.with_ymd_and_hms(2024, 2, 5, 9, 0, 0).unwrap(),
+            ))
+            .with_strict_validation(true);
+
+        let (_, next_task) = service
+            .complete_and_reschedule("task-001", false)
+            .await
+            .unwrap();
+        let next_task = next_task.expect("Weekly task should be rescheduled");
+        assert_eq!(next_task.created_at, "2024-02-05T09:00:00Z");
+        assert_eq!(next_task.due_date.as_deref(), Some("2024-02-10T17:00:00Z"));
+        assert_eq!(service.load_tasks().await.unwrap().tasks.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_due_date_before_creation_is_rejected() {
+        let (_dir, service) = service_with_tasks(vec![sample_task("task-1")]).await;
+        let service = service.with_strict_validation(true);
+
+        let update = TaskUpdate {
+            due_date: Some(Some("2024-01-14T09:00:00Z".to_string())),
+            ..TaskUpdate::default()
+        };
+        let err = service
+            .update_task("task-1", &update, false)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TaskError::Validation(_)));
+        assert!(err.to_string().contains("is before created_at"));
+
+        let now = Utc.with_ymd_and_hms(2024, 1, 20, 9, 0, 0).unwrap();
+        let err = service
+            .set_due_date("task-1", "2024-01-10", now)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TaskError::Validation(_)));
+
+        // Nothing was saved, so the file still loads in strict mode
+        let task = service.find_task_by_id("task-1").await.unwrap();
+        assert_eq!(task.due_date, None);
+        let task = service
+            .set_due_date("task-1", "2024-01-16", now)
+            .await
+            .unwrap();
+        assert_eq!(task.due_date.as_deref(), Some("2024-01-16T23:59:59Z"));
+    }
+
+    #[tokio::test]
+    async fn test_due_date_before_creation_is_allowed_when_lenient() {
+        let (_dir, service) = service_with_tasks(vec![sample_task("task-1")]).await;
+
+        let now = Utc.with_ymd_and_hms(2024, 1, 20, 9, 0, 0).unwrap();
+        let task = service
+            .set_due_date("task-1", "2024-01-10", now)
+            .await
+            .unwrap();
+        assert_eq!(task.due_date.as_deref(), Some("2024-01-10T23:59:59Z"));
+
+        let update = TaskUpdate {
+            due_date: Some(Some("2024-01-14T09:00:00Z".to_string())),
+            ..TaskUpdate::default()
+        };
+        let task = service.update_task("task-1", &update, false).await.unwrap();
+        assert_eq!(task.due_date.as_deref(), Some("2024-01-14T09:00:00Z"));
+        assert_eq!(task.created_at, "2024-01-15T09:00:00Z");
+    }
+
+    #[tokio::test]
+    async fn test_lenient_load_warns_about_due_before_creation() {
+        let mut task = sample_task("task-1");
+        task.due_date = Some("2024-01-14T09:00:00Z".to_string());
+        let (_dir, service) = service_with_tasks(vec![task]).await;
+
+        assert_eq!(service.load_tasks().await.unwrap().tasks.len(), 1);
+        let err = service
+            .with_strict_validation(true)
+            .load_tasks()
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("is before created_at"));
+    }
+
+    #[tokio::test]
+    async fn test_import_csv_overdue_task_without_created_at() {
+        let (_dir, service) = service_with_tasks(vec![]).await;
+        let service = service.with_clock(FixedClock(
+            Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap(),
+        ));
+
+        let csv = "id,title,status,priority,due_date
+                   task-1,Overdue,pending,high,2024-02-01T17:00:00Z
+";
+        let err = service
+            .clone()
+            .with_strict_validation(true)
+            .import_csv(csv, ImportMode::Merge)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("is before created_at"));
+        assert!(service.load_tasks().await.unwrap().tasks.is_empty());
+
+        service.import_csv(csv, ImportMode::Merge).await.unwrap();
+        let overdue = service.find_task_by_id("task-1").await.unwrap();
+        assert_eq!(overdue.created_at, "2024-03-01T12:00:00Z");
+    }
+
+    #[tokio::test]
+    async fn test_comment_persists_across_reload() {
+        let (dir, service) = service_with_tasks(vec![sample_task("task-1")]).await;
+        let comment = TaskComment {
+            author: Some("alice".to_string()),
+            body: "Waiting on design review".to_string(),
+            created_at: "2024-01-16T10:00:00Z".to_string(),
+        };
+
+        service
+            .add_comment("task-1", comment.clone())
+            .await
+            .unwrap();
+
+        let reloaded = TaskService::new(TaskStorage::new(dir.path().join("tasks.json")));
+        let task = reloaded.find_task_by_id("task-1").await.unwrap();
+        assert_eq!(task.comments, vec![comment]);
+    }
+
+    #[tokio::test]
+    async fn test_update_task_keeps_comments() {
+        let mut task = sample_task("task-1");
+        task.comments = vec![TaskComment {
+            author: None,
+            body: "First note".to_string(),
+            created_at: "2024-01-16T10:00:00Z".to_string(),
+        }];
+        let (_dir, service) = service_with_tasks(vec![task]).await;
+
+        let update = TaskUpdate {
+            title: Some("Renamed".to_string()),
+            ..TaskUpdate::default()
+        };
+        let updated = service.update_task("task-1", &update, false).await.unwrap();
+        assert_eq!(updated.title, "Renamed");
+        assert_eq!(updated.comments.len(), 1);
+
+        let task = service.find_task_by_id("task-1").await.unwrap();
+        assert_eq!(task.comments[0].body, "First note");
+    }
+
+    #[tokio::test]
+    async fn test_update_task_records_history() {
+        let (_dir, service) = service_with_tasks(vec![sample_task("task-1")]).await;
+        let now = Utc.with_ymd_and_hms(2024, 2, 1, 12, 0, 0).unwrap();
+        let service = service.with_clock(FixedClock(now));
+
+        let update = TaskUpdate {
+            priority: Some(Priority::High),
+            ..TaskUpdate::default()
+        };
+        let updated = service.update_task("task-1", &update, false).await.unwrap();
+        assert_eq!(
+            updated.history,
+            vec![TaskChange {
+                field: "priority".to_string(),
+                old: serde_json::json!("medium"),
+                new: serde_json::json!("high"),
+                at: "2024-02-01T12:00:00Z".to_string(),
+            }]
+        );
+
+        // Later edits append to the history rather than replacing it
+        let rename = TaskUpdate {
+            title: Some("Renamed".to_string()),
+            ..TaskUpdate::default()
+        };
+        service.update_task("task-1", &rename, false).await.unwrap();
+        service
+            .set_status("task-1", TaskStatus::InProgress, false)
+            .await
+            .unwrap();
+        let task = service.find_task_by_id("task-1").await.unwrap();
+        let fields: Vec<&str> = task.history.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, vec!["priority", "title", "status"]);
+        assert_eq!(task.history[2].old, serde_json::json!("pending"));
+        assert_eq!(task.history[2].new, serde_json::json!("in_progress"));
+
+        // Setting a field to the value it already has isn't an edit
+        service
+            .set_status("task-1", TaskStatus::InProgress, false)
+            .await
+            .unwrap();
+        let task = service.find_task_by_id("task-1").await.unwrap();
+        assert_eq!(task.history.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_update_task_dry_run_does_not_save() {
+        let (dir, service) = service_with_tasks(vec![sample_task("task-1")]).await;
+        let path = dir.path().join("tasks.json");
+        let before = std::fs::read_to_string(&path).unwrap();
+
+        let update = TaskUpdate {
+            priority: Some(Priority::Critical),
+            ..TaskUpdate::default()
+        };
+        let preview = service.update_task("task-1", &update, true).await.unwrap();
+        assert_eq!(preview.priority, Priority::Critical);
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), before);
+        let stored = service.find_task_by_id("task-1").await.unwrap();
+        assert_eq!(stored.priority, Priority::Medium);
+
+        assert!(service.delete_task("task-1", true).await.unwrap());
+        assert!(!service.delete_task("missing", true).await.unwrap());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), before);
+    }
+
+    #[tokio::test]
+    async fn test_deduplicate_ids() {
+        let mut copy = sample_task("task-001");
+        copy.title = "Hand-edited copy".to_string();
+        let tasks = vec![
+            sample_task("task-001"),
+            sample_task("task-002"),
+            copy,
+            sample_task("task-002"),
+        ];
+        let (_dir, service) = service_with_tasks(tasks).await;
+
+        assert_eq!(
+            service.find_duplicate_ids().await.unwrap(),
+            vec!["task-001", "task-002"]
+        );
+
+        let renamed = service.deduplicate_ids().await.unwrap();
+        let old_ids: Vec<&str> = renamed.iter().map(|(old, _)| old.as_str()).collect();
+        assert_eq!(old_ids, vec!["task-001", "task-002"]);
+        for (_, new_id) in &renamed {
+            assert!(
+                uuid::Uuid::parse_str(new_id).is_ok(),
+                "not a UUID: {}",
+                new_id
+            );
+        }
+        assert!(service.find_duplicate_ids().await.unwrap().is_empty());
+
+        let tasks = service.load_tasks().await.unwrap().tasks;
+        let ids: HashSet<&str> = tasks.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids.len(), 4);
+        assert_eq!(tasks[0].title, "Task task-001");
+        assert_eq!(tasks[2].id, renamed[0].1);
+        assert_eq!(tasks[2].title, "Hand-edited copy");
+
+        let (_dir, service) =
+            service_with_tasks(vec![sample_task("task-1"), sample_task("task-1")]).await;
+        let service = service.with_id_strategy(IdStrategy::Slug);
+        let renamed = service.deduplicate_ids().await.unwrap();
+        assert_eq!(renamed[0].1, "task-task-1");
+
+        assert!(service.deduplicate_ids().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_archive_completed() {
+        let mut done = sample_task("task-1");
+        done.status = TaskStatus::Completed;
+        let mut dropped = sample_task("task-2");
+        dropped.status = TaskStatus::Cancelled;
+        let mut also_done = sample_task("task-4");
+        also_done.status = TaskStatus::Completed;
+        let (dir, service) =
+            service_with_tasks(vec![done, dropped, sample_task("task-3"), also_done]).await;
+        let archive = TaskStorage::new(dir.path().join("tasks.archive.json"));
+        let service = service.with_archive(archive.clone());
+
+        assert_eq!(service.archive_completed(false).await.unwrap(), 2);
+        let active = service.load_tasks().await.unwrap().tasks;
+        let ids: Vec<&str> = active.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec!["task-2", "task-3"]);
+        assert_eq!(archive.load_tasks().await.unwrap().tasks.len(), 2);
+
+        assert_eq!(service.archive_completed(true).await.unwrap(), 1);
+        assert_eq!(service.load_tasks().await.unwrap().tasks.len(), 1);
+        assert_eq!(archive.load_tasks().await.unwrap().tasks.len(), 3);
+
+        assert_eq!(service.archive_completed(true).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_restore_task_round_trip() {
+        let mut done = sample_task("task-1");
+        done.status = TaskStatus::Completed;
+        let (dir, service) = service_with_tasks(vec![done, sample_task("task-2")]).await;
+        let archive = TaskStorage::new(dir.path().join("tasks.archive.json"));
+        let service = service.with_archive(archive.clone());
+
+        service.archive_completed(false).await.unwrap();
+        assert!(service.find_task_by_id("task-1").await.is_err());
+
+        let restored = service.restore_task("task-1", false).await.unwrap();
+        assert_eq!(restored.status, TaskStatus::Pending);
+        let task = service.find_task_by_id("task-1").await.unwrap();
+        assert_eq!(task.status, TaskStatus::Pending);
+        assert!(archive.load_tasks().await.unwrap().tasks.is_empty());
+
+        let err = service.restore_task("task-1", false).await.unwrap_err();
+        assert!(matches!(err, TaskError::NotFound(_)));
+    }
+
+    #[tokio::test]
+    async fn test_restore_task_refuses_id_clash() {
+        let mut done = sample_task("task-1");
+        done.status = TaskStatus::Completed;
+        let (dir, service) = service_with_tasks(vec![sample_task("task-1")]).await;
+        let archive = TaskStorage::new(dir.path().join("tasks.archive.json"));
+        archive.upsert_task(&done).await.unwrap();
+        let service = service.with_archive(archive.clone());
+
+        let err = service.restore_task("task-1", true).await.unwrap_err();
+        assert!(err.to_string().contains("already exists"));
+        assert_eq!(archive.load_tasks().await.unwrap().tasks.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_archive_completed_requires_archive() {
+        let (_dir, service) = service_with_tasks(vec![sample_task("task-1")]).await;
+        let err = service.archive_completed(false).await.unwrap_err();
+        assert!(matches!(err, TaskError::Validation(_)));
+    }
+
+    #[tokio::test]
+    async fn test_strict_transitions() {
+        let mut cancelled = sample_task("task-1");
+        cancelled.status = TaskStatus::Cancelled;
+        let (_dir, service) = service_with_tasks(vec![cancelled]).await;
+        let service = service.with_strict_transitions(true);
+
+        let err = service
+            .set_status("task-1", TaskStatus::InProgress, false)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TaskError::Validation(_)));
+        assert!(err.to_string().contains("from Cancelled to In Progress"));
+        assert!(
+            service
+                .complete_and_reschedule("task-1", false)
+                .await
+                .is_err()
+        );
+        let update = TaskUpdate {
+            status: Some(TaskStatus::Completed),
+            ..TaskUpdate::default()
+        };
+        assert!(service.update_task("task-1", &update, false).await.is_err());
+        assert_eq!(
+            service.find_task_by_id("task-1").await.unwrap().status,
+            TaskStatus::Cancelled
+        );
+
+        let previous = service
+            .set_status("task-1", TaskStatus::InProgress, true)
+            .await
+            .unwrap();
+        assert_eq!(previous, TaskStatus::Cancelled);
+        assert_eq!(
+            service.find_task_by_id("task-1").await.unwrap().status,
+            TaskStatus::InProgress
+        );
+    }
+
+    #[tokio::test]
+    async fn test_transitions_unchecked_by_default() {
+        let mut completed = sample_task("task-1");
+        completed.status = TaskStatus::Completed;
+        let (_dir, service) = service_with_tasks(vec![completed]).await;
+
+        service
+            .set_status("task-1", TaskStatus::InProgress, false)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_health_report() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tasks.json");
+        let service = TaskService::new(TaskStorage::new(path.clone()));
+
+        let report = service.health().await.unwrap();
+        assert!(!report.file_exists);
+        assert_eq!(report.task_count, 0);
+        assert_eq!(report.last_modified, None);
+
+        let mut collection = TaskCollection::new();
+        collection.tasks = sample_tasks(3);
+        service.save_tasks(&collection).await.unwrap();
+
+        let report = service.health().await.unwrap();
+        assert_eq!(report.file_path, path);
+        assert!(report.file_exists);
+        assert_eq!(report.task_count, 3);
+        assert!(report.last_modified.is_some());
+        assert_eq!(report.schema_version, crate::models::SCHEMA_VERSION);
+    }
+
+    #[tokio::test]
+    async fn test_update_task_enforces_limits() {
+        let (_dir, service) = service_with_tasks(vec![sample_task("task-1")]).await;
+        let service = service.with_limits(TaskLimits {
+            max_title_length: 20,
+            max_description_length: 100,
+            max_tags: 2,
+        });
+
+        let update = TaskUpdate {
+            title: Some("x".repeat(21)),
+            ..TaskUpdate::default()
+        };
+        let err = service
+            .update_task("task-1", &update, false)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TaskError::Validation(_)));
+        assert!(err.to_string().starts_with("title is too long"));
+
+        let update = TaskUpdate {
+            tags: Some(vec!["a".to_string(), "b".to_string(), "c".to_string()]),
+            ..TaskUpdate::default()
+        };
+        let err = service
+            .update_task("task-1", &update, false)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().starts_with("tags has too many entries"));
+
+        service.add_tag("task-1", "a").await.unwrap();
+        service.add_tag("task-1", "b").await.unwrap();
+        assert!(service.add_tag("task-1", "c").await.is_err());
+
+        let task = service.find_task_by_id("task-1").await.unwrap();
+        assert_eq!(task.title, "Task task-1");
+        assert_eq!(task.tags, vec!["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_update_task_rejects_empty_update() {
+        let (_dir, service) = service_with_tasks(vec![sample_task("task-1")]).await;
+
+        let err = service
+            .update_task("task-1", &TaskUpdate::default(), false)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TaskError::Validation(_)));
+    }
+
+    #[tokio::test]
+    async fn test_set_metadata_keeps_other_keys() {
+        let (_dir, service) = service_with_tasks(vec![sample_task("task-1")]).await;
+
+        service
+            .set_metadata("task-1", "points", serde_json::json!(3))
+            .await
+            .unwrap();
+        service
+            .set_metadata("task-1", "ticket", serde_json::json!("PROJ-42"))
+            .await
+            .unwrap();
+        let task = service
+            .set_metadata("task-1", "points", serde_json::json!(5))
+            .await
+            .unwrap();
+
+        assert_eq!(task.metadata.len(), 2);
+        assert_eq!(task.metadata["points"], 5);
+        assert_eq!(task.metadata["ticket"], "PROJ-42");
+    }
+
+    #[tokio::test]
+    async fn test_update_task_merges_metadata() {
+        let mut task = sample_task("task-1");
+        task.metadata
+            .insert("points".to_string(), serde_json::json!(3));
+        task.metadata
+            .insert("ticket".to_string(), serde_json::json!("PROJ-42"));
+        let (_dir, service) = service_with_tasks(vec![task]).await;
+
+        let update = TaskUpdate::from_arguments(&args(serde_json::json!({
+            "id": "task-1",
+            "metadata": { "points": 8, "ticket": null, "url": "https://example.com" }
+        })))
+        .unwrap();
+        let task = service.update_task("task-1", &update, false).await.unwrap();
+
+        assert_eq!(task.metadata.len(), 2);
+        assert_eq!(task.metadata["points"], 8);
+        assert_eq!(task.metadata["url"], "https://example.com");
+        assert!(!task.metadata.contains_key("ticket"));
     }
 }
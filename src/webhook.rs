@@ -0,0 +1,96 @@
+use tracing::warn;
+
+use crate::event_log::TaskEvent;
+
+/// Sends each [`TaskEvent`] as a JSON POST to a configured URL
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl Webhook {
+    /// Create a webhook posting to `url`
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// URL the events are posted to
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Post `events` one by one in the background and return right away
+    ///
+    /// Delivery is best effort: a request that fails or gets a non-success status is
+    /// logged and not retried. Must be called from within a Tokio runtime.
+    pub fn notify(&self, events: Vec<TaskEvent>) {
+        if events.is_empty() {
+            return;
+        }
+
+        let webhook = self.clone();
+        tokio::spawn(async move {
+            for event in &events {
+                let result = webhook
+                    .client
+                    .post(&webhook.url)
+                    .json(event)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status());
+                if let Err(e) = result {
+                    warn!(
+                        "Failed to post {:?} event for task {} to {}: {}",
+                        event.operation, event.task_id, webhook.url, e
+                    );
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{MemoryStorage, TaskStorage};
+    use crate::task_service::{NewTask, TaskService};
+    use std::time::Duration;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_create_posts_event_to_webhook() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/hooks/tasks"))
+            .respond_with(ResponseTemplate::new(204))
+            .mount(&server)
+            .await;
+
+        let service = TaskService::new(TaskStorage::in_memory(MemoryStorage::new()))
+            .with_webhook(Webhook::new(format!("{}/hooks/tasks", server.uri())));
+        let new_task = NewTask {
+            title: "Write release notes".to_string(),
+            ..NewTask::default()
+        };
+        let task = service.create_task(&new_task).await.unwrap();
+
+        // The POST is sent in the background, so wait for it to arrive
+        let mut requests = Vec::new();
+        for _ in 0..100 {
+            requests = server.received_requests().await.unwrap_or_default();
+            if !requests.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(requests.len(), 1);
+        let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body["operation"], "create");
+        assert_eq!(body["task_id"], task.id.as_str());
+    }
+}